@@ -0,0 +1,144 @@
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use network::auth::MOJANG_SESSION_SERVER;
+use network::bedrock_auth::{ChainVerifier, MOJANG_ROOT_KEY};
+
+/// Where the config is read from, relative to the working directory
+pub const CONFIG_PATH: &str = "phast.toml";
+
+/// Same as the vanilla server
+pub const DEFAULT_COMPRESSION_THRESHOLD: i32 = 256;
+pub const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+pub const DEFAULT_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Clients that aren't playing yet don't get KeepAlivePackets, so they're closed if they go quiet
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Settings read from `phast.toml`.
+/// Anything missing from the file is filled in with the default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Shown in the server list of both editions
+    pub motd: String,
+    pub max_players: u32,
+    /// Authenticates Java players with the session server & requires Bedrock players to be signed in to Xbox Live
+    pub online_mode: bool,
+    /// Java packets at least this big are compressed. Negative values disable compression.
+    pub compression_threshold: i32,
+    /// In chunks
+    pub view_distance: u32,
+    pub tick_rate: u32,
+    /// What to do with packets we can't read
+    pub unknown_packets: UnknownPacketPolicy,
+    pub java: JavaConfig,
+    pub bedrock: BedrockConfig,
+    pub timeouts: TimeoutConfig,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JavaConfig {
+    pub enabled: bool,
+    pub address: SocketAddr,
+    /// Used when `online_mode` is on
+    pub session_server: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BedrockConfig {
+    pub enabled: bool,
+    pub address: SocketAddr,
+    /// base64 DER key that login chains have to be signed by when `online_mode` is on
+    pub root_key: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownPacketPolicy {
+    /// Drop the packet
+    Skip,
+    /// Drop the packet & print it
+    Log,
+    /// Close the connection
+    Disconnect,
+}
+
+/// In seconds
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    pub keep_alive_interval: u64,
+    pub keep_alive_timeout: u64,
+    pub read_timeout: u64,
+}
+
+impl ServerConfig {
+    /// Reads the config at `path`, writing the defaults there first if it doesn't exist
+    pub fn load(path: &str) -> Result<ServerConfig, String> {
+        if !Path::new(path).exists() {
+            let config = ServerConfig::default();
+            let toml = toml::to_string_pretty(&config).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            fs::write(path, toml).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            println!("[Server] Wrote default config to {}", path);
+            return Ok(config);
+        }
+
+        let toml = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let config: ServerConfig = toml::from_str(&toml).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if ChainVerifier::new(&config.bedrock.root_key).is_none() {
+            return Err(format!("bedrock.root_key in {} isn't valid base64", path));
+        }
+        Ok(config)
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            motd: "§bphast §eserver".to_string(),
+            max_players: 20,
+            online_mode: true,
+            compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            view_distance: 10,
+            tick_rate: 20,
+            unknown_packets: UnknownPacketPolicy::Log,
+            java: JavaConfig::default(),
+            bedrock: BedrockConfig::default(),
+            timeouts: TimeoutConfig::default(),
+        }
+    }
+}
+
+impl Default for JavaConfig {
+    fn default() -> Self {
+        JavaConfig {
+            enabled: true,
+            address: "0.0.0.0:25565".parse().unwrap(),
+            session_server: MOJANG_SESSION_SERVER.to_string(),
+        }
+    }
+}
+
+impl Default for BedrockConfig {
+    fn default() -> Self {
+        BedrockConfig {
+            enabled: true,
+            address: "0.0.0.0:19132".parse().unwrap(),
+            root_key: MOJANG_ROOT_KEY.to_string(),
+        }
+    }
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        TimeoutConfig {
+            keep_alive_interval: DEFAULT_KEEP_ALIVE_INTERVAL.as_secs(),
+            keep_alive_timeout: DEFAULT_KEEP_ALIVE_TIMEOUT.as_secs(),
+            read_timeout: DEFAULT_READ_TIMEOUT.as_secs(),
+        }
+    }
+}
@@ -2,14 +2,44 @@
 
 extern crate concurrent_hashmap;
 extern crate paste;
+extern crate rand;
+extern crate rsa;
+extern crate aes;
+extern crate cfb8;
+extern crate flate2;
+extern crate sha1;
+extern crate md5;
+#[macro_use]
+extern crate serde_json;
+extern crate ureq;
+extern crate mio;
+extern crate p384;
+extern crate base64;
+extern crate ctr;
+extern crate sha2;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
 
+mod config;
+mod status;
 mod server;
 mod network;
+use config::*;
 use server::*;
 use network::*;
 
 fn main() {
-    let mut server = Server::new();
+    let config = match ServerConfig::load(CONFIG_PATH) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("[Server] {}", e);
+            return;
+        }
+    };
+
+    let mut server = Server::new(config);
     server.start();
     server.join_network_threads();
 }
@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use sha1::{Digest, Sha1};
+use serde_json::Value;
+
+use network::types::UUID;
+
+/// Where vanilla servers check if a player has joined
+pub const MOJANG_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+/// A session server that doesn't answer in time fails the login instead of holding up an auth thread
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A player's identity after they've been authenticated
+#[derive(Clone, Debug)]
+pub struct GameProfile {
+    pub uuid: UUID,
+    pub name: String,
+    /// Signed textures from the session server. Offline players don't have any.
+    pub properties: Vec<ProfileProperty>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+impl GameProfile {
+    /// The UUID with hyphens, which is what the `LoginSuccessPacket` expects
+    pub fn hyphenated_uuid(&self) -> String {
+        self.uuid.hyphenated()
+    }
+}
+
+/// Decides who a player is once encryption has been enabled.
+/// Implementations may block, so this is called off the game loop thread.
+pub trait Authenticator: Send + Sync {
+    /// `server_hash` is the signed SHA-1 hash computed by `server_hash`.
+    /// Returns `None` if the player couldn't be authenticated.
+    fn authenticate(&self, username: &str, server_hash: &str) -> Option<GameProfile>;
+}
+
+/// Trusts whatever username the client sends
+pub struct OfflineAuthenticator;
+
+impl Authenticator for OfflineAuthenticator {
+    fn authenticate(&self, username: &str, _server_hash: &str) -> Option<GameProfile> {
+        Some(GameProfile {
+            uuid: UUID::offline(username),
+            name: username.to_string(),
+            properties: vec![],
+        })
+    }
+}
+
+/// Asks a session server if the player has joined using the `hasJoined` endpoint.
+/// The base url can be changed to point at something other than Mojang's server.
+pub struct SessionServerAuthenticator {
+    pub base_url: String,
+    agent: ureq::Agent,
+}
+
+impl SessionServerAuthenticator {
+    pub fn new(base_url: &str) -> SessionServerAuthenticator {
+        SessionServerAuthenticator {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(CONNECT_TIMEOUT)
+                .timeout(REQUEST_TIMEOUT)
+                .build(),
+        }
+    }
+}
+
+impl Default for SessionServerAuthenticator {
+    fn default() -> Self {
+        SessionServerAuthenticator::new(MOJANG_SESSION_SERVER)
+    }
+}
+
+impl Authenticator for SessionServerAuthenticator {
+    fn authenticate(&self, username: &str, server_hash: &str) -> Option<GameProfile> {
+        let url = format!("{}/session/minecraft/hasJoined", self.base_url);
+        let response = match self.agent.get(&url)
+            .query("username", username)
+            .query("serverId", server_hash)
+            .call() {
+            Ok(response) => response,
+            Err(e) => {
+                println!("[Auth] Session server request failed: {}", e);
+                return None;
+            }
+        };
+
+        // the session server responds with 204 No Content if the player hasn't joined
+        if response.status() != 200 {
+            return None;
+        }
+
+        let body = response.into_string().ok()?;
+        let json: Value = serde_json::from_str(&body).ok()?;
+        let uuid = UUID::parse(json["id"].as_str()?)?;
+        let name = json["name"].as_str()?.to_string();
+
+        let properties = match json["properties"].as_array() {
+            Some(properties) => properties.iter().filter_map(|property| {
+                Some(ProfileProperty {
+                    name: property["name"].as_str()?.to_string(),
+                    value: property["value"].as_str()?.to_string(),
+                    signature: property["signature"].as_str().map(|s| s.to_string()),
+                })
+            }).collect(),
+            None => vec![]
+        };
+
+        Some(GameProfile {
+            uuid,
+            name,
+            properties,
+        })
+    }
+}
+
+/// Computes the hash sent to the session server.
+/// Minecraft formats the SHA-1 digest as a signed big-endian number in hex, so
+/// negative digests are written in two's complement with a leading `-`.
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(server_id.as_bytes());
+    hasher.update(shared_secret);
+    hasher.update(public_key);
+    let mut digest = hasher.finalize();
+
+    let negative = digest[0] & 0x80 == 0x80;
+    if negative {
+        // two's complement
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                let (value, overflow) = byte.overflowing_add(1);
+                *byte = value;
+                carry = overflow;
+            }
+        }
+    }
+
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    let hex = hex.trim_start_matches('0');
+    if negative {
+        format!("-{}", hex)
+    } else {
+        hex.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    /// Answers one request on a local port with `status` & `body`, returning the session server's url
+    /// & a handle that gives back the request line
+    fn session_server(status: &'static str, body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let length = stream.read(&mut buf).unwrap();
+                if length == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..length]);
+            }
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body).unwrap();
+            String::from_utf8_lossy(&request).lines().next().unwrap_or("").to_string()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn session_server_profile() {
+        let (url, server) = session_server("200 OK", r#"{
+            "id": "069a79f444e94726a5befca90e38aaf5",
+            "name": "Notch",
+            "properties": [{"name": "textures", "value": "e30=", "signature": "c2ln"}]
+        }"#);
+        let profile = SessionServerAuthenticator::new(&url).authenticate("Notch", "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1").unwrap();
+        assert_eq!(profile.name, "Notch");
+        assert_eq!(profile.hyphenated_uuid(), "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        assert_eq!(profile.properties.len(), 1);
+        assert_eq!(profile.properties[0].name, "textures");
+        assert_eq!(profile.properties[0].signature, Some("c2ln".to_string()));
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /session/minecraft/hasJoined?"));
+        assert!(request.contains("username=Notch"));
+        assert!(request.contains("serverId=-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"));
+    }
+
+    #[test]
+    fn session_server_not_joined() {
+        let (url, server) = session_server("204 No Content", "");
+        assert!(SessionServerAuthenticator::new(&url).authenticate("Notch", "abc").is_none());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn session_server_malformed_body() {
+        let (url, server) = session_server("200 OK", r#"{"id": "not a uuid", "name": "#);
+        assert!(SessionServerAuthenticator::new(&url).authenticate("Notch", "abc").is_none());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn server_hash_matches_published_vectors() {
+        assert_eq!(server_hash("Notch", &[], &[]), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
+        assert_eq!(server_hash("jeb_", &[], &[]), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+        assert_eq!(server_hash("simon", &[], &[]), "88e16a1019277b15d58faf0541e11910eb756f6");
+    }
+
+    #[test]
+    fn offline_uuid() {
+        let profile = OfflineAuthenticator.authenticate("Notch", "").unwrap();
+        assert_eq!(profile.uuid, UUID::offline("Notch"));
+        assert_eq!(profile.hyphenated_uuid(), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+    }
+}
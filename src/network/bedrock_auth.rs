@@ -0,0 +1,385 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use p384::ecdsa::{Signature, VerifyingKey};
+use p384::ecdsa::signature::Verifier;
+use p384::pkcs8::DecodePublicKey;
+use serde_json::Value;
+
+use network::auth::*;
+use network::types::UUID;
+
+/// The key Mojang signs the first token of Xbox Live authenticated chains with (base64 DER)
+pub const MOJANG_ROOT_KEY: &str = "MHYwEAYHKoZIzj0CAQYFK4EEACIDYgAE8ELkixyLcwlZryUQcu1TvPOmI2B7vX83ndnWRUaXm74wFfa5f/lwQNTfrLVHa2PmenpGI6JhIMUJaWZrjmMj90NoKNFSNBuKdm8rYiXsfaz3K36x/1U26HpG0ZxK/V1V";
+
+/// How far off the client's clock can be when checking `exp` & `nbf`
+const CLOCK_LEEWAY: u64 = 60;
+
+/// Who a Bedrock player is, taken from the `LoginPacket`
+#[derive(Clone, Debug)]
+pub struct BedrockIdentity {
+    pub display_name: String,
+    /// Only set for players authenticated by Xbox Live
+    pub xuid: Option<String>,
+    pub uuid: UUID,
+    /// The client's public key (base64 DER). Used to set up encryption.
+    pub public_key: String,
+    /// Whether the chain was signed by the root key
+    pub authenticated: bool,
+    pub skin: BedrockSkin,
+}
+
+impl BedrockIdentity {
+    /// So Bedrock players can be treated like Java players
+    pub fn profile(&self) -> GameProfile {
+        GameProfile {
+            uuid: self.uuid,
+            name: self.display_name.clone(),
+            properties: vec![],
+        }
+    }
+}
+
+/// Skin data from the client data token
+#[derive(Clone, Debug, Default)]
+pub struct BedrockSkin {
+    pub id: String,
+    /// RGBA pixels
+    pub data: Vec<u8>,
+    pub cape_data: Vec<u8>,
+    pub geometry_name: String,
+    /// Geometry JSON
+    pub geometry: Vec<u8>,
+}
+
+/// Checks the chain of JWTs in the `LoginPacket`.
+/// Each token is signed by the key in the token before it, and the first one is signed by its own `x5u` key.
+pub struct ChainVerifier {
+    /// DER encoded key that authenticated chains have to start from.
+    /// If this is `None`, chains that aren't signed by anyone (offline players) are accepted.
+    pub root_key: Option<Vec<u8>>,
+}
+
+impl ChainVerifier {
+    /// Returns `None` if the root key isn't valid base64
+    pub fn new(root_key: &str) -> Option<ChainVerifier> {
+        Some(ChainVerifier {
+            root_key: Some(base64::decode(root_key).ok()?),
+        })
+    }
+
+    /// Accepts any chain that has valid signatures
+    pub fn offline() -> ChainVerifier {
+        ChainVerifier {
+            root_key: None,
+        }
+    }
+
+    /// Reads & verifies the payload of a `LoginPacket`.
+    /// Returns `None` if a signature is invalid, a token has expired, or the chain isn't signed by the root key.
+    pub fn verify(&self, payload: &[u8]) -> Option<BedrockIdentity> {
+        let (chain, client_data) = read_login_payload(payload)?;
+
+        let mut authenticated = false;
+        let mut key: Option<String> = None;
+        let mut identity: Option<(String, Option<String>, UUID)> = None;
+        for token in chain.iter() {
+            let jwt = Jwt::decode(token)?;
+            let x5u = jwt.header["x5u"].as_str()?.to_string();
+            // the first token is self signed, the rest have to be signed by the key before them
+            let signer = match key {
+                Some(ref key) if *key != x5u => return None,
+                Some(key) => key,
+                None => x5u
+            };
+            let der = base64::decode(&signer).ok()?;
+            if !jwt.verify(&der) || !jwt.is_current() {
+                return None;
+            }
+            if let Some(ref root_key) = self.root_key {
+                if der == *root_key {
+                    authenticated = true;
+                }
+            }
+
+            // anything before the root key could have been made by anyone
+            let extra_data = &jwt.payload["extraData"];
+            if extra_data.is_object() && (authenticated || self.root_key.is_none()) {
+                let display_name = extra_data["displayName"].as_str()?.to_string();
+                let xuid = match extra_data["XUID"].as_str() {
+                    Some(xuid) if !xuid.is_empty() => Some(xuid.to_string()),
+                    _ => None
+                };
+                let uuid = UUID::parse(extra_data["identity"].as_str()?)?;
+                identity = Some((display_name, xuid, uuid));
+            }
+
+            key = Some(jwt.payload["identityPublicKey"].as_str()?.to_string());
+        }
+
+        if self.root_key.is_some() && !authenticated {
+            return None;
+        }
+        let (display_name, xuid, uuid) = identity?;
+        let public_key = key?;
+
+        // the client data is signed by the client's key
+        let client_data = Jwt::decode(&client_data)?;
+        if !client_data.verify(&base64::decode(&public_key).ok()?) {
+            return None;
+        }
+
+        let data = &client_data.payload;
+        let skin = BedrockSkin {
+            id: data["SkinId"].as_str().unwrap_or_default().to_string(),
+            data: decode_field(&data["SkinData"]),
+            cape_data: decode_field(&data["CapeData"]),
+            geometry_name: data["SkinGeometryName"].as_str().unwrap_or_default().to_string(),
+            geometry: decode_field(&data["SkinGeometry"]),
+        };
+
+        Some(BedrockIdentity {
+            display_name,
+            xuid: if authenticated { xuid } else { None },
+            uuid,
+            public_key,
+            authenticated,
+            skin,
+        })
+    }
+}
+
+impl Default for ChainVerifier {
+    fn default() -> Self {
+        ChainVerifier::new(MOJANG_ROOT_KEY).unwrap()
+    }
+}
+
+/// Splits the payload of a `LoginPacket` into the chain tokens & the client data token.
+/// Both are prefixed by a little endian i32 length.
+pub fn read_login_payload(payload: &[u8]) -> Option<(Vec<String>, String)> {
+    let mut index = 0;
+    let chain = read_le_string(payload, &mut index)?;
+    let client_data = read_le_string(payload, &mut index)?;
+
+    let chain: Value = serde_json::from_str(&chain).ok()?;
+    let chain = chain["chain"].as_array()?
+        .iter()
+        .map(|token| token.as_str().map(|s| s.to_string()))
+        .collect::<Option<Vec<String>>>()?;
+    Some((chain, client_data))
+}
+
+fn read_le_string(bytes: &[u8], index: &mut usize) -> Option<String> {
+    if bytes.len() < *index + 4 {
+        return None;
+    }
+    let mut length = [0u8; 4];
+    length.copy_from_slice(&bytes[*index..(*index + 4)]);
+    let length = i32::from_le_bytes(length);
+    *index += 4;
+    if length < 0 || bytes.len() < *index + length as usize {
+        return None;
+    }
+    let string = String::from_utf8((&bytes[*index..(*index + length as usize)]).to_vec()).ok()?;
+    *index += length as usize;
+    Some(string)
+}
+
+/// Client data fields are base64, missing or invalid ones are left empty
+fn decode_field(value: &Value) -> Vec<u8> {
+    value.as_str()
+        .and_then(|s| base64::decode(s).ok())
+        .unwrap_or_default()
+}
+
+/// A decoded (not verified) JSON web token
+struct Jwt {
+    header: Value,
+    payload: Value,
+    /// `header.payload`, which is what the signature is over
+    signed: String,
+    signature: Vec<u8>,
+}
+
+impl Jwt {
+    fn decode(token: &str) -> Option<Jwt> {
+        let mut parts = token.split('.');
+        let header = parts.next()?;
+        let payload = parts.next()?;
+        let signature = parts.next()?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Jwt {
+            header: decode_json(header)?,
+            payload: decode_json(payload)?,
+            signed: format!("{}.{}", header, payload),
+            signature: base64::decode_config(signature, base64::URL_SAFE_NO_PAD).ok()?,
+        })
+    }
+
+    /// Checks the ES384 signature with a DER encoded public key
+    fn verify(&self, key: &[u8]) -> bool {
+        if self.header["alg"].as_str() != Some("ES384") {
+            return false;
+        }
+        let key = match VerifyingKey::from_public_key_der(key) {
+            Ok(key) => key,
+            Err(_) => return false
+        };
+        // JWTs use the raw r & s values instead of DER
+        let signature = match Signature::from_slice(&self.signature) {
+            Ok(signature) => signature,
+            Err(_) => return false
+        };
+        key.verify(self.signed.as_bytes(), &signature).is_ok()
+    }
+
+    /// Checks `exp` & `nbf` if they're there
+    fn is_current(&self) -> bool {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        if let Some(exp) = self.payload["exp"].as_u64() {
+            if exp.saturating_add(CLOCK_LEEWAY) < now {
+                return false;
+            }
+        }
+        if let Some(nbf) = self.payload["nbf"].as_u64() {
+            if nbf > now.saturating_add(CLOCK_LEEWAY) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn decode_json(part: &str) -> Option<Value> {
+    let bytes = base64::decode_config(part, base64::URL_SAFE_NO_PAD).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p384::SecretKey;
+    use p384::ecdsa::{Signature, SigningKey};
+    use p384::ecdsa::signature::Signer;
+    use p384::pkcs8::EncodePublicKey;
+
+    const IDENTITY: &str = "8a5a4a8e-4d6a-3c6e-9c1f-3e2b1d0c9a8b";
+
+    /// Returns the key & its base64 DER public key
+    fn generate_key() -> (SecretKey, String) {
+        let key = SecretKey::random(&mut rand::thread_rng());
+        let der = key.public_key().to_public_key_der().unwrap();
+        (key, base64::encode(der.as_bytes()))
+    }
+
+    fn sign(key: &SecretKey, x5u: &str, payload: Value) -> String {
+        let header = json!({ "alg": "ES384", "x5u": x5u });
+        let signed = format!("{}.{}",
+            base64::encode_config(header.to_string(), base64::URL_SAFE_NO_PAD),
+            base64::encode_config(payload.to_string(), base64::URL_SAFE_NO_PAD));
+        let signature: Signature = SigningKey::from(key).sign(signed.as_bytes());
+        format!("{}.{}", signed, base64::encode_config(signature.to_bytes(), base64::URL_SAFE_NO_PAD))
+    }
+
+    fn login_payload(chain: &[String], client_data: &str) -> Vec<u8> {
+        let chain = json!({ "chain": chain }).to_string();
+        let mut payload = Vec::new();
+        for string in [chain.as_str(), client_data].iter() {
+            payload.extend_from_slice(&(string.len() as i32).to_le_bytes());
+            payload.extend_from_slice(string.as_bytes());
+        }
+        payload
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn identity_payload(public_key: &str, exp: u64) -> Value {
+        json!({
+            "extraData": { "displayName": "Steve", "XUID": "1234", "identity": IDENTITY },
+            "identityPublicKey": public_key,
+            "nbf": now() - 60,
+            "exp": exp,
+        })
+    }
+
+    /// A self signed token handing off to the root key, then the identity signed by the root key
+    fn signed_chain(client: &(SecretKey, String), root: &(SecretKey, String), exp: u64) -> Vec<u8> {
+        let chain = vec![
+            sign(&client.0, &client.1, json!({ "identityPublicKey": root.1, "certificateAuthority": true })),
+            sign(&root.0, &root.1, identity_payload(&client.1, exp)),
+        ];
+        let client_data = sign(&client.0, &client.1, json!({ "SkinId": "Standard_Custom" }));
+        login_payload(&chain, &client_data)
+    }
+
+    #[test]
+    fn accepts_chain_signed_by_root_key() {
+        let (client, root) = (generate_key(), generate_key());
+        let verifier = ChainVerifier::new(&root.1).unwrap();
+        let identity = verifier.verify(&signed_chain(&client, &root, now() + 3600)).unwrap();
+        assert!(identity.authenticated);
+        assert_eq!(identity.display_name, "Steve");
+        assert_eq!(identity.xuid, Some("1234".to_string()));
+        assert_eq!(identity.uuid, UUID::parse(IDENTITY).unwrap());
+        assert_eq!(identity.public_key, client.1);
+        assert_eq!(identity.skin.id, "Standard_Custom");
+    }
+
+    #[test]
+    fn rejects_bad_signature() {
+        let (client, root, other) = (generate_key(), generate_key(), generate_key());
+        let chain = vec![
+            sign(&client.0, &client.1, json!({ "identityPublicKey": root.1 })),
+            // claims to be from the root key but isn't signed by it
+            sign(&other.0, &root.1, identity_payload(&client.1, now() + 3600)),
+        ];
+        let client_data = sign(&client.0, &client.1, json!({}));
+        let verifier = ChainVerifier::new(&root.1).unwrap();
+        assert!(verifier.verify(&login_payload(&chain, &client_data)).is_none());
+    }
+
+    #[test]
+    fn rejects_broken_x5u_link() {
+        let (client, root, other) = (generate_key(), generate_key(), generate_key());
+        let chain = vec![
+            sign(&client.0, &client.1, json!({ "identityPublicKey": root.1 })),
+            // validly signed, but not by the key the previous token handed off to
+            sign(&other.0, &other.1, identity_payload(&client.1, now() + 3600)),
+        ];
+        let client_data = sign(&client.0, &client.1, json!({}));
+        assert!(ChainVerifier::offline().verify(&login_payload(&chain, &client_data)).is_none());
+    }
+
+    #[test]
+    fn rejects_expired_chain() {
+        let (client, root) = (generate_key(), generate_key());
+        let verifier = ChainVerifier::new(&root.1).unwrap();
+        assert!(verifier.verify(&signed_chain(&client, &root, now() - 3600)).is_none());
+    }
+
+    #[test]
+    fn huge_expiry_does_not_overflow() {
+        let (client, root) = (generate_key(), generate_key());
+        let verifier = ChainVerifier::new(&root.1).unwrap();
+        assert!(verifier.verify(&signed_chain(&client, &root, u64::max_value())).is_some());
+    }
+
+    #[test]
+    fn rejects_unauthenticated_chain_when_root_key_is_set() {
+        let (client, root) = (generate_key(), generate_key());
+        // an offline player signs their own identity
+        let chain = vec![sign(&client.0, &client.1, identity_payload(&client.1, now() + 3600))];
+        let client_data = sign(&client.0, &client.1, json!({}));
+        let payload = login_payload(&chain, &client_data);
+
+        assert!(ChainVerifier::new(&root.1).unwrap().verify(&payload).is_none());
+        let identity = ChainVerifier::offline().verify(&payload).unwrap();
+        assert!(!identity.authenticated);
+        assert_eq!(identity.xuid, None);
+    }
+}
@@ -0,0 +1,607 @@
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
+use serde_json::{Map, Value};
+
+use network::packet::*;
+use network::types::VarIntLengthPrefixedString;
+use network::protocol::java::v1_14::ProtocolJava_1_14_Id;
+
+/// Java clients from this version on (1.16) understand hex colors & `contents` in hover events
+pub const HEX_COLOR_PROTOCOL_VERSION: i32 = 735;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Color {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    /// 0xRRGGBB, only shown by 1.16+ clients
+    Hex(u32),
+}
+
+/// The named colors with their name, legacy code & RGB value
+const NAMED_COLORS: [(Color, &str, char, u32); 16] = [
+    (Color::Black, "black", '0', 0x000000),
+    (Color::DarkBlue, "dark_blue", '1', 0x0000AA),
+    (Color::DarkGreen, "dark_green", '2', 0x00AA00),
+    (Color::DarkAqua, "dark_aqua", '3', 0x00AAAA),
+    (Color::DarkRed, "dark_red", '4', 0xAA0000),
+    (Color::DarkPurple, "dark_purple", '5', 0xAA00AA),
+    (Color::Gold, "gold", '6', 0xFFAA00),
+    (Color::Gray, "gray", '7', 0xAAAAAA),
+    (Color::DarkGray, "dark_gray", '8', 0x555555),
+    (Color::Blue, "blue", '9', 0x5555FF),
+    (Color::Green, "green", 'a', 0x55FF55),
+    (Color::Aqua, "aqua", 'b', 0x55FFFF),
+    (Color::Red, "red", 'c', 0xFF5555),
+    (Color::LightPurple, "light_purple", 'd', 0xFF55FF),
+    (Color::Yellow, "yellow", 'e', 0xFFFF55),
+    (Color::White, "white", 'f', 0xFFFFFF),
+];
+
+impl Color {
+    /// Reads a named color or `#RRGGBB`
+    pub fn from_name(name: &str) -> Option<Color> {
+        if name.starts_with('#') && name.len() == 7 {
+            return u32::from_str_radix(&name[1..], 16).ok().map(Color::Hex);
+        }
+        NAMED_COLORS.iter().find(|c| c.1 == name).map(|c| c.0)
+    }
+
+    /// Reads the character after a `§`
+    pub fn from_code(code: char) -> Option<Color> {
+        let code = code.to_ascii_lowercase();
+        NAMED_COLORS.iter().find(|c| c.2 == code).map(|c| c.0)
+    }
+
+    pub fn rgb(&self) -> u32 {
+        match self {
+            Color::Hex(rgb) => *rgb,
+            color => NAMED_COLORS.iter().find(|c| c.0 == *color).unwrap().3
+        }
+    }
+
+    /// The closest named color, for clients & text that don't have hex colors
+    pub fn to_named(&self) -> Color {
+        let rgb = self.rgb();
+        let distance = |other: u32| {
+            let channel = |shift: u32| ((rgb >> shift) & 0xFF) as i32 - ((other >> shift) & 0xFF) as i32;
+            channel(16).pow(2) + channel(8).pow(2) + channel(0).pow(2)
+        };
+        NAMED_COLORS.iter().min_by_key(|c| distance(c.3)).unwrap().0
+    }
+
+    pub fn code(&self) -> char {
+        let named = self.to_named();
+        NAMED_COLORS.iter().find(|c| c.0 == named).unwrap().2
+    }
+
+    /// Hex colors are changed to the closest named color unless `hex_colors` is set
+    pub fn name(&self, hex_colors: bool) -> String {
+        match self {
+            Color::Hex(rgb) if hex_colors => format!("#{:06X}", rgb),
+            _ => {
+                let named = self.to_named();
+                NAMED_COLORS.iter().find(|c| c.0 == named).unwrap().1.to_string()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClickAction {
+    OpenUrl,
+    RunCommand,
+    SuggestCommand,
+    ChangePage,
+    /// 1.15+
+    CopyToClipboard,
+}
+
+const CLICK_ACTIONS: [(ClickAction, &str); 5] = [
+    (ClickAction::OpenUrl, "open_url"),
+    (ClickAction::RunCommand, "run_command"),
+    (ClickAction::SuggestCommand, "suggest_command"),
+    (ClickAction::ChangePage, "change_page"),
+    (ClickAction::CopyToClipboard, "copy_to_clipboard"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClickEvent {
+    pub action: ClickAction,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HoverEvent {
+    ShowText(Box<TextComponent>),
+    /// The item as SNBT
+    ShowItem(String),
+    /// The entity as SNBT
+    ShowEntity(String),
+}
+
+/// Styles that children inherit from their parent
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Style {
+    color: Option<Color>,
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    strikethrough: bool,
+    obfuscated: bool,
+}
+
+impl Style {
+    fn inherit(&self, component: &TextComponent) -> Style {
+        Style {
+            color: component.color.or(self.color),
+            bold: component.bold.unwrap_or(self.bold),
+            italic: component.italic.unwrap_or(self.italic),
+            underlined: component.underlined.unwrap_or(self.underlined),
+            strikethrough: component.strikethrough.unwrap_or(self.strikethrough),
+            obfuscated: component.obfuscated.unwrap_or(self.obfuscated),
+        }
+    }
+
+    /// The codes that switch to this style. A color code also resets the formatting.
+    fn to_legacy(&self) -> String {
+        let mut codes = match self.color {
+            Some(color) => format!("§{}", color.code()),
+            None => "§r".to_string()
+        };
+        for &(set, code) in [(self.obfuscated, 'k'), (self.bold, 'l'), (self.strikethrough, 'm'),
+            (self.underlined, 'n'), (self.italic, 'o')].iter() {
+            if set {
+                codes.push('§');
+                codes.push(code);
+            }
+        }
+        codes
+    }
+}
+
+/// A chat message. This is what Java clients use for chat, disconnect reasons & the MOTD.
+/// Styles that aren't set (`None`) are inherited from the parent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextComponent {
+    pub text: String,
+    /// Translation key that's used instead of `text` if it's set
+    pub translate: Option<String>,
+    /// Arguments for `translate`
+    pub with: Vec<TextComponent>,
+    pub color: Option<Color>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub underlined: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub obfuscated: Option<bool>,
+    /// Put in the chat box when the text is shift clicked
+    pub insertion: Option<String>,
+    pub click_event: Option<ClickEvent>,
+    pub hover_event: Option<HoverEvent>,
+    pub extra: Vec<TextComponent>,
+}
+
+impl TextComponent {
+    pub fn text(text: &str) -> TextComponent {
+        TextComponent {
+            text: text.to_string(),
+            ..TextComponent::default()
+        }
+    }
+
+    pub fn translate(key: &str, with: Vec<TextComponent>) -> TextComponent {
+        TextComponent {
+            translate: Some(key.to_string()),
+            with,
+            ..TextComponent::default()
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.bold = Some(bold);
+        self
+    }
+
+    pub fn italic(mut self, italic: bool) -> Self {
+        self.italic = Some(italic);
+        self
+    }
+
+    pub fn underlined(mut self, underlined: bool) -> Self {
+        self.underlined = Some(underlined);
+        self
+    }
+
+    pub fn strikethrough(mut self, strikethrough: bool) -> Self {
+        self.strikethrough = Some(strikethrough);
+        self
+    }
+
+    pub fn obfuscated(mut self, obfuscated: bool) -> Self {
+        self.obfuscated = Some(obfuscated);
+        self
+    }
+
+    pub fn click(mut self, action: ClickAction, value: &str) -> Self {
+        self.click_event = Some(ClickEvent {
+            action,
+            value: value.to_string(),
+        });
+        self
+    }
+
+    pub fn hover(mut self, hover_event: HoverEvent) -> Self {
+        self.hover_event = Some(hover_event);
+        self
+    }
+
+    pub fn append(mut self, child: TextComponent) -> Self {
+        self.extra.push(child);
+        self
+    }
+
+    /// JSON for a client using `protocol_version`
+    pub fn to_json(&self, protocol_version: i32) -> String {
+        self.to_value(protocol_version >= HEX_COLOR_PROTOCOL_VERSION).to_string()
+    }
+
+    /// 1.16 added hex colors & changed hover events to use `contents`
+    pub fn to_value(&self, hex_colors: bool) -> Value {
+        let mut map = Map::new();
+        match self.translate {
+            Some(ref key) => {
+                map.insert("translate".to_string(), Value::from(key.as_str()));
+                if !self.with.is_empty() {
+                    map.insert("with".to_string(), Value::Array(self.with.iter().map(|c| c.to_value(hex_colors)).collect()));
+                }
+            }
+            None => {
+                map.insert("text".to_string(), Value::from(self.text.as_str()));
+            }
+        }
+
+        if let Some(color) = self.color {
+            map.insert("color".to_string(), Value::from(color.name(hex_colors)));
+        }
+        for &(name, value) in [("bold", self.bold), ("italic", self.italic), ("underlined", self.underlined),
+            ("strikethrough", self.strikethrough), ("obfuscated", self.obfuscated)].iter() {
+            if let Some(value) = value {
+                map.insert(name.to_string(), Value::from(value));
+            }
+        }
+        if let Some(ref insertion) = self.insertion {
+            map.insert("insertion".to_string(), Value::from(insertion.as_str()));
+        }
+
+        if let Some(ref click_event) = self.click_event {
+            let action = CLICK_ACTIONS.iter().find(|a| a.0 == click_event.action).unwrap().1;
+            map.insert("clickEvent".to_string(), json!({
+                "action": action,
+                "value": click_event.value,
+            }));
+        }
+        if let Some(ref hover_event) = self.hover_event {
+            let hover = match hover_event {
+                HoverEvent::ShowText(text) if hex_colors => json!({
+                    "action": "show_text",
+                    "contents": text.to_value(hex_colors),
+                }),
+                HoverEvent::ShowText(text) => json!({
+                    "action": "show_text",
+                    "value": text.to_value(hex_colors),
+                }),
+                // newer clients still read `value` for these
+                HoverEvent::ShowItem(item) => json!({
+                    "action": "show_item",
+                    "value": item,
+                }),
+                HoverEvent::ShowEntity(entity) => json!({
+                    "action": "show_entity",
+                    "value": entity,
+                }),
+            };
+            map.insert("hoverEvent".to_string(), hover);
+        }
+
+        if !self.extra.is_empty() {
+            map.insert("extra".to_string(), Value::Array(self.extra.iter().map(|c| c.to_value(hex_colors)).collect()));
+        }
+        Value::Object(map)
+    }
+
+    /// Reads a component from JSON. Plain strings & arrays are components too.
+    pub fn from_value(value: &Value) -> Option<TextComponent> {
+        match value {
+            Value::String(text) => Some(TextComponent::text(text)),
+            Value::Number(_) | Value::Bool(_) => Some(TextComponent::text(&value.to_string())),
+            // the first element is the parent of the rest
+            Value::Array(values) => {
+                let mut component = TextComponent::from_value(values.first()?)?;
+                for value in values.iter().skip(1) {
+                    component.extra.push(TextComponent::from_value(value)?);
+                }
+                Some(component)
+            }
+            Value::Object(map) => {
+                let mut component = TextComponent::text(map.get("text").and_then(|t| t.as_str()).unwrap_or(""));
+                component.translate = map.get("translate").and_then(|t| t.as_str()).map(|t| t.to_string());
+                if let Some(with) = map.get("with").and_then(|w| w.as_array()) {
+                    component.with = with.iter().map(TextComponent::from_value).collect::<Option<Vec<_>>>()?;
+                }
+                component.color = map.get("color").and_then(|c| c.as_str()).and_then(Color::from_name);
+                component.bold = map.get("bold").and_then(|b| b.as_bool());
+                component.italic = map.get("italic").and_then(|b| b.as_bool());
+                component.underlined = map.get("underlined").and_then(|b| b.as_bool());
+                component.strikethrough = map.get("strikethrough").and_then(|b| b.as_bool());
+                component.obfuscated = map.get("obfuscated").and_then(|b| b.as_bool());
+                component.insertion = map.get("insertion").and_then(|i| i.as_str()).map(|i| i.to_string());
+
+                if let Some(click_event) = map.get("clickEvent") {
+                    let action = click_event["action"].as_str()?;
+                    component.click_event = Some(ClickEvent {
+                        action: CLICK_ACTIONS.iter().find(|a| a.1 == action)?.0,
+                        value: click_event["value"].as_str()?.to_string(),
+                    });
+                }
+                if let Some(hover_event) = map.get("hoverEvent") {
+                    let value = match hover_event.get("contents") {
+                        Some(contents) => contents,
+                        None => &hover_event["value"]
+                    };
+                    component.hover_event = match hover_event["action"].as_str()? {
+                        "show_text" => Some(HoverEvent::ShowText(Box::new(TextComponent::from_value(value)?))),
+                        "show_item" => Some(HoverEvent::ShowItem(value.as_str().map(|s| s.to_string()).unwrap_or_else(|| value.to_string()))),
+                        "show_entity" => Some(HoverEvent::ShowEntity(value.as_str().map(|s| s.to_string()).unwrap_or_else(|| value.to_string()))),
+                        _ => None
+                    };
+                }
+
+                if let Some(extra) = map.get("extra").and_then(|e| e.as_array()) {
+                    component.extra = extra.iter().map(TextComponent::from_value).collect::<Option<Vec<_>>>()?;
+                }
+                Some(component)
+            }
+            Value::Null => None
+        }
+    }
+
+    /// Text with `§` codes, for Bedrock clients & legacy pings
+    pub fn to_legacy(&self) -> String {
+        let mut legacy = String::new();
+        let mut current = Style::default();
+        self.write_legacy(&Style::default(), &mut current, &mut legacy);
+        legacy
+    }
+
+    fn write_legacy(&self, parent: &Style, current: &mut Style, legacy: &mut String) {
+        let style = parent.inherit(self);
+        let text = match self.translate {
+            Some(ref key) => key.as_str(),
+            None => self.text.as_str()
+        };
+        if !text.is_empty() {
+            if style != *current {
+                legacy.push_str(&style.to_legacy());
+                *current = style.clone();
+            }
+            legacy.push_str(text);
+        }
+        for child in self.extra.iter() {
+            child.write_legacy(&style, current, legacy);
+        }
+    }
+
+    /// Just the text, without any formatting
+    pub fn to_plain(&self) -> String {
+        let mut plain = match self.translate {
+            Some(ref key) => key.clone(),
+            None => self.text.clone()
+        };
+        for child in self.extra.iter() {
+            plain.push_str(&child.to_plain());
+        }
+        plain
+    }
+
+    /// Reads text with `§` codes. Each run of text with the same style becomes a child.
+    pub fn from_legacy(legacy: &str) -> TextComponent {
+        let mut children = Vec::new();
+        let mut style = Style::default();
+        let mut text = String::new();
+
+        let mut chars = legacy.chars();
+        while let Some(c) = chars.next() {
+            if c != '§' {
+                text.push(c);
+                continue;
+            }
+            let code = match chars.next() {
+                Some(code) => code.to_ascii_lowercase(),
+                None => break
+            };
+
+            if !text.is_empty() {
+                children.push(TextComponent::styled(&text, &style));
+                text.clear();
+            }
+            match code {
+                // colors reset the formatting
+                _ if Color::from_code(code).is_some() => style = Style {
+                    color: Color::from_code(code),
+                    ..Style::default()
+                },
+                'k' => style.obfuscated = true,
+                'l' => style.bold = true,
+                'm' => style.strikethrough = true,
+                'n' => style.underlined = true,
+                'o' => style.italic = true,
+                'r' => style = Style::default(),
+                _ => {}
+            }
+        }
+        if !text.is_empty() {
+            children.push(TextComponent::styled(&text, &style));
+        }
+
+        if children.len() == 1 && children[0] == TextComponent::text(&children[0].text) {
+            return children.remove(0);
+        }
+        TextComponent {
+            extra: children,
+            ..TextComponent::default()
+        }
+    }
+
+    fn styled(text: &str, style: &Style) -> TextComponent {
+        let set = |value: bool| if value { Some(true) } else { None };
+        TextComponent {
+            text: text.to_string(),
+            color: style.color,
+            bold: set(style.bold),
+            italic: set(style.italic),
+            underlined: set(style.underlined),
+            strikethrough: set(style.strikethrough),
+            obfuscated: set(style.obfuscated),
+            ..TextComponent::default()
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TextComponent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        TextComponent::from_value(&value).ok_or_else(|| D::Error::custom("invalid text component"))
+    }
+}
+
+// Sent as a VarInt length prefixed JSON string
+
+impl ReadField for TextComponent {
+    fn read(bytes: &[u8], index: usize) -> Result<(TextComponent, usize), DecodeError> {
+        let (json, length) = <VarIntLengthPrefixedString as ReadField>::read(bytes, index)?;
+        let component = serde_json::from_str(&json.0).ok()
+            .and_then(|value: Value| TextComponent::from_value(&value))
+            .ok_or(DecodeError::UnknownVariant)?;
+        Ok((component, length))
+    }
+}
+
+impl TextComponent {
+    /// Writes the component as a packet field for a client using `protocol_version`
+    pub fn write_versioned(&self, buf: &mut Vec<u8>, protocol_version: i32) {
+        VarIntLengthPrefixedString(self.to_json(protocol_version)).write_to(buf);
+    }
+}
+
+// Packet fields are in the format of the newest protocol with a table, 1.16 packets will need
+// their own field type like `Position1_14`
+impl WriteField for TextComponent {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        self.write_versioned(buf, ProtocolJava_1_14_Id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_round_trip() {
+        let component = TextComponent::from_legacy("§6Hello §lworld§r!");
+        assert_eq!(component.extra, vec![
+            TextComponent::text("Hello ").color(Color::Gold),
+            TextComponent::text("world").color(Color::Gold).bold(true),
+            TextComponent::text("!"),
+        ]);
+        // a color code also resets the formatting, so it's written again before the bold
+        assert_eq!(component.to_legacy(), "§6Hello §6§lworld§r!");
+        assert_eq!(TextComponent::from_legacy(&component.to_legacy()), component);
+
+        assert_eq!(TextComponent::from_legacy("plain"), TextComponent::text("plain"));
+        assert_eq!(TextComponent::text("plain").to_legacy(), "plain");
+        // codes are case insensitive & a trailing § is dropped
+        assert_eq!(TextComponent::from_legacy("§Cred§").extra, vec![TextComponent::text("red").color(Color::Red)]);
+    }
+
+    #[test]
+    fn nearest_named_color() {
+        assert_eq!(Color::Hex(0xFE5656).to_named(), Color::Red);
+        assert_eq!(Color::Hex(0xFF0000).to_named(), Color::DarkRed);
+        assert_eq!(Color::Hex(0x000001).to_named(), Color::Black);
+        assert_eq!(Color::Hex(0xFFAA00).to_named(), Color::Gold);
+        assert_eq!(Color::Aqua.to_named(), Color::Aqua);
+
+        let hex = Color::Hex(0x123456);
+        assert_eq!(hex.code(), '8');
+        assert_eq!(hex.name(false), "dark_gray");
+        assert_eq!(hex.name(true), "#123456");
+        assert_eq!(Color::from_name("#123456"), Some(hex));
+        assert_eq!(Color::from_name("dark_gray"), Some(Color::DarkGray));
+    }
+
+    #[test]
+    fn nested_json() {
+        let tip = TextComponent::text("tip");
+        let component = TextComponent::text("a").color(Color::Gold).bold(true)
+            .append(TextComponent::text("b").italic(false).color(Color::Hex(0x123456))
+                .hover(HoverEvent::ShowText(Box::new(tip))));
+
+        assert_eq!(component.to_value(false), json!({
+            "text": "a",
+            "color": "gold",
+            "bold": true,
+            "extra": [{
+                "text": "b",
+                "color": "dark_gray",
+                "italic": false,
+                "hoverEvent": { "action": "show_text", "value": { "text": "tip" } },
+            }],
+        }));
+        let hex = json!({
+            "text": "a",
+            "color": "gold",
+            "bold": true,
+            "extra": [{
+                "text": "b",
+                "color": "#123456",
+                "italic": false,
+                "hoverEvent": { "action": "show_text", "contents": { "text": "tip" } },
+            }],
+        });
+        assert_eq!(component.to_value(true), hex);
+        assert_eq!(TextComponent::from_value(&hex), Some(component.clone()));
+
+        assert!(component.to_json(HEX_COLOR_PROTOCOL_VERSION - 1).contains("dark_gray"));
+        assert!(component.to_json(HEX_COLOR_PROTOCOL_VERSION).contains("#123456"));
+    }
+
+    #[test]
+    fn packet_field() {
+        let component = TextComponent::text("x").color(Color::Hex(0x123456));
+        let mut buf = Vec::new();
+        component.write_to(&mut buf);
+        let (read, length) = <TextComponent as ReadField>::read(&buf, 0).unwrap();
+        assert_eq!(length, buf.len());
+        // every packet table is older than 1.16
+        assert_eq!(read, TextComponent::text("x").color(Color::DarkGray));
+    }
+}
@@ -0,0 +1,45 @@
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+/// Packets that claim to inflate to more than this are rejected
+pub const MAX_DECOMPRESSED_SIZE: usize = 1 << 21;
+
+/// Compresses `bytes` with zlib
+pub fn compress(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::with_capacity(bytes.len() / 2), Compression::default());
+    // writing to a Vec can't fail
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Inflates zlib compressed `bytes`.
+/// Returns `None` if the data is malformed or inflates to more than `max_size` bytes.
+pub fn decompress(bytes: &[u8], max_size: usize) -> Option<Vec<u8>> {
+    let mut decoder = ZlibDecoder::new(bytes).take(max_size as u64 + 1);
+    let mut buf = Vec::with_capacity(bytes.len() * 2);
+    match decoder.read_to_end(&mut buf) {
+        Ok(_) if buf.len() <= max_size => Some(buf),
+        _ => None
+    }
+}
+
+/// Compresses `bytes` with gzip, which is used for files like `level.dat`
+pub fn compress_gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(bytes.len() / 2), Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Inflates gzip compressed `bytes`.
+/// Returns `None` if the data is malformed or inflates to more than `max_size` bytes.
+pub fn decompress_gzip(bytes: &[u8], max_size: usize) -> Option<Vec<u8>> {
+    let mut decoder = GzDecoder::new(bytes).take(max_size as u64 + 1);
+    let mut buf = Vec::with_capacity(bytes.len() * 2);
+    match decoder.read_to_end(&mut buf) {
+        Ok(_) if buf.len() <= max_size => Some(buf),
+        _ => None
+    }
+}
@@ -1,10 +1,17 @@
-use std::net::{TcpStream, UdpSocket, SocketAddr};
-use std::io::Write;
+use std::net::{UdpSocket, SocketAddr};
+use std::io::{ErrorKind, Write};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicIsize, Ordering};
+use std::time::{Duration, Instant};
 
 use network::types::*;
 use network::packet::*;
+use network::encryption::*;
+use network::compression::*;
+use network::reliability::*;
+use network::auth::GameProfile;
+use network::bedrock_auth::BedrockIdentity;
+
+use mio::net::TcpStream;
 use network::protocol::*;
 use network::protocol::bedrock::*;
 use network::protocol::java::*;
@@ -22,15 +29,44 @@ pub struct Connection {
     pub socket: SocketWrapper,
     pub protocol_state: State,
     pub protocol: Protocol,
+    // login
+    pub username: Option<String>,
+    pub verify_token: Vec<u8>,
+    pub shared_secret: Vec<u8>,
+    /// Set once the player has been authenticated
+    pub profile: Option<GameProfile>,
+    /// Set once a Bedrock player's login chain has been verified
+    pub bedrock_identity: Option<BedrockIdentity>,
+    cipher: Option<JavaCipher>,
+    bedrock_cipher: Option<BedrockCipher>,
+    compression_threshold: Option<usize>,
+    // bytes that are waiting for the socket to be writable
+    outgoing_buffer: Vec<u8>,
+    // timeouts
+    pub last_read: Instant,
+    /// The id of the KeepAlivePacket we're waiting on the client to send back
+    pub keep_alive_id: Option<i64>,
+    pub keep_alive_sent: Instant,
     // processing packets
     unprocessed_buffer: Vec<u8>,
-    datagram_sequence_id: AtomicIsize,
+    /// RakNet reliability state. Only used by Bedrock connections.
+    pub raknet: RakNetSession,
+    /// Set when the client sent something we can't recover from, so the connection should be dropped
+    closed: bool,
 }
 
+/// Vanilla won't read a frame length prefix longer than 3 bytes, which caps packets at 2 MiB
+pub const MAX_JAVA_PACKET_LENGTH: usize = MAX_PADDED_VAR_INT;
+
+/// A lone 0xFE may be the start of a 1.4 to 1.6 ping that was split across reads,
+/// so it's only answered as a Beta ping once nothing else has arrived for this long
+pub const BETA_PING_WAIT: Duration = Duration::from_millis(500);
+
 enum PacketResult {
     CompletePacket(Packet),
     NeedMoreData,
-    Skipped(usize),
+    /// The stream can't be read any further
+    Invalid(DecodeError),
 }
 
 use self::PacketResult::*;
@@ -50,11 +86,29 @@ impl Connection {
                 SocketWrapper::UDP(_) => Protocol::ProtocolBedrockRakNet(raknet::ProtocolBedrockRakNet),
             },
             socket,
+            username: None,
+            verify_token: vec![],
+            shared_secret: vec![],
+            profile: None,
+            bedrock_identity: None,
+            cipher: None,
+            bedrock_cipher: None,
+            compression_threshold: None,
+            outgoing_buffer: vec![],
+            last_read: Instant::now(),
+            keep_alive_id: None,
+            keep_alive_sent: Instant::now(),
             unprocessed_buffer: vec![],
-            datagram_sequence_id: AtomicIsize::new(42),
+            raknet: RakNetSession::new(),
+            closed: false,
         }
     }
 
+    /// Whether the client sent something that means the connection should be dropped
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
     pub fn is_tcp(&self) -> bool {
         match self.socket {
             SocketWrapper::TCP(_) => true,
@@ -69,9 +123,56 @@ impl Connection {
         }
     }
 
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// Switches the connection to AES/CFB8 using the shared secret the client sent us.
+    /// Returns false if the shared secret isn't valid.
+    pub fn enable_encryption(&mut self, shared_secret: &[u8]) -> bool {
+        match JavaCipher::new(shared_secret) {
+            Some(mut cipher) => {
+                // anything that came in after the EncryptionResponsePacket is already encrypted
+                cipher.decrypt(&mut self.unprocessed_buffer);
+                self.cipher = Some(cipher);
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Encrypts Bedrock batches from now on with the key derived in the handshake.
+    /// This should be called right after sending the `ServerToClientHandshakePacket`.
+    /// Returns false if the key isn't valid.
+    pub fn enable_bedrock_encryption(&mut self, key: &[u8]) -> bool {
+        match BedrockCipher::new(key, self.protocol.protocol_version()) {
+            Some(cipher) => {
+                self.bedrock_cipher = Some(cipher);
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Packets at least `threshold` bytes long will be compressed from now on.
+    /// This should be called right after sending the `SetCompressionPacket`.
+    pub fn enable_compression(&mut self, threshold: usize) {
+        self.compression_threshold = Some(threshold);
+    }
+
     // might need a lock so we only handle one read at a time
     pub fn handle_read(&mut self, bytes: &mut Vec<u8>) -> Vec<Packet> {
+        self.last_read = Instant::now();
+        if self.is_udp() {
+            let packets = self.read_datagram(bytes);
+            self.send_acks();
+            return packets;
+        }
+
         let mut packets: Vec<Packet> = Vec::with_capacity(1);
+        if let Some(ref mut cipher) = self.cipher {
+            cipher.decrypt(bytes);
+        }
         self.unprocessed_buffer.append(bytes);
         let mut needs_more_data = false;
         while self.unprocessed_buffer.len() > 0 && !needs_more_data {
@@ -89,8 +190,10 @@ impl Connection {
                 NeedMoreData => {
                     needs_more_data = true;
                 }
-                Skipped(amount) => {
-                    self.unprocessed_buffer = (&self.unprocessed_buffer[amount..]).to_vec();
+                Invalid(e) => {
+                    println!("Closing connection to {}: {}", self.address, e);
+                    self.closed = true;
+                    self.unprocessed_buffer.clear();
                 }
             }
         }
@@ -100,227 +203,285 @@ impl Connection {
         packets
     }
 
+    /// Sends ACKs & NAKs for the datagrams we've received
+    fn send_acks(&mut self) {
+        if let Some(ranges) = self.raknet.take_ack() {
+            let mut ack = vec![FLAG_VALID | FLAG_ACK];
+            ack.append(&mut ranges.write());
+            self.write(&ack);
+        }
+        if let Some(ranges) = self.raknet.take_nak() {
+            let mut nak = vec![FLAG_VALID | FLAG_NAK];
+            nak.append(&mut ranges.write());
+            self.write(&nak);
+        }
+    }
+
+    /// Called every tick by the game loop. Resends RakNet datagrams that weren't acknowledged in time.
+    pub fn tick(&mut self) {
+        if self.is_udp() {
+            for datagram in self.raknet.take_resends() {
+                self.write(&datagram);
+            }
+            self.send_acks();
+        }
+    }
+
+    /// Whether the client sent a lone 0xFE & nothing after it for `BETA_PING_WAIT`
+    pub fn is_beta_ping(&self) -> bool {
+        self.is_tcp() && self.protocol_state == State::JavaHandshake
+            && self.unprocessed_buffer == [0xFE] && self.last_read.elapsed() > BETA_PING_WAIT
+    }
+
     fn start_packet_read(&mut self) -> PacketResult {
         let bytes = &self.unprocessed_buffer.clone();
         let mut index: usize = 0;
 
         if self.is_tcp() {
-            if bytes.len() >= 3 {
-                let first = bytes[0];
-                let second = bytes[1];
-                let third = bytes[2];
-                if first == 0xFE && second == 0x01 && third == 0xFA {
-                    println!("Skipping legacy ping");
-                    // XXX: Legacy Ping 1.6
-                    index = 3;
-                    for s in 0..2 {
-                        let s1 = bytes[index];
-                        index += 1;
-                        let s2 = bytes[index];
-                        index += 1;
-                        let data_size: u16 = ((s1 as u16) << 8) | s2 as u16;
-                        index += (data_size as i32 * (2 - s)) as usize;
-                    }
-                    return Skipped(index);
+            if bytes[0] == 0xFE && self.protocol_state == State::JavaHandshake {
+                // a modern handshake is never long enough to start with 0xFE, so this is a legacy ping.
+                // anything after the first two bytes is ignored since the connection is closed after the response
+                if bytes.len() == 1 {
+                    // the server answers it as a Beta ping if the 0x01 never comes
+                    return NeedMoreData;
                 }
+                let packet = if bytes[1] == 0x01 {
+                    Packet::java_legacy_LegacyPingPacket(legacy::LegacyPingPacket::new())
+                } else {
+                    Packet::java_legacy_BetaPingPacket(legacy::BetaPingPacket::new())
+                };
+                self.unprocessed_buffer.clear();
+                return CompletePacket(packet);
             }
 
             // java edition
-            let length = match <VarInt as ReadField>::read(bytes, index) {
-                Some((l, v)) => {
+            // a bad length means we can't find where the next packet starts, so the stream is lost
+            let mut length = match read_frame_length(bytes) {
+                Ok(Some((l, v))) => {
                     index += v;
-                    l.0
+                    l
                 }
-                None => return NeedMoreData
+                Ok(None) => return NeedMoreData,
+                Err(e) => return Invalid(e)
             };
 
-
-            if bytes.len() < (length as usize) {
+            if bytes.len() < index + length {
                 // we don't have enough data yet
                 return NeedMoreData;
             }
 
-            let id_length;
-            let id = match <VarInt as ReadField>::read(bytes, index) {
-                Some((l, v)) => {
-                    index += v;
-                    id_length = v;
-                    l.0
+            // everything after the length prefix
+            let frame = match self.compression_threshold {
+                Some(threshold) => {
+                    let data_length = match <VarInt as ReadField>::read(&bytes[..(index + length)], index) {
+                        Ok((l, _)) if l.0 < 0 => return Invalid(DecodeError::LengthExceedsLimit),
+                        Ok((l, v)) => {
+                            index += v;
+                            length -= v;
+                            l.0 as usize
+                        }
+                        Err(e) => return Invalid(e)
+                    };
+
+                    if data_length == 0 {
+                        // below the threshold, so it wasn't compressed
+                        (&bytes[index..(index + length)]).to_vec()
+                    } else if data_length < threshold || data_length > MAX_DECOMPRESSED_SIZE {
+                        println!("Invalid compressed packet size {} from {}", data_length, self.address);
+                        return Invalid(DecodeError::LengthExceedsLimit);
+                    } else {
+                        match decompress(&bytes[index..(index + length)], data_length) {
+                            Some(ref inflated) if inflated.len() == data_length => inflated.clone(),
+                            _ => {
+                                println!("Failed to decompress packet from {}", self.address);
+                                return Invalid(DecodeError::LengthExceedsLimit);
+                            }
+                        }
+                    }
                 }
-                None => return NeedMoreData
+                None => (&bytes[index..(index + length)]).to_vec()
             };
+            index += length;
 
-            // get a vec of just the packet's bytes
-            let packet_bytes = (&bytes[index..((length as usize) + 1)]).to_vec();
-
-            // read the packet from the protocol
-            let packet = match self.protocol.read(id, self.protocol_state, Bound::Serverbound, packet_bytes) {
-                Some(packet) => packet,
-                None => return NeedMoreData
+            // the whole frame is here, so a missing id means the frame is malformed
+            let (id, id_length) = match <VarInt as ReadField>::read(&frame, 0) {
+                Ok((l, v)) => (l.0, v),
+                Err(e) => return Invalid(e)
             };
 
-            index += length as usize - id_length;
+            // read the packet from the protocol. the frame is complete, so this is never
+            // waiting on more data; unknown ids & malformed packets are left to the server
+            let packet = self.protocol.read_or_unknown(id, self.protocol_state, Bound::Serverbound, &frame[id_length..]);
 
             let remainder = &bytes[index..];
             self.unprocessed_buffer = remainder.to_vec();
 
             CompletePacket(packet)
-        } else if self.is_udp() {
-            // bedrock edition
-            let id = bytes[index] as i32;
-            index += 1;
-
-            if id & 0x80 == 0x80 && id != 0xA0 && id != 0xC0 {
-                println!("packet is datagram {:#b}\n  SPECIAL: {:X?}", id, bytes);
-                // is datagram
-
-                // header parts
-                let is_ack = id & 0x40 == 0x40;
-                let has_ba_and_as = is_ack && (id & 0x10 == 0x10);
-                let is_nack = !is_ack && (id & 0x20 == 0x20);
-                let is_packet_pair = !is_ack && !is_nack && (id & 0x10 == 0x10);
-                let is_continuous_send = !is_ack && !is_nack && (id & 0x8 == 0x8);
-                let needs_ba_and_as = !is_ack && !is_nack && (id & 0x4 == 0x4);
-
-                if bytes.len() < index + 3 { return NeedMoreData; }
-                let sequence_number = [bytes[index], bytes[index + 1], bytes[index + 2]];
-                index += 3;
-
-                let flags = bytes[index];
-                index += 1;
-                let reliability = (flags & 0xE0) >> 5;
-                println!("flags {:#b} {}", reliability, reliability);
-                let split = flags & 0x10 == 0x10;
-
-                let length = match <u16 as ReadField>::read(bytes, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        // this short is actually the data *bit* length
-                        (l / 8) as usize
-                    }
-                    None => return NeedMoreData
-                };
-
-                // TODO: Abstract this out of here
+        } else {
+            NeedMoreData
+        }
+    }
 
-                let reliability_message = match reliability {
-                    2 | 3 | 4 => {
-                        let s = [bytes[index], bytes[index + 1], bytes[index + 2]];
-                        index += 3;
-                        s
-                    }
-                    _ => [0, 0, 0]
-                };
+    /// Reads every packet in a RakNet datagram.
+    /// UDP keeps message boundaries, so unlike TCP each datagram is parsed on its own.
+    fn read_datagram(&mut self, bytes: &[u8]) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        if bytes.is_empty() {
+            return packets;
+        }
+        let bytes = bytes.to_vec();
+        let id = bytes[0];
+        let mut index: usize = 1;
+
+        if id & FLAG_VALID != FLAG_VALID {
+            // offline packets aren't framed
+            packets.push(self.protocol.read_or_unknown(id as i32, State::BedrockRakNetOffline, Bound::Serverbound, &bytes[index..]));
+            return packets;
+        }
 
-                let sequencing_index = match reliability {
-                    1 | 4 => {
-                        let s = [bytes[index], bytes[index + 1], bytes[index + 2]];
-                        index += 3;
-                        s
-                    }
-                    _ => [0, 0, 0]
-                };
+        if id & (FLAG_ACK | FLAG_NAK) != 0 {
+            let ranges = match <IntRangeList as ReadField>::read(&bytes, index) {
+                Ok((ranges, _)) => ranges,
+                Err(e) => {
+                    println!("Malformed ACK or NAK from {}: {}", self.address, e);
+                    return packets;
+                }
+            };
+            if id & FLAG_ACK == FLAG_ACK {
+                self.raknet.handle_ack(&ranges);
+            } else {
+                for datagram in self.raknet.handle_nak(&ranges) {
+                    self.write(&datagram);
+                }
+            }
+            return packets;
+        }
 
-                let (order_index, order_channel) = match reliability {
-                    1 | 3 | 4 | 7 => {
-                        let s = [bytes[index], bytes[index + 1], bytes[index + 2]];
-                        index += 3;
-                        let c = bytes[index];
-                        index += 1;
-                        (s, c)
-                    }
-                    _ => ([0, 0, 0], 0)
-                };
+        // is datagram
+        let sequence_number = match read_triad(&bytes, index) {
+            Ok(sequence_number) => sequence_number,
+            Err(_) => return packets
+        };
+        index += 3;
 
-                let (packet_count, packet_id, packet_index) = if split {
-                    println!("This packet was split!");
-                    let packet_count = match <u32 as ReadField>::read(bytes, index) {
-                        Some((l, v)) => {
-                            index += v;
-                            l
-                        }
-                        None => return NeedMoreData
-                    };
-                    let packet_id = match <u16 as ReadField>::read(bytes, index) {
-                        Some((l, v)) => {
-                            index += v;
-                            l
-                        }
-                        None => return NeedMoreData
-                    };
-                    let packet_index = match <u32 as ReadField>::read(bytes, index) {
-                        Some((l, v)) => {
-                            index += v;
-                            l
-                        }
-                        None => return NeedMoreData
-                    };
-                    (packet_count, packet_id, packet_index)
-                } else {
-                    (0, 0, 0)
-                };
+        if !self.raknet.receive_sequence_number(sequence_number) {
+            return packets;
+        }
 
-                let id = bytes[index] as i32;
-                index += 1;
+        while index < bytes.len() {
+            let (frame, length) = match Frame::read(&bytes, index) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    println!("Malformed frame in datagram {} from {}: {}", sequence_number, self.address, e);
+                    break;
+                }
+            };
+            index += length;
 
-                if index + length > bytes.len() {
-                    println!("need {}/{} ({:X?}/{:X?}) bytes!!", length, bytes.len(), length, bytes.len());
-                    return NeedMoreData;
+            for body in self.raknet.receive_frame(frame) {
+                if body.is_empty() {
+                    continue;
                 }
 
-                let packet_bytes = (&bytes[index..(index + length - 1)]).to_vec();
-                println!("ID: {} LENGTH: {}\nBYTES:{:X?}", id, length, packet_bytes);
+                if body[0] == BATCH_ID && self.protocol_state == State::BedrockMinecraft {
+                    packets.append(&mut self.read_batch(&body[1..]));
+                    continue;
+                }
 
-                let packet = match self.protocol.read(id, self.protocol_state, Bound::Serverbound, packet_bytes) {
-                    Some(packet) => packet,
-                    None => return NeedMoreData
-                };
+                let id = body[0] as i32;
+                let packet = self.protocol.read_or_unknown(id, State::BedrockRakNet, Bound::Serverbound, &body[1..]);
+                // later frames in this datagram may be in the next state
+                if let Some(state) = packet.next_state() {
+                    self.set_bedrock_state(state);
+                }
+                packets.push(packet);
+            }
+        }
 
-                let remainder = &bytes[(index + length - 1)..];
-                self.unprocessed_buffer = remainder.to_vec();
+        if self.raknet.is_overflowed() {
+            println!("Closing connection to {}: too many frames are waiting to be ordered", self.address);
+            self.closed = true;
+        }
+        packets
+    }
 
-                CompletePacket(packet)
-            } else {
-                // get a vec of just the packet's bytes
-                let packet_bytes = (&bytes[index..]).to_vec();
+    /// Reads the Minecraft packets in a batch frame
+    fn read_batch(&mut self, bytes: &[u8]) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        let bytes = match self.bedrock_cipher {
+            Some(ref mut cipher) => match cipher.decrypt(bytes) {
+                Some(bytes) => bytes,
+                None => {
+                    // the counter & cipher have already moved on, so every later batch would fail too
+                    println!("Closing connection to {}: invalid batch checksum", self.address);
+                    self.closed = true;
+                    return packets;
+                }
+            },
+            None => bytes.to_vec()
+        };
+        let batch = match bedrock::read_batch(&bytes) {
+            Some(batch) => batch,
+            None => {
+                println!("Failed to read batch from {}", self.address);
+                return packets;
+            }
+        };
 
-                // read the packet from the protocol
-                let packet = match self.protocol.read(id, State::BedrockRakNetOffline, Bound::Serverbound, packet_bytes) {
-                    Some(packet) => packet,
-                    None => return NeedMoreData
-                };
+        for bytes in batch {
+            let (header, length) = match <VarUInt as ReadField>::read(&bytes, 0) {
+                Ok(header) => header,
+                Err(_) => continue
+            };
+            let id = (header.0 as i32) & bedrock::PACKET_ID_MASK;
+            packets.push(self.protocol.read_or_unknown(id, State::BedrockMinecraft, Bound::Serverbound, &bytes[length..]));
+        }
+        packets
+    }
 
-                // TODO: Not this hack. Maybe return the length of the packet from protocol#read ?
-                let remainder = &bytes[(packet.write().len() + 1)..];
-                self.unprocessed_buffer = remainder.to_vec();
+    fn set_bedrock_state(&mut self, state: State) {
+        if state == State::BedrockMinecraft && self.protocol_state != state {
+            // the protocol is switched again once we know the version from the LoginPacket
+            self.protocol = Protocol::ProtocolBedrock_1_5(v1_5::ProtocolBedrock_1_5);
+        }
+        self.protocol_state = state;
+    }
 
-                CompletePacket(packet)
-            }
-        } else {
-            NeedMoreData
+    /// Responds to a legacy ping with a kick packet (0xFF) holding `reason` in UTF-16BE.
+    /// The connection should be closed after this.
+    pub fn send_legacy_kick(&mut self, reason: &str) {
+        let chars: Vec<u16> = reason.encode_utf16().collect();
+        let mut buf = vec![0xFF];
+        buf.extend_from_slice(&(chars.len() as u16).to_be_bytes());
+        for c in chars {
+            buf.extend_from_slice(&c.to_be_bytes());
         }
+        self.write(&buf);
     }
 
     pub fn send_packet(&mut self, packet: Packet) {
+        let is_raknet = packet.module() == "raknet";
         match self.protocol.write(packet, Bound::Clientbound) {
             Some(mut bytes) => {
-                if self.protocol_state == State::BedrockRakNet {
-                    // online raknet packets need a special header
-                    let len = (bytes.len() * 8) as u16;
-
-                    let d = self.datagram_sequence_id.fetch_add(1, Ordering::SeqCst);
-                    let mut header = vec![
-                        0x84u8, // todo: real raknet header
-                        (0xFF & d) as u8,
-                        (0xFF & (d >> 8)) as u8,
-                        (0xFF & (d >> 16)) as u8,
-                        0x40,
-                        ((len >> 8) & 0xFF) as u8,
-                        (len & 0xFF) as u8,
-                        0, 0, 0
-                    ];
-                    header.append(&mut bytes);
-                    self.write(header.as_slice());
+                if self.protocol_state == State::BedrockRakNet || self.protocol_state == State::BedrockMinecraft {
+                    // online raknet packets are sent in frames, with Minecraft packets in a batch
+                    let bytes = if is_raknet {
+                        bytes
+                    } else {
+                        let batch = bedrock::write_batch(&[bytes]);
+                        let mut buf = vec![BATCH_ID];
+                        match self.bedrock_cipher {
+                            Some(ref mut cipher) => buf.append(&mut cipher.encrypt(&batch)),
+                            None => buf.extend_from_slice(&batch)
+                        }
+                        buf
+                    };
+                    for frame in self.raknet.create_frames(bytes, Reliability::ReliableOrdered) {
+                        let datagram = self.raknet.create_datagram(&frame);
+                        self.write(datagram.as_slice());
+                    }
+                } else if self.is_tcp() {
+                    self.write_java_packet(bytes);
                 } else {
                     self.write(bytes.as_slice());
                 }
@@ -329,15 +490,168 @@ impl Connection {
         }
     }
 
+    /// Writes a Java packet with its length prefix, compressing it if it's over the threshold.
+    /// `bytes` starts with `JAVA_FRAME_HEADER_SIZE` bytes of space for the header.
+    fn write_java_packet(&mut self, mut bytes: Vec<u8>) {
+        let packet_length = bytes.len() - JAVA_FRAME_HEADER_SIZE;
+        match self.compression_threshold {
+            Some(threshold) if packet_length >= threshold => {
+                let data_length = VarInt(packet_length as i32);
+                let compressed = compress(&bytes[JAVA_FRAME_HEADER_SIZE..]);
+                let length = VarInt((data_length.size_hint() + compressed.len()) as i32);
+
+                let mut framed = Vec::with_capacity(length.size_hint() + length.0 as usize);
+                length.write_to(&mut framed);
+                data_length.write_to(&mut framed);
+                framed.extend_from_slice(&compressed);
+                self.write(&framed);
+            }
+            Some(_) if packet_length + 1 <= MAX_PADDED_VAR_INT => {
+                // a data length of 0 means the packet isn't compressed
+                write_padded_var_int(packet_length + 1, &mut bytes[..3]);
+                bytes[3] = 0;
+                self.write(&bytes);
+            }
+            None if packet_length <= MAX_PADDED_VAR_INT => {
+                write_padded_var_int(packet_length, &mut bytes[1..4]);
+                self.write(&bytes[1..]);
+            }
+            _ => {
+                // too big for the space we reserved, which vanilla clients won't accept anyway
+                println!("Packet of {} bytes is too big to send to {}", packet_length, self.address);
+            }
+        }
+    }
+
     /// Writes `bytes` to the connected client
     pub fn write(&mut self, bytes: &[u8]) {
-        match self.socket {
-            SocketWrapper::TCP(ref mut stream) => {
-                stream.write(bytes);
+        if self.is_tcp() {
+            let start = self.outgoing_buffer.len();
+            self.outgoing_buffer.extend_from_slice(bytes);
+            // encrypt as it's queued so the cipher sees bytes in the order they're sent
+            if let Some(ref mut cipher) = self.cipher {
+                cipher.encrypt(&mut self.outgoing_buffer[start..]);
             }
-            SocketWrapper::UDP(ref mut socket) => {
-                socket.send_to(bytes, self.address);
+            self.flush();
+        } else if let SocketWrapper::UDP(ref socket) = self.socket {
+            socket.send_to(bytes, self.address);
+        }
+    }
+
+    /// Writes as much of the outgoing buffer as the socket will take.
+    /// Whatever is left is written when the TCP thread sees the socket is writable again.
+    pub fn flush(&mut self) {
+        if let SocketWrapper::TCP(ref mut stream) = self.socket {
+            while self.outgoing_buffer.len() > 0 {
+                match stream.write(&self.outgoing_buffer) {
+                    Ok(0) => break,
+                    Ok(length) => {
+                        self.outgoing_buffer.drain(..length);
+                    }
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        println!("Failed to write to {}: {}", self.address, e);
+                        break;
+                    }
+                }
             }
         }
     }
 }
+
+/// Reads the length prefix of a Java frame.
+/// Returns the length & how many bytes the prefix took up, or `None` if the prefix hasn't all arrived yet.
+pub fn read_frame_length(bytes: &[u8]) -> Result<Option<(usize, usize)>, DecodeError> {
+    match <VarInt as ReadField>::read(&bytes[..bytes.len().min(3)], 0) {
+        Ok((length, _)) if length.0 < 0 || length.0 as usize > MAX_JAVA_PACKET_LENGTH => {
+            Err(DecodeError::LengthExceedsLimit)
+        }
+        Ok((length, size)) => Ok(Some((length.0 as usize, size))),
+        Err(DecodeError::UnexpectedEof) if bytes.len() < 3 => Ok(None),
+        // the 3rd byte said there was more
+        Err(DecodeError::UnexpectedEof) => Err(DecodeError::VarIntTooLong),
+        Err(e) => Err(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Bedrock connection that's past the offline handshake & the socket standing in for its client
+    fn udp_connection() -> (Connection, UdpSocket) {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let mut connection = Connection::new(client.local_addr().unwrap(), SocketWrapper::UDP(Arc::new(server)));
+        connection.protocol_state = State::BedrockRakNet;
+        (connection, client)
+    }
+
+    fn frame(reliable_index: u32, body: Vec<u8>) -> Frame {
+        Frame {
+            reliability: Reliability::Reliable,
+            reliable_index,
+            sequence_index: 0,
+            order_index: 0,
+            order_channel: 0,
+            split: None,
+            body,
+        }
+    }
+
+    fn datagram(sequence_number: u32, frames: &[Frame]) -> Vec<u8> {
+        let mut datagram = vec![FLAG_VALID];
+        datagram.extend_from_slice(&write_triad(sequence_number));
+        for frame in frames {
+            datagram.append(&mut frame.write());
+        }
+        datagram
+    }
+
+    fn is_disconnect(packet: &Packet) -> bool {
+        match packet {
+            Packet::bedrock_raknet_DisconnectNotificationPacket(_) => true,
+            _ => false
+        }
+    }
+
+    #[test]
+    fn every_frame_in_a_datagram_is_read() {
+        let (mut connection, client) = udp_connection();
+        let mut bytes = datagram(0, &[frame(0, vec![0x15]), frame(1, vec![0x15]), frame(2, vec![0x15])]);
+        let packets = connection.handle_read(&mut bytes);
+        assert_eq!(packets.len(), 3);
+        assert!(packets.iter().all(is_disconnect));
+        assert!(connection.unprocessed_buffer.is_empty());
+
+        // and the datagram is acknowledged
+        let mut buf = [0u8; 64];
+        let (length, _) = client.recv_from(&mut buf).unwrap();
+        assert_eq!(buf[0], FLAG_VALID | FLAG_ACK);
+        let (ranges, _) = <IntRangeList as ReadField>::read(&buf[..length], 1).unwrap();
+        assert_eq!(from_ranges(&ranges), vec![0]);
+    }
+
+    #[test]
+    fn datagrams_are_read_on_their_own() {
+        let (mut connection, _client) = udp_connection();
+        let mut first = datagram(0, &[frame(0, vec![0x15])]);
+        let mut second = datagram(1, &[frame(1, vec![0x15])]);
+        assert_eq!(connection.handle_read(&mut first).len(), 1);
+        assert_eq!(connection.handle_read(&mut second).len(), 1);
+        assert!(connection.unprocessed_buffer.is_empty());
+    }
+
+    #[test]
+    fn truncated_frame_keeps_the_ones_before_it() {
+        let (mut connection, _client) = udp_connection();
+        let mut bytes = datagram(0, &[frame(0, vec![0x15]), frame(1, vec![0x15, 0x00])]);
+        bytes.pop();
+        let packets = connection.handle_read(&mut bytes);
+        assert_eq!(packets.len(), 1);
+        assert!(is_disconnect(&packets[0]));
+        assert!(!connection.is_closed());
+    }
+}
@@ -0,0 +1,355 @@
+use aes::{Aes128, Aes256};
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit, StreamCipher};
+use aes::cipher::generic_array::GenericArray;
+use p384::{PublicKey, SecretKey};
+use p384::ecdh::diffie_hellman;
+use p384::ecdsa::{Signature, SigningKey};
+use p384::ecdsa::signature::Signer;
+use p384::pkcs8::{DecodePublicKey, EncodePublicKey};
+use rand::RngCore;
+use rsa::{RsaPrivateKey, RsaPublicKey, Pkcs1v15Encrypt};
+use sha2::{Digest, Sha256};
+
+/// Vanilla servers use a 1024 bit key for the login handshake
+const RSA_KEY_BITS: usize = 1024;
+
+/// RSA keypair generated when the server starts.
+/// The public key is sent to Java clients in the `EncryptionRequestPacket` so
+/// they can encrypt the shared secret they generate.
+pub struct ServerKey {
+    private_key: RsaPrivateKey,
+    /// ASN.1 DER encoded public key
+    public_key: Vec<u8>,
+}
+
+impl ServerKey {
+    pub fn generate() -> ServerKey {
+        let mut rng = rand::thread_rng();
+        let private_key = RsaPrivateKey::new(&mut rng, RSA_KEY_BITS).expect("failed to generate RSA keypair");
+        let public_key = RsaPublicKey::from(&private_key)
+            .to_public_key_der()
+            .expect("failed to encode RSA public key")
+            .as_bytes()
+            .to_vec();
+
+        ServerKey {
+            private_key,
+            public_key,
+        }
+    }
+
+    /// DER encoded public key
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Decrypts data the client encrypted with our public key
+    pub fn decrypt(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        self.private_key.decrypt(Pkcs1v15Encrypt, bytes).ok()
+    }
+}
+
+/// Random bytes used for the verify token in the `EncryptionRequestPacket`
+pub fn generate_verify_token() -> Vec<u8> {
+    let mut token = vec![0u8; 4];
+    rand::thread_rng().fill_bytes(&mut token);
+    token
+}
+
+/// AES-128 in CFB8 mode, which is what the Java protocol uses once encryption is enabled.
+/// The shared secret is used as both the key and the IV.
+/// Each direction keeps its own state since this is a stream cipher.
+pub struct JavaCipher {
+    encryptor: cfb8::Encryptor<Aes128>,
+    decryptor: cfb8::Decryptor<Aes128>,
+}
+
+impl JavaCipher {
+    /// Returns `None` if the shared secret isn't 16 bytes
+    pub fn new(shared_secret: &[u8]) -> Option<JavaCipher> {
+        if shared_secret.len() != 16 {
+            return None;
+        }
+
+        Some(JavaCipher {
+            encryptor: cfb8::Encryptor::<Aes128>::new_from_slices(shared_secret, shared_secret).ok()?,
+            decryptor: cfb8::Decryptor::<Aes128>::new_from_slices(shared_secret, shared_secret).ok()?,
+        })
+    }
+
+    /// Encrypts bytes going to the client in place
+    pub fn encrypt(&mut self, bytes: &mut [u8]) {
+        // CFB8 has a block size of one byte
+        for byte in bytes.chunks_mut(1) {
+            self.encryptor.encrypt_block_mut(GenericArray::from_mut_slice(byte));
+        }
+    }
+
+    /// Decrypts bytes coming from the client in place
+    pub fn decrypt(&mut self, bytes: &mut [u8]) {
+        for byte in bytes.chunks_mut(1) {
+            self.decryptor.decrypt_block_mut(GenericArray::from_mut_slice(byte));
+        }
+    }
+}
+
+/// Bedrock versions from this one on use AES-256 in GCM mode (without the tag) instead of CFB8
+pub const BEDROCK_GCM_PROTOCOL_VERSION: i32 = 431;
+
+/// P-384 keypair used for the Bedrock encryption handshake.
+/// It signs the `ServerToClientHandshakePacket` token & is used for ECDH with the client's key.
+pub struct BedrockKey {
+    secret_key: SecretKey,
+    /// base64 DER encoded public key, the format used in Bedrock's tokens
+    public_key: String,
+}
+
+impl BedrockKey {
+    pub fn generate() -> BedrockKey {
+        BedrockKey::from_secret(SecretKey::random(&mut rand::thread_rng()))
+    }
+
+    fn from_secret(secret_key: SecretKey) -> BedrockKey {
+        let public_key = secret_key.public_key()
+            .to_public_key_der()
+            .expect("failed to encode P-384 public key");
+
+        BedrockKey {
+            secret_key,
+            public_key: base64::encode(public_key.as_bytes()),
+        }
+    }
+
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// The token sent in the `ServerToClientHandshakePacket`, signed with our key
+    pub fn handshake_token(&self, salt: &[u8]) -> String {
+        let header = serde_json::json!({
+            "alg": "ES384",
+            "x5u": self.public_key,
+        });
+        let payload = serde_json::json!({
+            "salt": base64::encode(salt),
+        });
+
+        let signed = format!("{}.{}",
+            base64::encode_config(header.to_string(), base64::URL_SAFE_NO_PAD),
+            base64::encode_config(payload.to_string(), base64::URL_SAFE_NO_PAD));
+        let signature: Signature = SigningKey::from(&self.secret_key).sign(signed.as_bytes());
+        format!("{}.{}", signed, base64::encode_config(signature.to_bytes(), base64::URL_SAFE_NO_PAD))
+    }
+
+    /// Derives the encryption key from ECDH with the client's base64 DER public key.
+    /// Returns `None` if the client's key isn't valid.
+    pub fn derive_key(&self, client_key: &str, salt: &[u8]) -> Option<Vec<u8>> {
+        let client_key = PublicKey::from_public_key_der(&base64::decode(client_key).ok()?).ok()?;
+        let shared_secret = diffie_hellman(self.secret_key.to_nonzero_scalar(), client_key.as_affine());
+
+        let mut hasher = Sha256::new();
+        hasher.update(salt);
+        hasher.update(shared_secret.raw_secret_bytes());
+        Some(hasher.finalize().to_vec())
+    }
+}
+
+/// Random bytes used for the salt in the `ServerToClientHandshakePacket`
+pub fn generate_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    salt
+}
+
+enum BedrockStream {
+    Cfb8(cfb8::Encryptor<Aes256>, cfb8::Decryptor<Aes256>),
+    /// GCM without the tag is just CTR starting at 2
+    Ctr(ctr::Ctr32BE<Aes256>, ctr::Ctr32BE<Aes256>),
+}
+
+/// AES-256 over the body of Bedrock batches once the handshake is done.
+/// Each batch has an 8 byte checksum at the end so the counters have to stay in sync with the client.
+pub struct BedrockCipher {
+    key: Vec<u8>,
+    stream: BedrockStream,
+    send_counter: u64,
+    receive_counter: u64,
+}
+
+impl BedrockCipher {
+    /// Returns `None` if the key isn't 32 bytes
+    pub fn new(key: &[u8], protocol_version: i32) -> Option<BedrockCipher> {
+        if key.len() != 32 {
+            return None;
+        }
+
+        let stream = if protocol_version >= BEDROCK_GCM_PROTOCOL_VERSION {
+            let mut iv = [0u8; 16];
+            iv[..12].copy_from_slice(&key[..12]);
+            iv[15] = 2;
+            BedrockStream::Ctr(
+                ctr::Ctr32BE::<Aes256>::new_from_slices(key, &iv).ok()?,
+                ctr::Ctr32BE::<Aes256>::new_from_slices(key, &iv).ok()?,
+            )
+        } else {
+            BedrockStream::Cfb8(
+                cfb8::Encryptor::<Aes256>::new_from_slices(key, &key[..16]).ok()?,
+                cfb8::Decryptor::<Aes256>::new_from_slices(key, &key[..16]).ok()?,
+            )
+        };
+
+        Some(BedrockCipher {
+            key: key.to_vec(),
+            stream,
+            send_counter: 0,
+            receive_counter: 0,
+        })
+    }
+
+    /// Adds the checksum to the body of a batch & encrypts it
+    pub fn encrypt(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let mut buf = bytes.to_vec();
+        buf.extend_from_slice(&self.checksum(self.send_counter, bytes));
+        self.send_counter += 1;
+
+        match self.stream {
+            BedrockStream::Cfb8(ref mut encryptor, _) => {
+                for byte in buf.chunks_mut(1) {
+                    encryptor.encrypt_block_mut(GenericArray::from_mut_slice(byte));
+                }
+            }
+            BedrockStream::Ctr(ref mut encryptor, _) => encryptor.apply_keystream(&mut buf)
+        }
+        buf
+    }
+
+    /// Decrypts the body of a batch & checks its checksum.
+    /// Returns `None` if the checksum doesn't match.
+    pub fn decrypt(&mut self, bytes: &[u8]) -> Option<Vec<u8>> {
+        let mut buf = bytes.to_vec();
+        match self.stream {
+            BedrockStream::Cfb8(_, ref mut decryptor) => {
+                for byte in buf.chunks_mut(1) {
+                    decryptor.decrypt_block_mut(GenericArray::from_mut_slice(byte));
+                }
+            }
+            BedrockStream::Ctr(_, ref mut decryptor) => decryptor.apply_keystream(&mut buf)
+        }
+
+        if buf.len() < 8 {
+            return None;
+        }
+        let checksum = buf.split_off(buf.len() - 8);
+        let expected = self.checksum(self.receive_counter, &buf);
+        self.receive_counter += 1;
+        if checksum == expected {
+            Some(buf)
+        } else {
+            None
+        }
+    }
+
+    /// First 8 bytes of SHA-256(counter, body, key)
+    fn checksum(&self, counter: u64, bytes: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(counter.to_le_bytes());
+        hasher.update(bytes);
+        hasher.update(&self.key);
+        hasher.finalize()[..8].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // the client's public key for a secret of 48 0x02 bytes
+    const CLIENT_KEY: &str = "MHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEMWFAwmjIhBzd0dy7UaEdUW0oXN2ml58duSMLmpQ28H6jusuPQgDjgmNDOEhNGc30lKGkV99Cs+fiKiVTAElTBbXW8CCPKq2hdBrxqbqqc8OduXHvBtGA2VJOGipNM9+g";
+
+    fn derived_key() -> Vec<u8> {
+        let server = BedrockKey::from_secret(SecretKey::from_slice(&[1; 48]).unwrap());
+        let salt: Vec<u8> = (0..16).collect();
+        server.derive_key(CLIENT_KEY, &salt).unwrap()
+    }
+
+    #[test]
+    fn java_cipher() {
+        let secret: Vec<u8> = (0..16).collect();
+        let mut cipher = JavaCipher::new(&secret).unwrap();
+        let mut bytes = b"phast".to_vec();
+        cipher.encrypt(&mut bytes);
+        assert_eq!(hex(&bytes), "7a3d56eff0");
+        cipher.decrypt(&mut bytes);
+        assert_eq!(bytes, b"phast");
+
+        // it's a stream, so splitting the input up doesn't change anything
+        let mut split = JavaCipher::new(&secret).unwrap();
+        let (mut first, mut second) = (b"pha".to_vec(), b"st".to_vec());
+        split.encrypt(&mut first);
+        split.encrypt(&mut second);
+        assert_eq!(hex(&[first, second].concat()), "7a3d56eff0");
+
+        assert!(JavaCipher::new(&secret[..15]).is_none());
+    }
+
+    #[test]
+    fn ecdh_key() {
+        assert_eq!(hex(&derived_key()), "334ff0b5e3d2bc0ee8bd689c59940df9b6a47ad3fd83f252cf1e4605cc1abb60");
+        let server = BedrockKey::generate();
+        assert!(server.derive_key("not base64!", &[]).is_none());
+        assert!(server.derive_key(&base64::encode([0u8; 32]), &[]).is_none());
+    }
+
+    #[test]
+    fn bedrock_cipher_vectors() {
+        let key = derived_key();
+        // the IV is the start of the key, with a counter starting at 2 for GCM
+        let mut gcm = BedrockCipher::new(&key, BEDROCK_GCM_PROTOCOL_VERSION).unwrap();
+        assert_eq!(hex(&gcm.encrypt(b"hello")), "67697918a524128a85f13ef571");
+        let mut cfb8 = BedrockCipher::new(&key, BEDROCK_GCM_PROTOCOL_VERSION - 1).unwrap();
+        assert_eq!(hex(&cfb8.encrypt(b"hello")), "0bdda9f1cce63b8bedab5a7772");
+
+        assert!(BedrockCipher::new(&key[..16], BEDROCK_GCM_PROTOCOL_VERSION).is_none());
+    }
+
+    #[test]
+    fn bedrock_cipher_round_trip() {
+        let key = derived_key();
+        for &protocol_version in &[BEDROCK_GCM_PROTOCOL_VERSION - 1, BEDROCK_GCM_PROTOCOL_VERSION] {
+            // both directions start from the same state, so a cipher can read what it wrote
+            let mut cipher = BedrockCipher::new(&key, protocol_version).unwrap();
+            for batch in &[&b"first"[..], &b""[..], &[0xFE; 300][..]] {
+                let encrypted = cipher.encrypt(batch);
+                assert_eq!(encrypted.len(), batch.len() + 8);
+                assert_eq!(cipher.decrypt(&encrypted).as_ref().map(|b| &b[..]), Some(*batch));
+            }
+        }
+    }
+
+    #[test]
+    fn bedrock_cipher_rejects_tampering() {
+        let key = derived_key();
+        for &protocol_version in &[BEDROCK_GCM_PROTOCOL_VERSION - 1, BEDROCK_GCM_PROTOCOL_VERSION] {
+            for &index in &[0, 6, 12] {
+                let mut cipher = BedrockCipher::new(&key, protocol_version).unwrap();
+                let mut encrypted = cipher.encrypt(b"hello");
+                encrypted[index] ^= 1;
+                assert!(cipher.decrypt(&encrypted).is_none());
+            }
+
+            // the counter & stream have moved on, so a batch can't be replayed
+            let mut sender = BedrockCipher::new(&key, protocol_version).unwrap();
+            let mut receiver = BedrockCipher::new(&key, protocol_version).unwrap();
+            let first = sender.encrypt(b"hello");
+            assert!(receiver.decrypt(&first).is_some());
+            assert!(receiver.decrypt(&first).is_none());
+
+            let mut short = BedrockCipher::new(&key, protocol_version).unwrap();
+            assert!(short.decrypt(&[0; 7]).is_none());
+        }
+    }
+}
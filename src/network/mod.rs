@@ -4,16 +4,26 @@ pub mod connection;
 #[macro_use]
 pub mod protocol;
 pub mod types;
+pub mod encryption;
+pub mod compression;
+pub mod auth;
+pub mod reliability;
+pub mod bedrock_auth;
+pub mod chat;
+pub mod nbt;
 
-use std::time::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
-use std::io::Read;
+use std::io::{ErrorKind, Read};
 use std::sync::mpsc::{channel, Sender, Receiver};
-use std::net::{TcpListener, UdpSocket, SocketAddr};
+use std::net::{UdpSocket, SocketAddr};
 
 use concurrent_hashmap::*;
+use mio::{Events, Interest, Poll, Token};
+use mio::net::TcpListener;
 use network::protocol::*;
 use network::packet::*;
 use network::connection::*;
@@ -21,16 +31,33 @@ use network::connection::*;
 pub struct ConnectionManager {
     pub connections: ConcHashMap<SocketAddr, connection::Connection>,
     pub tcp_addresses: Mutex<Vec<SocketAddr>>,
+    pub udp_addresses: Mutex<Vec<SocketAddr>>,
+    /// Tells the game loop about every connection that gets removed
+    disconnect_sender: Mutex<Sender<SocketAddr>>,
 }
 
 impl ConnectionManager {
-    pub fn new() -> ConnectionManager {
+    pub fn new(disconnect_sender: Sender<SocketAddr>) -> ConnectionManager {
         ConnectionManager {
             connections: ConcHashMap::<SocketAddr, connection::Connection>::new(),
             tcp_addresses: Mutex::new(Vec::with_capacity(20)),
+            udp_addresses: Mutex::new(Vec::with_capacity(20)),
+            disconnect_sender: Mutex::new(disconnect_sender),
         }
     }
 
+    /// Removes a connection from the manager & lets the game loop know.
+    /// Dropping the returned `Connection` closes its socket.
+    pub fn remove(&self, address: &SocketAddr) -> Option<connection::Connection> {
+        self.tcp_addresses.lock().unwrap().retain(|a| a != address);
+        self.udp_addresses.lock().unwrap().retain(|a| a != address);
+        let connection = self.connections.remove(address);
+        if connection.is_some() {
+            self.disconnect_sender.lock().unwrap().send(*address).unwrap();
+        }
+        connection
+    }
+
     pub fn get_protocol(&self, address: SocketAddr) -> Option<Protocol> {
         if let Some(mut connection) = self.connections.find_mut(&address) {
             Some(connection.get().protocol)
@@ -47,7 +74,10 @@ pub struct NetworkManager {
 }
 
 impl NetworkManager {
-    pub fn new(connection_manager: Arc<ConnectionManager>, packet_sender: Sender<(SocketAddr, Packet)>) -> Self {
+    pub fn new(
+        connection_manager: Arc<ConnectionManager>,
+        packet_sender: Sender<(SocketAddr, Packet)>,
+    ) -> Self {
         Self {
             connection_manager,
             packet_sender,
@@ -55,36 +85,33 @@ impl NetworkManager {
         }
     }
 
-    pub fn start(&mut self) {
+    /// Starts the TCP server on `java_address` & the UDP server on `bedrock_address`.
+    /// An edition is disabled if its address is `None`.
+    pub fn start(&mut self, java_address: Option<SocketAddr>, bedrock_address: Option<SocketAddr>) {
         let (unprocessed_sender, unprocessed_receiver) = channel::<(SocketAddr, Vec<u8>)>();
-        let tcp_listener_thread = thread::Builder::new().name("TCP-Listener".into());
-        let tcp_listener_handle = tcp_listener_thread.spawn({
-            let connection_manager = self.connection_manager.clone();
-            move || {
-                NetworkManager::start_tcp_listener(connection_manager);
-            }
-        }).unwrap();
-        self.threads.push(tcp_listener_handle);
-
-        let tcp_read_thread = thread::Builder::new().name("TCP-Read".into());
-        let tcp_read_handle = tcp_read_thread.spawn({
-            let connection_manager = self.connection_manager.clone();
-            let unprocessed_sender = unprocessed_sender.clone();
-            move || {
-                NetworkManager::start_tcp_reads(connection_manager, unprocessed_sender);
-            }
-        }).unwrap();
-        self.threads.push(tcp_read_handle);
+        if let Some(address) = java_address {
+            let tcp_thread = thread::Builder::new().name("TCP".into());
+            let tcp_handle = tcp_thread.spawn({
+                let connection_manager = self.connection_manager.clone();
+                let unprocessed_sender = unprocessed_sender.clone();
+                move || {
+                    NetworkManager::start_tcp(address, connection_manager, unprocessed_sender);
+                }
+            }).unwrap();
+            self.threads.push(tcp_handle);
+        }
 
-        let udp_thread = thread::Builder::new().name("UDP".into());
-        let udp_handle = udp_thread.spawn({
-            let connection_manager = self.connection_manager.clone();
-            let unprocessed_sender = unprocessed_sender.clone();
-            move || {
-                NetworkManager::start_udp(connection_manager, unprocessed_sender);
-            }
-        }).unwrap();
-        self.threads.push(udp_handle);
+        if let Some(address) = bedrock_address {
+            let udp_thread = thread::Builder::new().name("UDP".into());
+            let udp_handle = udp_thread.spawn({
+                let connection_manager = self.connection_manager.clone();
+                let unprocessed_sender = unprocessed_sender.clone();
+                move || {
+                    NetworkManager::start_udp(address, connection_manager, unprocessed_sender);
+                }
+            }).unwrap();
+            self.threads.push(udp_handle);
+        }
 
         let packet_parse_thread = thread::Builder::new().name("Packet-Parse".into());
         let packet_parse_handle = packet_parse_thread.spawn({
@@ -111,12 +138,20 @@ impl NetworkManager {
         loop {
             match bytes.recv() {
                 Ok((address, mut bytes)) => {
+                    let mut closed = false;
                     if let Some(mut connection) = connection_manager.connections.find_mut(&address) {
-                        let packets = (*connection.get()).handle_read(&mut bytes);
+                        let connection = connection.get();
+                        let packets = connection.handle_read(&mut bytes);
                         for packet in packets {
                             println!("[Packet-Parse] Received {} from {}", packet.name(), address);
                             packet_channel.send((address, packet)).unwrap();
                         }
+                        closed = connection.is_closed();
+                    }
+
+                    if closed {
+                        // the client sent something we couldn't make sense of
+                        connection_manager.remove(&address);
                     }
                 }
                 Err(e) => {
@@ -126,71 +161,148 @@ impl NetworkManager {
         }
     }
 
-    /// Starts listening for incoming connections and adds them to the `connection_manager`
-    fn start_tcp_listener(connection_manager: Arc<ConnectionManager>) {
-        let listener = TcpListener::bind("0.0.0.0:25565").unwrap();
-        // this thread can be blocking since it isn't locking anything
-        listener.set_nonblocking(false).unwrap();
-        println!("[TCP-Listener] Binding server to on 0.0.0.0:25565");
-
-        for stream in listener.incoming() {
-            match stream {
-                Ok(mut socket) => {
-                    // these connections need to be non-blocking so we don't hog
-                    // the lock to the connection in the thread below
-                    socket.set_nonblocking(true).unwrap();
-                    let address = socket.peer_addr().unwrap();
-                    let mut connection = Connection::new(address, SocketWrapper::TCP(socket));
-                    connection_manager.connections.insert(address, connection);
-                    let mut tcp_addresses = connection_manager.tcp_addresses.lock().unwrap();
-                    tcp_addresses.push(address);
-                    println!("[TCP-Listener] Accepted new connection from {}", address);
-                }
-                Err(e) => {
-                    println!("[TCP-Listener] Failed to accept connection: {}", e);
+    /// Event loop for Java Edition connections.
+    /// New connections are accepted and added to the `connection_manager`, readable sockets
+    /// are drained and the bytes sent off to be parsed, and writable sockets get any data
+    /// that couldn't be written earlier. Closed sockets are removed from the `connection_manager`.
+    fn start_tcp(
+        address: SocketAddr,
+        connection_manager: Arc<ConnectionManager>,
+        byte_sender: Sender<(SocketAddr, Vec<u8>)>,
+    ) {
+        const LISTENER: Token = Token(0);
+        // how often we look for connections the game loop has closed
+        let prune_interval = Duration::from_secs(1);
+
+        let mut poll = Poll::new().unwrap();
+        let mut events = Events::with_capacity(1024);
+
+        let mut listener = TcpListener::bind(address).unwrap();
+        poll.registry().register(&mut listener, LISTENER, Interest::READABLE).unwrap();
+        println!("[TCP] Binding server to on {}", address);
+
+        let mut tokens: HashMap<Token, SocketAddr> = HashMap::new();
+        let mut next_token = 1usize;
+        let mut buf = vec![0u8; 4096];
+        let mut last_prune = Instant::now();
+
+        loop {
+            if let Err(e) = poll.poll(&mut events, Some(prune_interval)) {
+                if e.kind() == ErrorKind::Interrupted {
+                    continue;
                 }
+                panic!("[TCP] Failed to poll: {}", e);
             }
-        }
-    }
 
-    fn start_tcp_reads(connection_manager: Arc<ConnectionManager>, byte_sender: Sender<(SocketAddr, Vec<u8>)>) {
-        let read_tick = Duration::from_millis(100);
-        loop {
-            let now = SystemTime::now();
-            for address in connection_manager.tcp_addresses.lock().unwrap().iter() {
-                if let Some(mut connection) = connection_manager.connections.find_mut(&address) {
-                    match connection.get().socket {
-                        SocketWrapper::TCP(ref mut stream) => {
-                            let mut buf = vec![0; 64];
-                            let length = stream.read(&mut buf).unwrap_or(0);
-
-                            if length > 0 {
-//                                println!("[TCP-Read] Read {} bytes from {}", length, address);
-                                byte_sender.send((*address, (&buf[..length]).to_vec())).unwrap();
+            if last_prune.elapsed() >= prune_interval {
+                // the game loop may have kicked some clients, so forget about their tokens
+                tokens.retain(|_, address| connection_manager.connections.find(address).is_some());
+                last_prune = Instant::now();
+            }
+
+            for event in events.iter() {
+                match event.token() {
+                    LISTENER => {
+                        // accept everything that's waiting
+                        loop {
+                            match listener.accept() {
+                                Ok((mut socket, address)) => {
+                                    let token = Token(next_token);
+                                    next_token += 1;
+                                    if let Err(e) = poll.registry().register(&mut socket, token, Interest::READABLE | Interest::WRITABLE) {
+                                        println!("[TCP] Failed to register connection from {}: {}", address, e);
+                                        continue;
+                                    }
+                                    socket.set_nodelay(true).unwrap_or(());
+                                    tokens.insert(token, address);
+
+                                    let connection = Connection::new(address, SocketWrapper::TCP(socket));
+                                    connection_manager.connections.insert(address, connection);
+                                    let mut tcp_addresses = connection_manager.tcp_addresses.lock().unwrap();
+                                    tcp_addresses.push(address);
+                                    println!("[TCP] Accepted new connection from {}", address);
+                                }
+                                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                                Err(e) => {
+                                    println!("[TCP] Failed to accept connection: {}", e);
+                                    break;
+                                }
                             }
                         }
-                        _ => {}
-                    };
-                }
-            }
-            match now.elapsed() {
-                Ok(elapsed) => {
-                    let sleep = read_tick - elapsed;
-//                    println!("[TCP-Read] Sleeping for {:?}", sleep);
-                    thread::sleep(sleep);
+                    }
+                    token => {
+                        let address = match tokens.get(&token) {
+                            Some(address) => *address,
+                            None => continue
+                        };
+
+                        let mut closed = false;
+                        match connection_manager.connections.find_mut(&address) {
+                            Some(mut connection) => {
+                                let connection = connection.get();
+
+                                if event.is_readable() {
+                                    // events are edge triggered, so we need to read until there's nothing left
+                                    let mut read = Vec::new();
+                                    if let SocketWrapper::TCP(ref mut stream) = connection.socket {
+                                        loop {
+                                            match stream.read(&mut buf) {
+                                                Ok(0) => {
+                                                    // end of stream
+                                                    closed = true;
+                                                    break;
+                                                }
+                                                Ok(length) => read.extend_from_slice(&buf[..length]),
+                                                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                                                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                                                Err(e) => {
+                                                    println!("[TCP] Failed to read from {}: {}", address, e);
+                                                    closed = true;
+                                                    break;
+                                                }
+                                            }
+                                        }
+                                    }
+
+                                    if read.len() > 0 {
+                                        byte_sender.send((address, read)).unwrap();
+                                    }
+                                }
+
+                                if event.is_writable() && !closed {
+                                    connection.flush();
+                                }
+
+                                if event.is_error() {
+                                    closed = true;
+                                }
+                            }
+                            None => {
+                                // the game loop removed this connection
+                                tokens.remove(&token);
+                            }
+                        }
+
+                        if closed {
+                            println!("[TCP] {} disconnected", address);
+                            tokens.remove(&token);
+                            connection_manager.remove(&address);
+                        }
+                    }
                 }
-                Err(_) => {}
             }
         }
     }
 
     // Bedrock Edition uses UDP
-    fn start_udp(connection_manager: Arc<ConnectionManager>, byte_sender: Sender<(SocketAddr, Vec<u8>)>) {
-        let socket = Arc::new(UdpSocket::bind("0.0.0.0:19132").unwrap());
-        println!("[UDP] Binding server to 0.0.0.0:19132");
+    fn start_udp(address: SocketAddr, connection_manager: Arc<ConnectionManager>, byte_sender: Sender<(SocketAddr, Vec<u8>)>) {
+        // big enough for any MTU a client will try, including the padded OpenConnectionRequest1Packet
+        const UDP_BUFFER_SIZE: usize = 2048;
+        let socket = Arc::new(UdpSocket::bind(address).unwrap());
+        println!("[UDP] Binding server to {}", address);
 
         loop {
-            let mut buf = vec![0; 64];
+            let mut buf = vec![0; UDP_BUFFER_SIZE];
             let (length, address) = socket.recv_from(&mut buf).unwrap();
             if length > 0 {
                 let buf = (&mut buf[..length]).to_vec();
@@ -201,6 +313,7 @@ impl NetworkManager {
                     println!("[UDP] Accepted new connection from {}", address);
                     let mut connection = Connection::new(address, SocketWrapper::UDP(socket.clone()));
                     connection_manager.connections.insert(address, connection);
+                    connection_manager.udp_addresses.lock().unwrap().push(address);
                 }
                 byte_sender.send((address, buf)).unwrap();
             }
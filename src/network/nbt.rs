@@ -0,0 +1,725 @@
+use std::collections::BTreeMap;
+
+use network::packet::*;
+use network::compression;
+
+pub const TAG_END: u8 = 0;
+pub const TAG_BYTE: u8 = 1;
+pub const TAG_SHORT: u8 = 2;
+pub const TAG_INT: u8 = 3;
+pub const TAG_LONG: u8 = 4;
+pub const TAG_FLOAT: u8 = 5;
+pub const TAG_DOUBLE: u8 = 6;
+pub const TAG_BYTE_ARRAY: u8 = 7;
+pub const TAG_STRING: u8 = 8;
+pub const TAG_LIST: u8 = 9;
+pub const TAG_COMPOUND: u8 = 10;
+pub const TAG_INT_ARRAY: u8 = 11;
+pub const TAG_LONG_ARRAY: u8 = 12;
+
+/// Vanilla refuses to nest compounds & lists deeper than this
+pub const MAX_DEPTH: usize = 512;
+/// Compressed NBT files are rejected if they inflate to more than this
+pub const MAX_FILE_SIZE: usize = 1 << 26;
+/// Compressed NBT in packets is rejected if it inflates to more than this, same as vanilla's `NBTSizeTracker`
+pub const MAX_NETWORK_SIZE: usize = 1 << 21;
+
+/// How numbers & lengths are laid out
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum NbtEncoding {
+    /// Java Edition, for both files & packets. Strings are modified UTF-8
+    BigEndian,
+    /// Bedrock Edition files like `level.dat`
+    LittleEndian,
+    /// Bedrock Edition packets. Ints & longs are ZigZag VarInts and lengths are VarInts
+    Network,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Tag {
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    /// Every element must have the same type
+    List(Vec<Tag>),
+    Compound(BTreeMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Default for Tag {
+    fn default() -> Self {
+        Tag::End
+    }
+}
+
+impl Tag {
+    pub fn id(&self) -> u8 {
+        match self {
+            Tag::End => TAG_END,
+            Tag::Byte(_) => TAG_BYTE,
+            Tag::Short(_) => TAG_SHORT,
+            Tag::Int(_) => TAG_INT,
+            Tag::Long(_) => TAG_LONG,
+            Tag::Float(_) => TAG_FLOAT,
+            Tag::Double(_) => TAG_DOUBLE,
+            Tag::ByteArray(_) => TAG_BYTE_ARRAY,
+            Tag::String(_) => TAG_STRING,
+            Tag::List(_) => TAG_LIST,
+            Tag::Compound(_) => TAG_COMPOUND,
+            Tag::IntArray(_) => TAG_INT_ARRAY,
+            Tag::LongArray(_) => TAG_LONG_ARRAY,
+        }
+    }
+
+    /// Looks up `key` if this is a compound
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(key),
+            _ => None
+        }
+    }
+}
+
+/// A named root tag. Java Edition sends a lone `TAG_End` for "no NBT",
+/// which reads as an `Nbt` with an empty name & `Tag::End`.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Nbt {
+    pub name: String,
+    pub tag: Tag,
+}
+
+/// Bedrock Edition NBT in little-endian file encoding, for use in packets
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct LittleEndianNbt(pub Nbt);
+
+/// Bedrock Edition NBT in network encoding, for use in packets
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct NetworkNbt(pub Nbt);
+
+/// Java Edition 1.7 NBT, gzip compressed & prefixed by its length as a short.
+/// A length of -1 means there's no NBT.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct GzipNbt(pub Nbt);
+
+impl Nbt {
+    pub fn new(name: &str, tag: Tag) -> Nbt {
+        Nbt { name: name.to_string(), tag }
+    }
+
+    /// Returns the root tag & how many bytes it took up
+    pub fn decode(bytes: &[u8], encoding: NbtEncoding) -> Result<(Nbt, usize), DecodeError> {
+        let mut reader = NbtReader { bytes, index: 0, encoding };
+        let id = reader.read_u8()?;
+        if id == TAG_END {
+            return Ok((Nbt::default(), 1));
+        }
+        let name = reader.read_string()?;
+        let tag = reader.read_tag(id, 0)?;
+        Ok((Nbt { name, tag }, reader.index))
+    }
+
+    pub fn encode(&self, encoding: NbtEncoding) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_to(encoding, &mut buf);
+        buf
+    }
+
+    /// Appends the encoded tag to `buf`
+    pub fn encode_to(&self, encoding: NbtEncoding, buf: &mut Vec<u8>) {
+        let mut writer = NbtWriter { bytes: buf, encoding };
+        writer.bytes.push(self.tag.id());
+        if self.tag.id() != TAG_END {
+            writer.write_string(&self.name);
+            writer.write_tag(&self.tag);
+        }
+    }
+
+    /// Reads a gzip compressed file, like `level.dat` or `servers.dat`
+    pub fn read_gzip(bytes: &[u8], encoding: NbtEncoding) -> Option<Nbt> {
+        let bytes = compression::decompress_gzip(bytes, MAX_FILE_SIZE)?;
+        Nbt::decode(&bytes, encoding).ok().map(|(nbt, _)| nbt)
+    }
+
+    pub fn write_gzip(&self, encoding: NbtEncoding) -> Vec<u8> {
+        compression::compress_gzip(&self.encode(encoding))
+    }
+
+    /// Reads zlib compressed data, like chunks in region files
+    pub fn read_zlib(bytes: &[u8], encoding: NbtEncoding) -> Option<Nbt> {
+        let bytes = compression::decompress(bytes, MAX_FILE_SIZE)?;
+        Nbt::decode(&bytes, encoding).ok().map(|(nbt, _)| nbt)
+    }
+
+    pub fn write_zlib(&self, encoding: NbtEncoding) -> Vec<u8> {
+        compression::compress(&self.encode(encoding))
+    }
+}
+
+struct NbtReader<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    encoding: NbtEncoding,
+}
+
+impl<'a> NbtReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], DecodeError> {
+        let slice = read_slice(self.bytes, self.index, length)?;
+        self.index += length;
+        Ok(slice)
+    }
+
+    fn read_array<T: Default + AsMut<[u8]>>(&mut self) -> Result<T, DecodeError> {
+        let mut array = T::default();
+        let length = array.as_mut().len();
+        array.as_mut().copy_from_slice(self.take(length)?);
+        if self.encoding == NbtEncoding::BigEndian {
+            array.as_mut().reverse();
+        }
+        // arrays are now little-endian
+        Ok(array)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn read_var_u64(&mut self, max_bytes: usize) -> Result<u64, DecodeError> {
+        let mut result = 0u64;
+        for i in 0..max_bytes {
+            let byte = self.read_u8()?;
+            result |= ((byte & 0x7F) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(DecodeError::VarIntTooLong)
+    }
+
+    fn read_i16(&mut self) -> Result<i16, DecodeError> {
+        self.read_array::<[u8; 2]>().map(i16::from_le_bytes)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, DecodeError> {
+        if self.encoding == NbtEncoding::Network {
+            let value = self.read_var_u64(5)? as u32;
+            Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+        } else {
+            self.read_array::<[u8; 4]>().map(i32::from_le_bytes)
+        }
+    }
+
+    fn read_i64(&mut self) -> Result<i64, DecodeError> {
+        if self.encoding == NbtEncoding::Network {
+            let value = self.read_var_u64(10)?;
+            Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+        } else {
+            self.read_array::<[u8; 8]>().map(i64::from_le_bytes)
+        }
+    }
+
+    /// Array & list lengths. Anything that couldn't fit in the remaining bytes is rejected
+    /// before allocating.
+    fn read_length(&mut self) -> Result<usize, DecodeError> {
+        let length = self.read_i32()?;
+        if length < 0 || length as usize > self.bytes.len() - self.index {
+            return Err(DecodeError::LengthExceedsLimit);
+        }
+        Ok(length as usize)
+    }
+
+    /// How many elements to reserve space for up front. Lists & compounds nest, so lists of them
+    /// grow as they're read instead of reserving at every level before any child is read.
+    fn capacity(&self, length: usize, element_id: u8) -> usize {
+        let remaining = self.bytes.len() - self.index;
+        match min_encoded_size(element_id, self.encoding) {
+            Some(size) => length.min(remaining / size),
+            None => 0
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, DecodeError> {
+        let length = match self.encoding {
+            NbtEncoding::Network => {
+                let length = self.read_var_u64(5)?;
+                if length > i16::MAX as u64 {
+                    return Err(DecodeError::LengthExceedsLimit);
+                }
+                length as usize
+            }
+            _ => self.read_i16()? as u16 as usize
+        };
+        let bytes = self.take(length)?;
+        match self.encoding {
+            NbtEncoding::BigEndian => from_modified_utf8(bytes).ok_or(DecodeError::InvalidUtf8),
+            _ => String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+        }
+    }
+
+    fn read_tag(&mut self, id: u8, depth: usize) -> Result<Tag, DecodeError> {
+        if depth > MAX_DEPTH {
+            return Err(DecodeError::LengthExceedsLimit);
+        }
+        Ok(match id {
+            TAG_BYTE => Tag::Byte(self.read_u8()? as i8),
+            TAG_SHORT => Tag::Short(self.read_i16()?),
+            TAG_INT => Tag::Int(self.read_i32()?),
+            TAG_LONG => Tag::Long(self.read_i64()?),
+            TAG_FLOAT => Tag::Float(self.read_array::<[u8; 4]>().map(f32::from_le_bytes)?),
+            TAG_DOUBLE => Tag::Double(self.read_array::<[u8; 8]>().map(f64::from_le_bytes)?),
+            TAG_BYTE_ARRAY => {
+                let length = self.read_length()?;
+                Tag::ByteArray(self.take(length)?.iter().map(|b| *b as i8).collect())
+            }
+            TAG_STRING => Tag::String(self.read_string()?),
+            TAG_LIST => {
+                let element_id = self.read_u8()?;
+                let length = self.read_length()?;
+                if element_id == TAG_END && length > 0 {
+                    return Err(DecodeError::UnknownVariant);
+                }
+                let mut list = Vec::with_capacity(self.capacity(length, element_id));
+                for _ in 0..length {
+                    list.push(self.read_tag(element_id, depth + 1)?);
+                }
+                Tag::List(list)
+            }
+            TAG_COMPOUND => {
+                let mut map = BTreeMap::new();
+                loop {
+                    let id = self.read_u8()?;
+                    if id == TAG_END {
+                        break;
+                    }
+                    let name = self.read_string()?;
+                    let tag = self.read_tag(id, depth + 1)?;
+                    map.insert(name, tag);
+                }
+                Tag::Compound(map)
+            }
+            TAG_INT_ARRAY => {
+                let length = self.read_length()?;
+                let mut array = Vec::with_capacity(self.capacity(length, TAG_INT));
+                for _ in 0..length {
+                    array.push(self.read_i32()?);
+                }
+                Tag::IntArray(array)
+            }
+            TAG_LONG_ARRAY => {
+                let length = self.read_length()?;
+                let mut array = Vec::with_capacity(self.capacity(length, TAG_LONG));
+                for _ in 0..length {
+                    array.push(self.read_i64()?);
+                }
+                Tag::LongArray(array)
+            }
+            _ => return Err(DecodeError::UnknownVariant)
+        })
+    }
+}
+
+/// The fewest bytes a tag's payload can take up, or `None` for tags that can contain other tags
+fn min_encoded_size(id: u8, encoding: NbtEncoding) -> Option<usize> {
+    let network = encoding == NbtEncoding::Network;
+    match id {
+        TAG_BYTE => Some(1),
+        TAG_SHORT => Some(2),
+        // VarInts can be a single byte
+        TAG_INT | TAG_LONG if network => Some(1),
+        TAG_INT | TAG_FLOAT => Some(4),
+        TAG_LONG | TAG_DOUBLE => Some(8),
+        TAG_STRING if network => Some(1),
+        TAG_STRING => Some(2),
+        TAG_BYTE_ARRAY | TAG_INT_ARRAY | TAG_LONG_ARRAY if network => Some(1),
+        TAG_BYTE_ARRAY | TAG_INT_ARRAY | TAG_LONG_ARRAY => Some(4),
+        _ => None
+    }
+}
+
+struct NbtWriter<'a> {
+    bytes: &'a mut Vec<u8>,
+    encoding: NbtEncoding,
+}
+
+impl<'a> NbtWriter<'a> {
+    /// `bytes` are little-endian
+    fn write_array(&mut self, bytes: &[u8]) {
+        if self.encoding == NbtEncoding::BigEndian {
+            self.bytes.extend(bytes.iter().rev());
+        } else {
+            self.bytes.extend_from_slice(bytes);
+        }
+    }
+
+    fn write_var_u64(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn write_i32(&mut self, value: i32) {
+        if self.encoding == NbtEncoding::Network {
+            self.write_var_u64(((value << 1) ^ (value >> 31)) as u32 as u64);
+        } else {
+            self.write_array(&value.to_le_bytes());
+        }
+    }
+
+    fn write_i64(&mut self, value: i64) {
+        if self.encoding == NbtEncoding::Network {
+            self.write_var_u64(((value << 1) ^ (value >> 63)) as u64);
+        } else {
+            self.write_array(&value.to_le_bytes());
+        }
+    }
+
+    fn write_string(&mut self, string: &str) {
+        let bytes = match self.encoding {
+            NbtEncoding::BigEndian => to_modified_utf8(string),
+            _ => string.as_bytes().to_vec()
+        };
+        match self.encoding {
+            NbtEncoding::Network => self.write_var_u64(bytes.len() as u64),
+            _ => self.write_array(&(bytes.len() as u16).to_le_bytes())
+        }
+        self.bytes.extend(bytes);
+    }
+
+    fn write_tag(&mut self, tag: &Tag) {
+        match tag {
+            Tag::End => {}
+            Tag::Byte(value) => self.bytes.push(*value as u8),
+            Tag::Short(value) => self.write_array(&value.to_le_bytes()),
+            Tag::Int(value) => self.write_i32(*value),
+            Tag::Long(value) => self.write_i64(*value),
+            Tag::Float(value) => self.write_array(&value.to_le_bytes()),
+            Tag::Double(value) => self.write_array(&value.to_le_bytes()),
+            Tag::ByteArray(array) => {
+                self.write_i32(array.len() as i32);
+                self.bytes.extend(array.iter().map(|b| *b as u8));
+            }
+            Tag::String(string) => self.write_string(string),
+            Tag::List(list) => {
+                // empty lists are written with TAG_End as the element type, like vanilla does
+                self.bytes.push(list.first().map(Tag::id).unwrap_or(TAG_END));
+                self.write_i32(list.len() as i32);
+                for element in list {
+                    self.write_tag(element);
+                }
+            }
+            Tag::Compound(map) => {
+                for (name, tag) in map {
+                    self.bytes.push(tag.id());
+                    self.write_string(name);
+                    self.write_tag(tag);
+                }
+                self.bytes.push(TAG_END);
+            }
+            Tag::IntArray(array) => {
+                self.write_i32(array.len() as i32);
+                for value in array {
+                    self.write_i32(*value);
+                }
+            }
+            Tag::LongArray(array) => {
+                self.write_i32(array.len() as i32);
+                for value in array {
+                    self.write_i64(*value);
+                }
+            }
+        }
+    }
+}
+
+/// Java's modified UTF-8 writes NUL as 2 bytes & supplementary characters as surrogate pairs
+fn to_modified_utf8(string: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(string.len());
+    for unit in string.encode_utf16() {
+        match unit {
+            0x01..=0x7F => bytes.push(unit as u8),
+            0x00 | 0x80..=0x7FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+    bytes
+}
+
+fn from_modified_utf8(bytes: &[u8]) -> Option<String> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i] as u16;
+        let continuation = |offset: usize| -> Option<u16> {
+            match bytes.get(i + offset) {
+                Some(b) if b & 0xC0 == 0x80 => Some((b & 0x3F) as u16),
+                _ => None
+            }
+        };
+        if byte < 0x80 {
+            units.push(byte);
+            i += 1;
+        } else if byte & 0xE0 == 0xC0 {
+            units.push((byte & 0x1F) << 6 | continuation(1)?);
+            i += 2;
+        } else if byte & 0xF0 == 0xE0 {
+            units.push((byte & 0x0F) << 12 | continuation(1)? << 6 | continuation(2)?);
+            i += 3;
+        } else {
+            return None;
+        }
+    }
+    String::from_utf16(&units).ok()
+}
+
+impl ReadField for Nbt {
+    fn read(bytes: &[u8], index: usize) -> Result<(Nbt, usize), DecodeError> {
+        Nbt::decode(bytes.get(index..).ok_or(DecodeError::UnexpectedEof)?, NbtEncoding::BigEndian)
+    }
+}
+
+impl WriteField for Nbt {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        self.encode_to(NbtEncoding::BigEndian, buf);
+    }
+}
+
+impl ReadField for LittleEndianNbt {
+    fn read(bytes: &[u8], index: usize) -> Result<(LittleEndianNbt, usize), DecodeError> {
+        Nbt::decode(bytes.get(index..).ok_or(DecodeError::UnexpectedEof)?, NbtEncoding::LittleEndian).map(|(nbt, length)| (LittleEndianNbt(nbt), length))
+    }
+}
+
+impl WriteField for LittleEndianNbt {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        self.0.encode_to(NbtEncoding::LittleEndian, buf);
+    }
+}
+
+impl ReadField for NetworkNbt {
+    fn read(bytes: &[u8], index: usize) -> Result<(NetworkNbt, usize), DecodeError> {
+        Nbt::decode(bytes.get(index..).ok_or(DecodeError::UnexpectedEof)?, NbtEncoding::Network).map(|(nbt, length)| (NetworkNbt(nbt), length))
+    }
+}
+
+impl WriteField for NetworkNbt {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        self.0.encode_to(NbtEncoding::Network, buf);
+    }
+}
+
+impl ReadField for GzipNbt {
+    fn read(bytes: &[u8], index: usize) -> Result<(GzipNbt, usize), DecodeError> {
+        let (length, size) = <i16 as ReadField>::read(bytes, index)?;
+        if length < 0 {
+            return Ok((GzipNbt(Nbt::default()), size));
+        }
+        let compressed = read_slice(bytes, index + size, length as usize)?;
+        // compressed NBT that fails to inflate is as unreadable as a bad tag
+        let inflated = compression::decompress_gzip(compressed, MAX_NETWORK_SIZE).ok_or(DecodeError::UnknownVariant)?;
+        let (nbt, _) = Nbt::decode(&inflated, NbtEncoding::BigEndian)?;
+        Ok((GzipNbt(nbt), size + length as usize))
+    }
+}
+
+impl WriteField for GzipNbt {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        if self.0.tag == Tag::End {
+            (-1i16).write_to(buf);
+        } else {
+            let compressed = self.0.write_gzip(NbtEncoding::BigEndian);
+            if compressed.len() > i16::MAX as usize {
+                // the length can't be written, so the NBT is left out rather than sent corrupted
+                println!("Not writing NBT that compresses to {} bytes, the most a 1.7 packet can hold is {}", compressed.len(), i16::MAX);
+                (-1i16).write_to(buf);
+                return;
+            }
+            (compressed.len() as i16).write_to(buf);
+            buf.extend_from_slice(&compressed);
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCODINGS: [NbtEncoding; 3] = [NbtEncoding::BigEndian, NbtEncoding::LittleEndian, NbtEncoding::Network];
+
+    /// A compound holding every kind of tag
+    fn every_tag() -> Nbt {
+        let mut nested = BTreeMap::new();
+        nested.insert("name".to_string(), Tag::String("Bananrama".to_string()));
+        nested.insert("empty".to_string(), Tag::Compound(BTreeMap::new()));
+
+        let mut root = BTreeMap::new();
+        root.insert("byte".to_string(), Tag::Byte(-128));
+        root.insert("short".to_string(), Tag::Short(-32768));
+        root.insert("int".to_string(), Tag::Int(i32::MIN));
+        root.insert("long".to_string(), Tag::Long(i64::MAX));
+        root.insert("float".to_string(), Tag::Float(0.49823147));
+        root.insert("double".to_string(), Tag::Double(-0.4931287132182315));
+        root.insert("byte array".to_string(), Tag::ByteArray(vec![0, -1, 127, -128]));
+        root.insert("string".to_string(), Tag::String("HELLO WORLD THIS IS A TEST STRING ÅÄÖ!".to_string()));
+        root.insert("list".to_string(), Tag::List(vec![Tag::Long(11), Tag::Long(-12), Tag::Long(13)]));
+        root.insert("empty list".to_string(), Tag::List(vec![]));
+        root.insert("list of compounds".to_string(), Tag::List(vec![Tag::Compound(nested.clone()), Tag::Compound(BTreeMap::new())]));
+        root.insert("compound".to_string(), Tag::Compound(nested));
+        root.insert("int array".to_string(), Tag::IntArray(vec![-1, 0, 1, i32::MAX]));
+        root.insert("long array".to_string(), Tag::LongArray(vec![i64::MIN, -1, 0, 1]));
+        Nbt::new("Level", Tag::Compound(root))
+    }
+
+    #[test]
+    fn every_tag_round_trips() {
+        let nbt = every_tag();
+        for &encoding in ENCODINGS.iter() {
+            let bytes = nbt.encode(encoding);
+            let (decoded, length) = Nbt::decode(&bytes, encoding).unwrap();
+            assert_eq!(decoded, nbt, "{:?}", encoding);
+            assert_eq!(length, bytes.len(), "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn encodings_lay_out_numbers_differently() {
+        let nbt = Nbt::new("", Tag::Int(-2));
+        assert_eq!(nbt.encode(NbtEncoding::BigEndian), vec![TAG_INT, 0, 0, 0xFF, 0xFF, 0xFF, 0xFE]);
+        assert_eq!(nbt.encode(NbtEncoding::LittleEndian), vec![TAG_INT, 0, 0, 0xFE, 0xFF, 0xFF, 0xFF]);
+        // ZigZag VarInt, with a VarInt string length
+        assert_eq!(nbt.encode(NbtEncoding::Network), vec![TAG_INT, 0, 0x03]);
+    }
+
+    #[test]
+    fn empty_nbt_is_a_lone_end_tag() {
+        for &encoding in ENCODINGS.iter() {
+            assert_eq!(Nbt::default().encode(encoding), vec![TAG_END]);
+            assert_eq!(Nbt::decode(&[TAG_END], encoding).unwrap(), (Nbt::default(), 1));
+        }
+    }
+
+    #[test]
+    fn modified_utf8() {
+        // NUL takes 2 bytes & characters outside the BMP are written as surrogate pairs
+        let string = "a\u{0}é€😀";
+        let bytes = to_modified_utf8(string);
+        assert_eq!(bytes, vec![0x61, 0xC0, 0x80, 0xC3, 0xA9, 0xE2, 0x82, 0xAC, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+        assert_eq!(from_modified_utf8(&bytes), Some(string.to_string()));
+
+        // the other encodings use standard UTF-8
+        let nbt = Nbt::new(string, Tag::String(string.to_string()));
+        for &encoding in ENCODINGS.iter() {
+            assert_eq!(Nbt::decode(&nbt.encode(encoding), encoding).unwrap().0, nbt);
+        }
+        assert!(nbt.encode(NbtEncoding::LittleEndian).windows(4).any(|w| w == "😀".as_bytes()));
+
+        // a lone continuation byte or a missing one is invalid
+        assert_eq!(from_modified_utf8(&[0x80]), None);
+        assert_eq!(from_modified_utf8(&[0xC3]), None);
+    }
+
+    fn nested_lists(depth: usize) -> Nbt {
+        let mut tag = Tag::List(vec![]);
+        for _ in 0..depth {
+            tag = Tag::List(vec![tag]);
+        }
+        Nbt::new("", tag)
+    }
+
+    #[test]
+    fn depth_limit() {
+        for &encoding in ENCODINGS.iter() {
+            let nbt = nested_lists(MAX_DEPTH);
+            assert_eq!(Nbt::decode(&nbt.encode(encoding), encoding).unwrap().0, nbt);
+
+            let bytes = nested_lists(MAX_DEPTH + 1).encode(encoding);
+            assert_eq!(Nbt::decode(&bytes, encoding), Err(DecodeError::LengthExceedsLimit));
+        }
+    }
+
+    #[test]
+    fn truncated_input_is_rejected() {
+        for &encoding in ENCODINGS.iter() {
+            let bytes = every_tag().encode(encoding);
+            for length in 0..bytes.len() {
+                assert!(Nbt::decode(&bytes[..length], encoding).is_err(), "{:?} cut at {}", encoding, length);
+            }
+        }
+    }
+
+    #[test]
+    fn lengths_longer_than_the_input_are_rejected() {
+        // a byte array claiming i32::MAX elements
+        let bytes = [TAG_BYTE_ARRAY, 0, 0, 0x7F, 0xFF, 0xFF, 0xFF, 1, 2];
+        assert_eq!(Nbt::decode(&bytes, NbtEncoding::BigEndian), Err(DecodeError::LengthExceedsLimit));
+        // a list of TAG_End with elements
+        let bytes = [TAG_LIST, 0, 0, TAG_END, 0, 0, 0, 1, 0];
+        assert_eq!(Nbt::decode(&bytes, NbtEncoding::BigEndian), Err(DecodeError::UnknownVariant));
+    }
+
+    #[test]
+    fn compressed_round_trips() {
+        let nbt = every_tag();
+        for &encoding in ENCODINGS.iter() {
+            assert_eq!(Nbt::read_gzip(&nbt.write_gzip(encoding), encoding), Some(nbt.clone()));
+            assert_eq!(Nbt::read_zlib(&nbt.write_zlib(encoding), encoding), Some(nbt.clone()));
+        }
+        assert_eq!(Nbt::read_gzip(&[0x1F, 0x8B, 0], NbtEncoding::BigEndian), None);
+    }
+
+    #[test]
+    fn packet_fields_round_trip() {
+        let nbt = every_tag();
+
+        let bytes = LittleEndianNbt(nbt.clone()).write();
+        assert_eq!(LittleEndianNbt::read(&bytes, 0).unwrap(), (LittleEndianNbt(nbt.clone()), bytes.len()));
+
+        let bytes = NetworkNbt(nbt.clone()).write();
+        assert_eq!(NetworkNbt::read(&bytes, 0).unwrap(), (NetworkNbt(nbt.clone()), bytes.len()));
+
+        let bytes = GzipNbt(nbt.clone()).write();
+        assert_eq!(GzipNbt::read(&bytes, 0).unwrap(), (GzipNbt(nbt), bytes.len()));
+
+        // no NBT is a length of -1
+        let bytes = GzipNbt::default().write();
+        assert_eq!(bytes, vec![0xFF, 0xFF]);
+        assert_eq!(GzipNbt::read(&bytes, 0).unwrap(), (GzipNbt::default(), 2));
+    }
+
+    #[test]
+    fn gzip_field_limits() {
+        // compresses well but inflates past the network limit
+        let bomb = Nbt::new("", Tag::ByteArray(vec![0; MAX_NETWORK_SIZE]));
+        let bytes = GzipNbt(bomb.clone()).write();
+        assert!(bytes.len() < i16::MAX as usize);
+        assert!(GzipNbt::read(&bytes, 0).is_err());
+        // files can still be that big
+        assert_eq!(Nbt::read_gzip(&bomb.write_gzip(NbtEncoding::BigEndian), NbtEncoding::BigEndian), Some(bomb));
+
+        // doesn't compress, so the length wouldn't fit in a short
+        let noise = Nbt::new("", Tag::ByteArray((0..40000).map(|_| rand::random::<i8>()).collect()));
+        assert_eq!(GzipNbt(noise).write(), vec![0xFF, 0xFF]);
+    }
+}
@@ -1,22 +1,82 @@
+use std::fmt;
+
 use network::protocol;
 
 pub trait PacketType: Send + Sync {
     fn name(&self) -> &str;
-    fn read(&mut self, bytes: Vec<u8>) -> bool;
-    fn write(&self) -> Vec<u8>;
+    /// Returns how many bytes the packet's fields took up
+    fn read(&mut self, bytes: &[u8]) -> Result<usize, DecodeError>;
+    /// Appends the packet's fields to `buf`
+    fn write_to(&self, buf: &mut Vec<u8>);
+    /// Roughly how many bytes `write_to` adds
+    fn size_hint(&self) -> usize;
+
+    fn write(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.size_hint());
+        self.write_to(&mut buf);
+        buf
+    }
     fn next_state(&self) -> Option<protocol::State>;
 }
 
+/// Why a field couldn't be read
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The bytes ended early. On a stream this may just mean more data is on the way
+    UnexpectedEof,
+    /// A VarInt kept going past its maximum size
+    VarIntTooLong,
+    InvalidUtf8,
+    /// A length prefix was negative or bigger than we allow
+    LengthExceedsLimit,
+    /// An id or enum value that we don't know about
+    UnknownVariant,
+    /// A packet was read without using all of its bytes
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEof => write!(f, "unexpected end of data"),
+            DecodeError::VarIntTooLong => write!(f, "VarInt is too long"),
+            DecodeError::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            DecodeError::LengthExceedsLimit => write!(f, "length exceeds limit"),
+            DecodeError::UnknownVariant => write!(f, "unknown variant"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after packet"),
+        }
+    }
+}
+
 /// Read type from bytes
 pub trait ReadField {
     /// returns the type & the length to increment the index by
-    fn read(bytes: &Vec<u8>, index: usize) -> Option<(Self, usize)> where Self: Sized;
+    fn read(bytes: &[u8], index: usize) -> Result<(Self, usize), DecodeError> where Self: Sized;
+}
+
+/// Returns the `length` bytes starting at `index`
+pub fn read_slice(bytes: &[u8], index: usize, length: usize) -> Result<&[u8], DecodeError> {
+    index.checked_add(length)
+        .and_then(|end| bytes.get(index..end))
+        .ok_or(DecodeError::UnexpectedEof)
 }
 
 /// Write type to bytes
 pub trait WriteField where Self: Sized {
-    // TODO: Vec is probably going to kill performance
-    fn write(&self) -> Vec<u8>;
+    /// Appends the field to `buf`
+    fn write_to(&self, buf: &mut Vec<u8>);
+
+    /// Roughly how many bytes `write_to` adds, so buffers can be allocated once.
+    /// It's fine to guess low for things like compressed or variable length data.
+    fn size_hint(&self) -> usize {
+        0
+    }
+
+    fn write(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.size_hint());
+        self.write_to(&mut buf);
+        buf
+    }
 }
 
 #[macro_export]
@@ -51,28 +111,26 @@ macro_rules! packet {
 
             #[allow(unused_assignments)]
             #[allow(unused_variables)]
-            fn read(&mut self, bytes: Vec<u8>) -> bool {
+            fn read(&mut self, bytes: &[u8]) -> Result<usize, DecodeError> {
                 #[allow(unused_mut)]
                 let mut index = 0usize;
                 $(
-                    match <$t as ReadField>::read(&bytes, index) {
-                        Some((value, length)) => {
-                            self.$field = value;
-                            index += length;
-                        },
-                        None => return false
-                    }
+                    let (value, length) = <$t as ReadField>::read(bytes, index)?;
+                    self.$field = value;
+                    index += length;
                 )*
-                true
+                Ok(index)
             }
 
-            fn write(&self) -> Vec<u8> {
-                #[allow(unused_mut)]
-                let mut buf = Vec::<u8>::new();
+            #[allow(unused_variables)]
+            fn write_to(&self, buf: &mut Vec<u8>) {
                 $(
-                    buf.append(&mut self.$field.write());
+                    self.$field.write_to(buf);
                 )*
-                buf
+            }
+
+            fn size_hint(&self) -> usize {
+                0 $(+ self.$field.size_hint())*
             }
 
             fn next_state(&self) -> Option<State> {
@@ -81,3 +139,52 @@ macro_rules! packet {
         }
     };
 }
+
+/// A struct of fields that's read & written in order, for things like the records in a packet's array
+#[macro_export]
+macro_rules! field {
+    ($field_name:ident, $($field:ident: $t:ty),*) => {
+        #[derive(Clone, Default, Debug)]
+        pub struct $field_name {
+            $(
+                pub $field: $t,
+            )*
+        }
+
+        #[allow(dead_code)]
+        impl $field_name {
+            pub fn new($($field: $t,)*) -> Self {
+                Self {
+                    $(
+                        $field,
+                    )*
+                }
+            }
+        }
+
+        impl ReadField for $field_name {
+            fn read(bytes: &[u8], index: usize) -> Result<(Self, usize), DecodeError> {
+                let start_index = index;
+                #[allow(unused_mut)]
+                let mut index = index;
+                $(
+                    let ($field, length) = <$t as ReadField>::read(bytes, index)?;
+                    index += length;
+                )*
+                Ok(($field_name { $($field,)* }, index - start_index))
+            }
+        }
+
+        impl WriteField for $field_name {
+            fn write_to(&self, buf: &mut Vec<u8>) {
+                $(
+                    self.$field.write_to(buf);
+                )*
+            }
+
+            fn size_hint(&self) -> usize {
+                0 $(+ self.$field.size_hint())*
+            }
+        }
+    };
+}
@@ -1,3 +1,7 @@
+use network::compression::*;
+use network::packet::*;
+use network::types::VarUInt;
+
 pub mod raknet;
 pub mod v1_5;
 
@@ -8,3 +12,36 @@ pub const MAGIC: [u8; 16] = [
     0xFD, 0xFD, 0xFD, 0xFD,
     0x12, 0x34, 0x56, 0x78
 ];
+
+/// Id of the frame that wraps Minecraft packets
+pub const BATCH_ID: u8 = 0xFE;
+
+/// The packet header also holds the sender & target sub-client ids above these bits
+pub const PACKET_ID_MASK: i32 = 0x3FF;
+
+/// Inflates the body of a batch (without the 0xFE) and splits it into packets.
+/// Returns `None` if it can't be inflated or a packet runs past the end.
+pub fn read_batch(bytes: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let inflated = decompress(bytes, MAX_DECOMPRESSED_SIZE)?;
+
+    let mut packets = Vec::new();
+    let mut index = 0;
+    while index < inflated.len() {
+        let (length, size) = <VarUInt as ReadField>::read(&inflated, index).ok()?;
+        index += size;
+        packets.push(read_slice(&inflated, index, length.0 as usize).ok()?.to_vec());
+        index += length.0 as usize;
+    }
+    Some(packets)
+}
+
+/// Wraps packets (header & body) in a compressed batch.
+/// The 0xFE isn't included since the body may still need to be encrypted.
+pub fn write_batch(packets: &[Vec<u8>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for packet in packets {
+        buf.append(&mut VarUInt(packet.len() as u32).write());
+        buf.extend_from_slice(packet);
+    }
+    compress(&buf)
+}
@@ -15,7 +15,7 @@ protocol!("RakNet", ProtocolBedrockRakNet, ProtocolEdition::BedrockEdition, 9,
     0x10, BedrockRakNet, Clientbound, bedrock raknet ConnectionRequestAcceptedPacket,
     0x13, BedrockRakNet, Serverbound, bedrock raknet NewIncomingConnectionPacket,
     0x14, BedrockRakNet, None, bedrock raknet NoFreeIncomingConnectionsPacket,
-    0x15, BedrockRakNet, None, bedrock raknet DisconnectNotificationPacket,
+    0x15, BedrockRakNet, Any, bedrock raknet DisconnectNotificationPacket,
     0x17, BedrockRakNet, None, bedrock raknet ConnectionBannedPacket,
     0x19, BedrockRakNet, None, bedrock raknet IncompatibleProtocolPacket,
     0x1a, BedrockRakNet, None, bedrock raknet IpRecentlyConnectedPacket,
@@ -48,7 +48,7 @@ packet!(ConnectedPongPacket,
 packet!(OpenConnectionRequest1Packet,
     magic: RakNetMagic,
     protocol_version: u8,
-    mtu_data: FortySixZeros
+    mtu_padding: Padding
 );
 
 packet!(OpenConnectionReply1Packet,
@@ -92,6 +92,10 @@ packet!(NewIncomingConnectionPacket,
     system_addresses: Vec<Address>,
     client_timestamp: u64,
     server_timestamp: u64
+    ; |_s: &NewIncomingConnectionPacket| {
+        // Minecraft packets come in batches from now on
+        Some(State::BedrockMinecraft)
+    }
 );
 
 packet!(NoFreeIncomingConnectionsPacket,
@@ -0,0 +1,93 @@
+use packet::*;
+use protocol::*;
+
+// Packets for Minecraft Bedrock Edition Version 1.5 (protocol version 274)
+// Minecraft packets are sent inside of a batch (0xFE) frame, RakNet packets are still sent on their own
+// https://wiki.vg/Bedrock_Protocol
+
+protocol!("1.5", ProtocolBedrock_1_5, ProtocolEdition::BedrockEdition, 274,
+    // RakNet
+    0x00, BedrockRakNet, Serverbound, bedrock raknet ConnectedPingPacket,
+    0x03, BedrockRakNet, Clientbound, bedrock raknet ConnectedPongPacket,
+    0x15, BedrockRakNet, Any, bedrock raknet DisconnectNotificationPacket,
+    // Login
+    0x01, BedrockMinecraft, Serverbound, bedrock v1_5 LoginPacket,
+    0x02, BedrockMinecraft, Clientbound, bedrock v1_5 PlayStatusPacket,
+    0x03, BedrockMinecraft, Clientbound, bedrock v1_5 ServerToClientHandshakePacket,
+    0x04, BedrockMinecraft, Serverbound, bedrock v1_5 ClientToServerHandshakePacket,
+    0x05, BedrockMinecraft, Clientbound, bedrock v1_5 DisconnectPacket,
+    // Play
+    0x13, BedrockMinecraft, Serverbound, bedrock v1_5 MovePlayerPacket,
+    0x44, BedrockMinecraft, Serverbound, bedrock v1_5 MapInfoRequestPacket,
+    0x45, BedrockMinecraft, Serverbound, bedrock v1_5 RequestChunkRadiusPacket,
+    0x46, BedrockMinecraft, Clientbound, bedrock v1_5 ChunkRadiusUpdatedPacket
+);
+
+// PlayStatusPacket statuses
+pub const LOGIN_SUCCESS: i32 = 0;
+/// The client is older than the server
+pub const LOGIN_FAILED_CLIENT: i32 = 1;
+/// The server is older than the client
+pub const LOGIN_FAILED_SERVER: i32 = 2;
+pub const PLAYER_SPAWN: i32 = 3;
+pub const LOGIN_FAILED_SERVER_FULL: i32 = 7;
+
+// Login C->S
+
+packet!(LoginPacket,
+    // always big endian, so it can be read before we know which protocol the client is using
+    protocol_version: i32,
+    // chain & skin data
+    payload: VarIntLengthPrefixedByteArray
+);
+
+packet!(ClientToServerHandshakePacket,
+    // None
+);
+
+// Login S->C
+
+packet!(PlayStatusPacket,
+    status: i32
+);
+
+packet!(ServerToClientHandshakePacket,
+    jwt: VarIntLengthPrefixedString
+);
+
+packet!(DisconnectPacket,
+    hide_disconnect_screen: bool,
+    // the client only expects this if the screen isn't hidden
+    message: VarIntLengthPrefixedString
+);
+
+// Play C->S
+
+// the server also sends this, with teleport fields after `mode` when it's 2 (teleport)
+packet!(MovePlayerPacket,
+    runtime_entity_id: VarULong,
+    x: LittleEndian<f32>,
+    y: LittleEndian<f32>,
+    z: LittleEndian<f32>,
+    pitch: LittleEndian<f32>,
+    yaw: LittleEndian<f32>,
+    head_yaw: LittleEndian<f32>,
+    mode: u8,
+    on_ground: bool,
+    riding_runtime_entity_id: VarULong
+);
+
+packet!(MapInfoRequestPacket,
+    map_unique_id: ZigZagVarLong
+);
+
+packet!(RequestChunkRadiusPacket,
+    radius: ZigZagVarInt
+);
+
+// Play S->C
+
+// the radius the server will actually send, in chunks
+packet!(ChunkRadiusUpdatedPacket,
+    radius: ZigZagVarInt
+);
@@ -0,0 +1,18 @@
+use packet::*;
+use protocol::*;
+
+// Server list pings from before the 1.7 rewrite. Newer clients & monitoring tools still send these.
+// They aren't length prefixed, so they're read by the Connection instead of a protocol.
+// https://wiki.vg/Server_List_Ping#1.6
+
+// Handshake C->S
+
+// 0xFE 0x01, sent by 1.4 to 1.6 (which adds a plugin message after it)
+packet!(LegacyPingPacket,
+    // None
+);
+
+// Just 0xFE, sent by Beta 1.8 to 1.3
+packet!(BetaPingPacket,
+    // None
+);
@@ -1,3 +1,4 @@
+pub mod legacy;
 pub mod v1_7;
 pub mod v1_8;
 pub mod v1_9;
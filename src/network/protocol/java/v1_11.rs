@@ -14,12 +14,15 @@ protocol!("1.11", ProtocolJava_1_11, ProtocolEdition::JavaEdition, 316,
     0x01, JavaStatus, Serverbound, java v1_7 PingPacket,
     // Login
     0x00, JavaLogin, Serverbound, java v1_7 LoginStartPacket,
-    0x01, JavaLogin, Serverbound, java v1_7 EncryptionResponsePacket,
+    0x01, JavaLogin, Serverbound, java v1_8 EncryptionResponsePacket,
     0x00, JavaLogin, Clientbound, java v1_7 DisconnectPacket,
-    0x01, JavaLogin, Clientbound, java v1_7 EncryptionRequestPacket,
+    0x01, JavaLogin, Clientbound, java v1_8 EncryptionRequestPacket,
     0x02, JavaLogin, Clientbound, java v1_7 LoginSuccessPacket,
     0x03, JavaLogin, Clientbound, java v1_7 SetCompressionPacket,
     // Play
-    0x00, JavaPlay, Clientbound, java v1_7 KeepAlivePacket,
-    0x23, JavaPlay, Clientbound, java v1_9 JoinGamePacket
+    0x1F, JavaPlay, Clientbound, java v1_8 KeepAlivePacket,
+    0x0B, JavaPlay, Serverbound, java v1_8 KeepAlivePacket,
+    0x23, JavaPlay, Clientbound, java v1_9 JoinGamePacket,
+    0x43, JavaPlay, Clientbound, java v1_8 SpawnPositionPacket,
+    0x1A, JavaPlay, Clientbound, java v1_7 PlayDisconnectPacket
 );
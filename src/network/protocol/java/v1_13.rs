@@ -14,12 +14,15 @@ protocol!("1.13", ProtocolJava_1_13, ProtocolEdition::JavaEdition, 404,
     0x01, JavaStatus, Serverbound, java v1_7 PingPacket,
     // Login
     0x00, JavaLogin, Serverbound, java v1_7 LoginStartPacket,
-    0x01, JavaLogin, Serverbound, java v1_7 EncryptionResponsePacket,
+    0x01, JavaLogin, Serverbound, java v1_8 EncryptionResponsePacket,
     0x00, JavaLogin, Clientbound, java v1_7 DisconnectPacket,
-    0x01, JavaLogin, Clientbound, java v1_7 EncryptionRequestPacket,
+    0x01, JavaLogin, Clientbound, java v1_8 EncryptionRequestPacket,
     0x02, JavaLogin, Clientbound, java v1_7 LoginSuccessPacket,
     0x03, JavaLogin, Clientbound, java v1_7 SetCompressionPacket,
     // Play
-    0x00, JavaPlay, Clientbound, java v1_7 KeepAlivePacket,
-    0x25, JavaPlay, Clientbound, java v1_9 JoinGamePacket
+    0x21, JavaPlay, Clientbound, java v1_12 KeepAlivePacket,
+    0x0E, JavaPlay, Serverbound, java v1_12 KeepAlivePacket,
+    0x25, JavaPlay, Clientbound, java v1_9 JoinGamePacket,
+    0x49, JavaPlay, Clientbound, java v1_8 SpawnPositionPacket,
+    0x1B, JavaPlay, Clientbound, java v1_7 PlayDisconnectPacket
 );
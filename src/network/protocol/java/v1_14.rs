@@ -14,12 +14,33 @@ protocol!("19w04b", ProtocolJava_1_14, ProtocolEdition::JavaEdition, 457,
     0x01, JavaStatus, Serverbound, java v1_7 PingPacket,
     // Login
     0x00, JavaLogin, Serverbound, java v1_7 LoginStartPacket,
-    0x01, JavaLogin, Serverbound, java v1_7 EncryptionResponsePacket,
+    0x01, JavaLogin, Serverbound, java v1_8 EncryptionResponsePacket,
     0x00, JavaLogin, Clientbound, java v1_7 DisconnectPacket,
-    0x01, JavaLogin, Clientbound, java v1_7 EncryptionRequestPacket,
+    0x01, JavaLogin, Clientbound, java v1_8 EncryptionRequestPacket,
     0x02, JavaLogin, Clientbound, java v1_7 LoginSuccessPacket,
     0x03, JavaLogin, Clientbound, java v1_7 SetCompressionPacket,
     // Play
-    0x00, JavaPlay, Clientbound, java v1_7 KeepAlivePacket,
-    0x25, JavaPlay, Clientbound, java v1_9 JoinGamePacket
+    0x20, JavaPlay, Clientbound, java v1_12 KeepAlivePacket,
+    0x0F, JavaPlay, Serverbound, java v1_12 KeepAlivePacket,
+    0x25, JavaPlay, Clientbound, java v1_14 JoinGamePacket,
+    0x4D, JavaPlay, Clientbound, java v1_14 SpawnPositionPacket,
+    0x1A, JavaPlay, Clientbound, java v1_7 PlayDisconnectPacket
+);
+
+// Play S->C
+
+// difficulty moved to its own packet
+packet!(JoinGamePacket,
+    entity_id: i32,
+    game_mode: u8,
+    dimension: i32,
+    max_players: u8,
+    level_type: VarIntLengthPrefixedString,
+    view_distance: VarInt,
+    reduced_debug: u8
+);
+
+// the position is packed differently
+packet!(SpawnPositionPacket,
+    location: Position1_14
 );
@@ -1,5 +1,6 @@
 use packet::*;
 use protocol::*;
+use network::nbt::GzipNbt;
 
 // Packets for Minecraft Java Edition Version 1.7.10 (protocol version 5)
 // https://wiki.vg/index.php?title=Protocol&oldid=6003
@@ -19,9 +20,97 @@ protocol!("1.7", ProtocolJava_1_7, ProtocolEdition::JavaEdition, 5,
     0x01, JavaLogin, Clientbound, java v1_7 EncryptionRequestPacket,
     0x02, JavaLogin, Clientbound, java v1_7 LoginSuccessPacket,
     0x03, JavaLogin, Clientbound, java v1_7 SetCompressionPacket,
-    // Play
+    // Play S->C
     0x00, JavaPlay, Clientbound, java v1_7 KeepAlivePacket,
-    0x01, JavaPlay, Clientbound, java v1_7 JoinGamePacket
+    0x01, JavaPlay, Clientbound, java v1_7 JoinGamePacket,
+    0x02, JavaPlay, Clientbound, java v1_7 ClientboundChatMessagePacket,
+    0x03, JavaPlay, Clientbound, java v1_7 TimeUpdatePacket,
+    0x04, JavaPlay, Clientbound, java v1_7 EntityEquipmentPacket,
+    0x05, JavaPlay, Clientbound, java v1_7 SpawnPositionPacket,
+    0x06, JavaPlay, Clientbound, java v1_7 UpdateHealthPacket,
+    0x07, JavaPlay, Clientbound, java v1_7 RespawnPacket,
+    0x08, JavaPlay, Clientbound, java v1_7 ClientboundPlayerPositionAndLookPacket,
+    0x09, JavaPlay, Clientbound, java v1_7 ClientboundHeldItemChangePacket,
+    0x0A, JavaPlay, Clientbound, java v1_7 UseBedPacket,
+    0x0B, JavaPlay, Clientbound, java v1_7 ClientboundAnimationPacket,
+    0x0C, JavaPlay, Clientbound, java v1_7 SpawnPlayerPacket,
+    0x0D, JavaPlay, Clientbound, java v1_7 CollectItemPacket,
+    0x0E, JavaPlay, Clientbound, java v1_7 SpawnObjectPacket,
+    0x0F, JavaPlay, Clientbound, java v1_7 SpawnMobPacket,
+    0x10, JavaPlay, Clientbound, java v1_7 SpawnPaintingPacket,
+    0x11, JavaPlay, Clientbound, java v1_7 SpawnExperienceOrbPacket,
+    0x12, JavaPlay, Clientbound, java v1_7 EntityVelocityPacket,
+    0x13, JavaPlay, Clientbound, java v1_7 DestroyEntitiesPacket,
+    0x14, JavaPlay, Clientbound, java v1_7 EntityPacket,
+    0x15, JavaPlay, Clientbound, java v1_7 EntityRelativeMovePacket,
+    0x16, JavaPlay, Clientbound, java v1_7 EntityLookPacket,
+    0x17, JavaPlay, Clientbound, java v1_7 EntityLookAndRelativeMovePacket,
+    0x18, JavaPlay, Clientbound, java v1_7 EntityTeleportPacket,
+    0x19, JavaPlay, Clientbound, java v1_7 EntityHeadLookPacket,
+    0x1A, JavaPlay, Clientbound, java v1_7 EntityStatusPacket,
+    0x1B, JavaPlay, Clientbound, java v1_7 AttachEntityPacket,
+    0x1C, JavaPlay, Clientbound, java v1_7 EntityMetadataPacket,
+    0x1D, JavaPlay, Clientbound, java v1_7 EntityEffectPacket,
+    0x1E, JavaPlay, Clientbound, java v1_7 RemoveEntityEffectPacket,
+    0x1F, JavaPlay, Clientbound, java v1_7 SetExperiencePacket,
+    0x20, JavaPlay, Clientbound, java v1_7 EntityPropertiesPacket,
+    0x21, JavaPlay, Clientbound, java v1_7 ChunkDataPacket,
+    0x22, JavaPlay, Clientbound, java v1_7 MultiBlockChangePacket,
+    0x23, JavaPlay, Clientbound, java v1_7 BlockChangePacket,
+    0x24, JavaPlay, Clientbound, java v1_7 BlockActionPacket,
+    0x25, JavaPlay, Clientbound, java v1_7 BlockBreakAnimationPacket,
+    0x26, JavaPlay, Clientbound, java v1_7 MapChunkBulkPacket,
+    0x27, JavaPlay, Clientbound, java v1_7 ExplosionPacket,
+    0x28, JavaPlay, Clientbound, java v1_7 EffectPacket,
+    0x29, JavaPlay, Clientbound, java v1_7 SoundEffectPacket,
+    0x2A, JavaPlay, Clientbound, java v1_7 ParticlePacket,
+    0x2B, JavaPlay, Clientbound, java v1_7 ChangeGameStatePacket,
+    0x2C, JavaPlay, Clientbound, java v1_7 SpawnGlobalEntityPacket,
+    0x2D, JavaPlay, Clientbound, java v1_7 OpenWindowPacket,
+    0x2E, JavaPlay, Clientbound, java v1_7 CloseWindowPacket,
+    0x2F, JavaPlay, Clientbound, java v1_7 SetSlotPacket,
+    0x30, JavaPlay, Clientbound, java v1_7 WindowItemsPacket,
+    0x31, JavaPlay, Clientbound, java v1_7 WindowPropertyPacket,
+    0x32, JavaPlay, Clientbound, java v1_7 ConfirmTransactionPacket,
+    0x33, JavaPlay, Clientbound, java v1_7 UpdateSignPacket,
+    0x34, JavaPlay, Clientbound, java v1_7 MapsPacket,
+    0x35, JavaPlay, Clientbound, java v1_7 UpdateBlockEntityPacket,
+    0x36, JavaPlay, Clientbound, java v1_7 SignEditorOpenPacket,
+    0x37, JavaPlay, Clientbound, java v1_7 StatisticsPacket,
+    0x38, JavaPlay, Clientbound, java v1_7 PlayerListItemPacket,
+    0x39, JavaPlay, Clientbound, java v1_7 PlayerAbilitiesPacket,
+    0x3A, JavaPlay, Clientbound, java v1_7 ClientboundTabCompletePacket,
+    0x3B, JavaPlay, Clientbound, java v1_7 ScoreboardObjectivePacket,
+    0x3C, JavaPlay, Clientbound, java v1_7 UpdateScorePacket,
+    0x3D, JavaPlay, Clientbound, java v1_7 DisplayScoreboardPacket,
+    0x3E, JavaPlay, Clientbound, java v1_7 TeamsPacket,
+    0x3F, JavaPlay, Clientbound, java v1_7 PluginMessagePacket,
+    0x40, JavaPlay, Clientbound, java v1_7 PlayDisconnectPacket,
+    // Play C->S
+    0x00, JavaPlay, Serverbound, java v1_7 KeepAlivePacket,
+    0x01, JavaPlay, Serverbound, java v1_7 ServerboundChatMessagePacket,
+    0x02, JavaPlay, Serverbound, java v1_7 UseEntityPacket,
+    0x03, JavaPlay, Serverbound, java v1_7 PlayerPacket,
+    0x04, JavaPlay, Serverbound, java v1_7 PlayerPositionPacket,
+    0x05, JavaPlay, Serverbound, java v1_7 PlayerLookPacket,
+    0x06, JavaPlay, Serverbound, java v1_7 ServerboundPlayerPositionAndLookPacket,
+    0x07, JavaPlay, Serverbound, java v1_7 PlayerDiggingPacket,
+    0x08, JavaPlay, Serverbound, java v1_7 PlayerBlockPlacementPacket,
+    0x09, JavaPlay, Serverbound, java v1_7 ServerboundHeldItemChangePacket,
+    0x0A, JavaPlay, Serverbound, java v1_7 ServerboundAnimationPacket,
+    0x0B, JavaPlay, Serverbound, java v1_7 EntityActionPacket,
+    0x0C, JavaPlay, Serverbound, java v1_7 SteerVehiclePacket,
+    0x0D, JavaPlay, Serverbound, java v1_7 CloseWindowPacket,
+    0x0E, JavaPlay, Serverbound, java v1_7 ClickWindowPacket,
+    0x0F, JavaPlay, Serverbound, java v1_7 ConfirmTransactionPacket,
+    0x10, JavaPlay, Serverbound, java v1_7 CreativeInventoryActionPacket,
+    0x11, JavaPlay, Serverbound, java v1_7 EnchantItemPacket,
+    0x12, JavaPlay, Serverbound, java v1_7 UpdateSignPacket,
+    0x13, JavaPlay, Serverbound, java v1_7 PlayerAbilitiesPacket,
+    0x14, JavaPlay, Serverbound, java v1_7 ServerboundTabCompletePacket,
+    0x15, JavaPlay, Serverbound, java v1_7 ClientSettingsPacket,
+    0x16, JavaPlay, Serverbound, java v1_7 ClientStatusPacket,
+    0x17, JavaPlay, Serverbound, java v1_7 PluginMessagePacket
 );
 
 // Handshake C->S
@@ -64,13 +153,13 @@ packet!(PingPacket,
 // Login S->C
 
 packet!(DisconnectPacket,
-    reason: VarIntLengthPrefixedString
+    reason: TextComponent
 );
 
 packet!(EncryptionRequestPacket,
     server_id: VarIntLengthPrefixedString,
-    public_key: VarIntLengthPrefixedByteArray,
-    verify_token: VarIntLengthPrefixedByteArray
+    public_key: ShortLengthPrefixedByteArray,
+    verify_token: ShortLengthPrefixedByteArray
 );
 
 packet!(LoginSuccessPacket, // switches connection state to Play
@@ -89,16 +178,49 @@ packet!(LoginStartPacket,
 );
 
 packet!(EncryptionResponsePacket,
-    shared_secret: VarIntLengthPrefixedByteArray,
-    verify_token: VarIntLengthPrefixedByteArray
+    shared_secret: ShortLengthPrefixedByteArray,
+    verify_token: ShortLengthPrefixedByteArray
 );
 
-// Play S->C
+// Play S->C & C->S
 
 packet!(KeepAlivePacket,
     id: i32
 );
 
+packet!(CloseWindowPacket,
+    window_id: u8
+);
+
+packet!(ConfirmTransactionPacket,
+    window_id: i8,
+    action_number: i16,
+    accepted: bool
+);
+
+packet!(UpdateSignPacket,
+    x: i32,
+    y: i16,
+    z: i32,
+    line_1: VarIntLengthPrefixedString,
+    line_2: VarIntLengthPrefixedString,
+    line_3: VarIntLengthPrefixedString,
+    line_4: VarIntLengthPrefixedString
+);
+
+packet!(PlayerAbilitiesPacket,
+    flags: i8,
+    flying_speed: f32,
+    walking_speed: f32
+);
+
+packet!(PluginMessagePacket,
+    channel: VarIntLengthPrefixedString,
+    data: ShortLengthPrefixedByteArray
+);
+
+// Play S->C
+
 packet!(JoinGamePacket,
     entity_id: i32,
     game_mode: u8,
@@ -107,3 +229,772 @@ packet!(JoinGamePacket,
     max_players: u8,
     level_type: VarIntLengthPrefixedString
 );
+
+packet!(ClientboundChatMessagePacket,
+    message: TextComponent
+);
+
+packet!(TimeUpdatePacket,
+    world_age: i64,
+    time_of_day: i64
+);
+
+packet!(EntityEquipmentPacket,
+    entity_id: i32,
+    slot: i16,
+    item: Slot
+);
+
+packet!(SpawnPositionPacket,
+    x: i32,
+    y: i32,
+    z: i32
+);
+
+packet!(UpdateHealthPacket,
+    health: f32,
+    food: i16,
+    food_saturation: f32
+);
+
+packet!(RespawnPacket,
+    dimension: i32,
+    difficulty: u8,
+    game_mode: u8,
+    level_type: VarIntLengthPrefixedString
+);
+
+packet!(ClientboundPlayerPositionAndLookPacket,
+    x: f64,
+    y: f64, // eye height
+    z: f64,
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool
+);
+
+packet!(ClientboundHeldItemChangePacket,
+    slot: i8
+);
+
+packet!(UseBedPacket,
+    entity_id: i32,
+    x: i32,
+    y: u8,
+    z: i32
+);
+
+packet!(ClientboundAnimationPacket,
+    entity_id: VarInt,
+    animation: u8
+);
+
+field!(PlayerProperty,
+    name: VarIntLengthPrefixedString,
+    value: VarIntLengthPrefixedString,
+    signature: VarIntLengthPrefixedString
+);
+
+packet!(SpawnPlayerPacket,
+    entity_id: VarInt,
+    uuid: HyphenatedUUID,
+    name: VarIntLengthPrefixedString,
+    properties: LengthPrefixedVec<VarInt, PlayerProperty>,
+    x: FixedPointInt,
+    y: FixedPointInt,
+    z: FixedPointInt,
+    yaw: Angle,
+    pitch: Angle,
+    current_item: i16,
+    metadata: EntityMetadata
+);
+
+packet!(CollectItemPacket,
+    collected_entity_id: i32,
+    collector_entity_id: i32
+);
+
+packet!(SpawnObjectPacket,
+    entity_id: VarInt,
+    object_type: i8,
+    x: FixedPointInt,
+    y: FixedPointInt,
+    z: FixedPointInt,
+    pitch: Angle,
+    yaw: Angle,
+    data: ObjectData
+);
+
+packet!(SpawnMobPacket,
+    entity_id: VarInt,
+    mob_type: u8,
+    x: FixedPointInt,
+    y: FixedPointInt,
+    z: FixedPointInt,
+    yaw: Angle,
+    pitch: Angle,
+    head_pitch: Angle,
+    velocity_x: i16,
+    velocity_y: i16,
+    velocity_z: i16,
+    metadata: EntityMetadata
+);
+
+packet!(SpawnPaintingPacket,
+    entity_id: VarInt,
+    title: VarIntLengthPrefixedString,
+    x: i32,
+    y: i32,
+    z: i32,
+    direction: i32
+);
+
+packet!(SpawnExperienceOrbPacket,
+    entity_id: VarInt,
+    x: FixedPointInt,
+    y: FixedPointInt,
+    z: FixedPointInt,
+    count: i16
+);
+
+packet!(EntityVelocityPacket,
+    entity_id: i32,
+    velocity_x: i16,
+    velocity_y: i16,
+    velocity_z: i16
+);
+
+packet!(DestroyEntitiesPacket,
+    entity_ids: LengthPrefixedVec<u8, i32>
+);
+
+packet!(EntityPacket,
+    entity_id: i32
+);
+
+packet!(EntityRelativeMovePacket,
+    entity_id: i32,
+    delta_x: FixedPointByte,
+    delta_y: FixedPointByte,
+    delta_z: FixedPointByte
+);
+
+packet!(EntityLookPacket,
+    entity_id: i32,
+    yaw: Angle,
+    pitch: Angle
+);
+
+packet!(EntityLookAndRelativeMovePacket,
+    entity_id: i32,
+    delta_x: FixedPointByte,
+    delta_y: FixedPointByte,
+    delta_z: FixedPointByte,
+    yaw: Angle,
+    pitch: Angle
+);
+
+packet!(EntityTeleportPacket,
+    entity_id: i32,
+    x: FixedPointInt,
+    y: FixedPointInt,
+    z: FixedPointInt,
+    yaw: Angle,
+    pitch: Angle
+);
+
+packet!(EntityHeadLookPacket,
+    entity_id: i32,
+    head_yaw: Angle
+);
+
+packet!(EntityStatusPacket,
+    entity_id: i32,
+    status: i8
+);
+
+packet!(AttachEntityPacket,
+    entity_id: i32,
+    vehicle_id: i32,
+    leash: bool
+);
+
+packet!(EntityMetadataPacket,
+    entity_id: i32,
+    metadata: EntityMetadata
+);
+
+packet!(EntityEffectPacket,
+    entity_id: i32,
+    effect_id: i8,
+    amplifier: i8,
+    duration: i16
+);
+
+packet!(RemoveEntityEffectPacket,
+    entity_id: i32,
+    effect_id: i8
+);
+
+packet!(SetExperiencePacket,
+    experience_bar: f32,
+    level: i16,
+    total_experience: i16
+);
+
+field!(AttributeModifier,
+    uuid: UUID,
+    amount: f64,
+    operation: i8
+);
+
+field!(EntityProperty,
+    key: VarIntLengthPrefixedString,
+    value: f64,
+    modifiers: LengthPrefixedVec<i16, AttributeModifier>
+);
+
+packet!(EntityPropertiesPacket,
+    entity_id: i32,
+    properties: LengthPrefixedVec<i32, EntityProperty>
+);
+
+packet!(ChunkDataPacket,
+    chunk_x: i32,
+    chunk_z: i32,
+    ground_up_continuous: bool,
+    primary_bit_mask: u16,
+    add_bit_mask: u16,
+    data: LengthPrefixedVec<i32, u8> // zlib compressed
+);
+
+packet!(MultiBlockChangePacket,
+    chunk_x: i32,
+    chunk_z: i32,
+    record_count: i16,
+    records: LengthPrefixedVec<i32, u8> // 4 bytes per record
+);
+
+packet!(BlockChangePacket,
+    x: i32,
+    y: u8,
+    z: i32,
+    block_id: VarInt,
+    block_data: u8
+);
+
+packet!(BlockActionPacket,
+    x: i32,
+    y: i16,
+    z: i32,
+    action_id: u8,
+    action_param: u8,
+    block_type: VarInt
+);
+
+packet!(BlockBreakAnimationPacket,
+    entity_id: VarInt,
+    x: i32,
+    y: i32,
+    z: i32,
+    destroy_stage: i8
+);
+
+packet!(MapChunkBulkPacket,
+    chunks: ChunkBulk
+);
+
+field!(ExplosionRecord,
+    x: i8,
+    y: i8,
+    z: i8
+);
+
+packet!(ExplosionPacket,
+    x: f32,
+    y: f32,
+    z: f32,
+    radius: f32,
+    records: LengthPrefixedVec<i32, ExplosionRecord>,
+    motion_x: f32,
+    motion_y: f32,
+    motion_z: f32
+);
+
+packet!(EffectPacket,
+    effect_id: i32,
+    x: i32,
+    y: u8,
+    z: i32,
+    data: i32,
+    disable_relative_volume: bool
+);
+
+packet!(SoundEffectPacket,
+    sound_name: VarIntLengthPrefixedString,
+    x: i32, // multiplied by 8
+    y: i32,
+    z: i32,
+    volume: f32,
+    pitch: u8
+);
+
+packet!(ParticlePacket,
+    particle_name: VarIntLengthPrefixedString,
+    x: f32,
+    y: f32,
+    z: f32,
+    offset_x: f32,
+    offset_y: f32,
+    offset_z: f32,
+    speed: f32,
+    count: i32
+);
+
+packet!(ChangeGameStatePacket,
+    reason: u8,
+    value: f32
+);
+
+packet!(SpawnGlobalEntityPacket,
+    entity_id: VarInt,
+    entity_type: i8,
+    x: FixedPointInt,
+    y: FixedPointInt,
+    z: FixedPointInt
+);
+
+packet!(OpenWindowPacket,
+    window_id: u8,
+    inventory_type: u8,
+    title: VarIntLengthPrefixedString,
+    slot_count: u8,
+    use_provided_title: bool,
+    entity_id: TrailingOption<i32> // only sent for horses
+);
+
+packet!(SetSlotPacket,
+    window_id: i8,
+    slot: i16,
+    item: Slot
+);
+
+packet!(WindowItemsPacket,
+    window_id: u8,
+    items: LengthPrefixedVec<i16, Slot>
+);
+
+packet!(WindowPropertyPacket,
+    window_id: u8,
+    property: i16,
+    value: i16
+);
+
+packet!(MapsPacket,
+    item_damage: VarInt,
+    data: ShortLengthPrefixedByteArray
+);
+
+packet!(UpdateBlockEntityPacket,
+    x: i32,
+    y: i16,
+    z: i32,
+    action: u8,
+    nbt: GzipNbt
+);
+
+packet!(SignEditorOpenPacket,
+    x: i32,
+    y: i32,
+    z: i32
+);
+
+field!(Statistic,
+    name: VarIntLengthPrefixedString,
+    value: VarInt
+);
+
+packet!(StatisticsPacket,
+    statistics: LengthPrefixedVec<VarInt, Statistic>
+);
+
+packet!(PlayerListItemPacket,
+    player_name: VarIntLengthPrefixedString,
+    online: bool,
+    ping: i16
+);
+
+packet!(ClientboundTabCompletePacket,
+    matches: LengthPrefixedVec<VarInt, VarIntLengthPrefixedString>
+);
+
+packet!(ScoreboardObjectivePacket,
+    objective_name: VarIntLengthPrefixedString,
+    objective_value: VarIntLengthPrefixedString,
+    mode: i8
+);
+
+packet!(UpdateScorePacket,
+    item_name: VarIntLengthPrefixedString,
+    action: ScoreAction
+);
+
+packet!(DisplayScoreboardPacket,
+    position: i8,
+    score_name: VarIntLengthPrefixedString
+);
+
+packet!(TeamsPacket,
+    team_name: VarIntLengthPrefixedString,
+    action: TeamAction
+);
+
+packet!(PlayDisconnectPacket,
+    reason: TextComponent
+);
+
+// Play C->S
+
+packet!(ServerboundChatMessagePacket,
+    message: VarIntLengthPrefixedString
+);
+
+packet!(UseEntityPacket,
+    target: i32,
+    mouse: i8
+);
+
+packet!(PlayerPacket,
+    on_ground: bool
+);
+
+packet!(PlayerPositionPacket,
+    x: f64,
+    feet_y: f64,
+    head_y: f64,
+    z: f64,
+    on_ground: bool
+);
+
+packet!(PlayerLookPacket,
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool
+);
+
+packet!(ServerboundPlayerPositionAndLookPacket,
+    x: f64,
+    feet_y: f64,
+    head_y: f64,
+    z: f64,
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool
+);
+
+packet!(PlayerDiggingPacket,
+    status: i8,
+    x: i32,
+    y: u8,
+    z: i32,
+    face: i8
+);
+
+packet!(PlayerBlockPlacementPacket,
+    x: i32,
+    y: u8,
+    z: i32,
+    direction: i8,
+    held_item: Slot,
+    cursor_x: i8,
+    cursor_y: i8,
+    cursor_z: i8
+);
+
+packet!(ServerboundHeldItemChangePacket,
+    slot: i16
+);
+
+packet!(ServerboundAnimationPacket,
+    entity_id: i32,
+    animation: i8
+);
+
+packet!(EntityActionPacket,
+    entity_id: i32,
+    action_id: i8,
+    jump_boost: i32
+);
+
+packet!(SteerVehiclePacket,
+    sideways: f32,
+    forward: f32,
+    jump: bool,
+    unmount: bool
+);
+
+packet!(ClickWindowPacket,
+    window_id: i8,
+    slot: i16,
+    button: i8,
+    action_number: i16,
+    mode: i8,
+    clicked_item: Slot
+);
+
+packet!(CreativeInventoryActionPacket,
+    slot: i16,
+    clicked_item: Slot
+);
+
+packet!(EnchantItemPacket,
+    window_id: i8,
+    enchantment: i8
+);
+
+packet!(ServerboundTabCompletePacket,
+    text: VarIntLengthPrefixedString
+);
+
+packet!(ClientSettingsPacket,
+    locale: VarIntLengthPrefixedString,
+    view_distance: i8,
+    chat_flags: i8,
+    chat_colors: bool,
+    difficulty: i8,
+    show_cape: bool
+);
+
+packet!(ClientStatusPacket,
+    action_id: i8
+);
+
+// Fields that depend on an earlier value
+
+/// Extra data for `SpawnObjectPacket`. The velocity is only sent when `data` isn't 0.
+#[derive(Clone, Default, Debug)]
+pub struct ObjectData {
+    pub data: i32,
+    pub velocity: Option<(i16, i16, i16)>,
+}
+
+impl ReadField for ObjectData {
+    fn read(bytes: &[u8], index: usize) -> Result<(ObjectData, usize), DecodeError> {
+        let (data, length) = <i32 as ReadField>::read(bytes, index)?;
+        if data == 0 {
+            return Ok((ObjectData { data, velocity: None }, length));
+        }
+        let (x, _) = <i16 as ReadField>::read(bytes, index + 4)?;
+        let (y, _) = <i16 as ReadField>::read(bytes, index + 6)?;
+        let (z, _) = <i16 as ReadField>::read(bytes, index + 8)?;
+        Ok((ObjectData { data, velocity: Some((x, y, z)) }, length + 6))
+    }
+}
+
+impl WriteField for ObjectData {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        self.data.write_to(buf);
+        if self.data != 0 {
+            let (x, y, z) = self.velocity.unwrap_or_default();
+            x.write_to(buf);
+            y.write_to(buf);
+            z.write_to(buf);
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        if self.data != 0 { 10 } else { 4 }
+    }
+}
+
+field!(ChunkMeta,
+    chunk_x: i32,
+    chunk_z: i32,
+    primary_bit_mask: u16,
+    add_bit_mask: u16
+);
+
+/// The chunks in a `MapChunkBulkPacket`. The column count comes before the data, but the
+/// columns themselves come after it.
+#[derive(Clone, Default, Debug)]
+pub struct ChunkBulk {
+    pub sky_light_sent: bool,
+    /// zlib compressed
+    pub data: Vec<u8>,
+    pub columns: Vec<ChunkMeta>,
+}
+
+impl ReadField for ChunkBulk {
+    fn read(bytes: &[u8], index: usize) -> Result<(ChunkBulk, usize), DecodeError> {
+        let (count, _) = <i16 as ReadField>::read(bytes, index)?;
+        let (data_length, _) = <i32 as ReadField>::read(bytes, index + 2)?;
+        let (sky_light_sent, _) = <bool as ReadField>::read(bytes, index + 6)?;
+        if count < 0 || data_length < 0 {
+            return Err(DecodeError::LengthExceedsLimit);
+        }
+        let data = read_slice(bytes, index + 7, data_length as usize)?.to_vec();
+        let mut length = 7 + data.len();
+
+        let mut columns = Vec::with_capacity((count as usize).min(bytes.len().saturating_sub(index + length)));
+        for _ in 0..count {
+            let (column, size) = <ChunkMeta as ReadField>::read(bytes, index + length)?;
+            length += size;
+            columns.push(column);
+        }
+        Ok((ChunkBulk { sky_light_sent, data, columns }, length))
+    }
+}
+
+impl WriteField for ChunkBulk {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        (self.columns.len() as i16).write_to(buf);
+        (self.data.len() as i32).write_to(buf);
+        self.sky_light_sent.write_to(buf);
+        buf.extend_from_slice(&self.data);
+        for column in self.columns.iter() {
+            column.write_to(buf);
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        7 + self.data.len() + self.columns.len() * 12
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ScoreAction {
+    Update {
+        score_name: VarIntLengthPrefixedString,
+        value: i32,
+    },
+    Remove,
+}
+
+impl Default for ScoreAction {
+    fn default() -> Self {
+        ScoreAction::Remove
+    }
+}
+
+impl ReadField for ScoreAction {
+    fn read(bytes: &[u8], index: usize) -> Result<(ScoreAction, usize), DecodeError> {
+        let (action, length) = <u8 as ReadField>::read(bytes, index)?;
+        match action {
+            0 => {
+                let (score_name, size) = <VarIntLengthPrefixedString as ReadField>::read(bytes, index + length)?;
+                let (value, _) = <i32 as ReadField>::read(bytes, index + length + size)?;
+                Ok((ScoreAction::Update { score_name, value }, length + size + 4))
+            }
+            1 => Ok((ScoreAction::Remove, length)),
+            _ => Err(DecodeError::UnknownVariant)
+        }
+    }
+}
+
+impl WriteField for ScoreAction {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            ScoreAction::Update { score_name, value } => {
+                buf.push(0);
+                score_name.write_to(buf);
+                value.write_to(buf);
+            }
+            ScoreAction::Remove => buf.push(1)
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        match self {
+            ScoreAction::Update { score_name, .. } => 5 + score_name.size_hint(),
+            ScoreAction::Remove => 1
+        }
+    }
+}
+
+field!(TeamInfo,
+    display_name: VarIntLengthPrefixedString,
+    prefix: VarIntLengthPrefixedString,
+    suffix: VarIntLengthPrefixedString,
+    friendly_fire: i8
+);
+
+pub type TeamPlayers = LengthPrefixedVec<i16, VarIntLengthPrefixedString>;
+
+/// The mode of a `TeamsPacket` & the fields that go with it.
+/// Later versions send different team info & player lists in the same layout.
+#[derive(Clone, Debug)]
+pub enum TeamAction<I = TeamInfo, P = TeamPlayers> {
+    Create(I, P),
+    Remove,
+    UpdateInfo(I),
+    AddPlayers(P),
+    RemovePlayers(P),
+}
+
+impl<I, P> Default for TeamAction<I, P> {
+    fn default() -> Self {
+        TeamAction::Remove
+    }
+}
+
+impl<I: ReadField, P: ReadField> ReadField for TeamAction<I, P> {
+    fn read(bytes: &[u8], index: usize) -> Result<(Self, usize), DecodeError> {
+        let (mode, mut length) = <u8 as ReadField>::read(bytes, index)?;
+        let action = match mode {
+            0 => {
+                let (info, size) = <I as ReadField>::read(bytes, index + length)?;
+                length += size;
+                let (players, size) = <P as ReadField>::read(bytes, index + length)?;
+                length += size;
+                TeamAction::Create(info, players)
+            }
+            1 => TeamAction::Remove,
+            2 => {
+                let (info, size) = <I as ReadField>::read(bytes, index + length)?;
+                length += size;
+                TeamAction::UpdateInfo(info)
+            }
+            3 | 4 => {
+                let (players, size) = <P as ReadField>::read(bytes, index + length)?;
+                length += size;
+                if mode == 3 { TeamAction::AddPlayers(players) } else { TeamAction::RemovePlayers(players) }
+            }
+            _ => return Err(DecodeError::UnknownVariant)
+        };
+        Ok((action, length))
+    }
+}
+
+impl<I: WriteField, P: WriteField> WriteField for TeamAction<I, P> {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            TeamAction::Create(info, players) => {
+                buf.push(0);
+                info.write_to(buf);
+                players.write_to(buf);
+            }
+            TeamAction::Remove => buf.push(1),
+            TeamAction::UpdateInfo(info) => {
+                buf.push(2);
+                info.write_to(buf);
+            }
+            TeamAction::AddPlayers(players) => {
+                buf.push(3);
+                players.write_to(buf);
+            }
+            TeamAction::RemovePlayers(players) => {
+                buf.push(4);
+                players.write_to(buf);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        match self {
+            TeamAction::Create(info, players) => 1 + info.size_hint() + players.size_hint(),
+            TeamAction::Remove => 1,
+            TeamAction::UpdateInfo(info) => 1 + info.size_hint(),
+            TeamAction::AddPlayers(players) | TeamAction::RemovePlayers(players) => 1 + players.size_hint(),
+        }
+    }
+}
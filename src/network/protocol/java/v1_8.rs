@@ -1,5 +1,7 @@
 use packet::*;
 use protocol::*;
+use protocol::java::v1_7;
+use network::nbt::Nbt;
 
 // Packets for Minecraft Java Edition Version 1.8.9 (protocol version 47)
 // https://wiki.vg/index.php?title=Protocol&oldid=7368
@@ -14,14 +16,147 @@ protocol!("1.8", ProtocolJava_1_8, ProtocolEdition::JavaEdition, 47,
     0x01, JavaStatus, Serverbound, java v1_7 PingPacket,
     // Login
     0x00, JavaLogin, Serverbound, java v1_7 LoginStartPacket,
-    0x01, JavaLogin, Serverbound, java v1_7 EncryptionResponsePacket,
+    0x01, JavaLogin, Serverbound, java v1_8 EncryptionResponsePacket,
     0x00, JavaLogin, Clientbound, java v1_7 DisconnectPacket,
-    0x01, JavaLogin, Clientbound, java v1_7 EncryptionRequestPacket,
+    0x01, JavaLogin, Clientbound, java v1_8 EncryptionRequestPacket,
     0x02, JavaLogin, Clientbound, java v1_7 LoginSuccessPacket,
     0x03, JavaLogin, Clientbound, java v1_7 SetCompressionPacket,
-    // Play
-    0x00, JavaPlay, Clientbound, java v1_7 KeepAlivePacket,
-    0x01, JavaPlay, Clientbound, java v1_8 JoinGamePacket
+    // Play S->C
+    0x00, JavaPlay, Clientbound, java v1_8 KeepAlivePacket,
+    0x01, JavaPlay, Clientbound, java v1_8 JoinGamePacket,
+    0x02, JavaPlay, Clientbound, java v1_8 ClientboundChatMessagePacket,
+    0x03, JavaPlay, Clientbound, java v1_7 TimeUpdatePacket,
+    0x04, JavaPlay, Clientbound, java v1_8 EntityEquipmentPacket,
+    0x05, JavaPlay, Clientbound, java v1_8 SpawnPositionPacket,
+    0x06, JavaPlay, Clientbound, java v1_8 UpdateHealthPacket,
+    0x07, JavaPlay, Clientbound, java v1_7 RespawnPacket,
+    0x08, JavaPlay, Clientbound, java v1_8 ClientboundPlayerPositionAndLookPacket,
+    0x09, JavaPlay, Clientbound, java v1_7 ClientboundHeldItemChangePacket,
+    0x0A, JavaPlay, Clientbound, java v1_8 UseBedPacket,
+    0x0B, JavaPlay, Clientbound, java v1_7 ClientboundAnimationPacket,
+    0x0C, JavaPlay, Clientbound, java v1_8 SpawnPlayerPacket,
+    0x0D, JavaPlay, Clientbound, java v1_8 CollectItemPacket,
+    0x0E, JavaPlay, Clientbound, java v1_7 SpawnObjectPacket,
+    0x0F, JavaPlay, Clientbound, java v1_8 SpawnMobPacket,
+    0x10, JavaPlay, Clientbound, java v1_8 SpawnPaintingPacket,
+    0x11, JavaPlay, Clientbound, java v1_7 SpawnExperienceOrbPacket,
+    0x12, JavaPlay, Clientbound, java v1_8 EntityVelocityPacket,
+    0x13, JavaPlay, Clientbound, java v1_8 DestroyEntitiesPacket,
+    0x14, JavaPlay, Clientbound, java v1_8 EntityPacket,
+    0x15, JavaPlay, Clientbound, java v1_8 EntityRelativeMovePacket,
+    0x16, JavaPlay, Clientbound, java v1_8 EntityLookPacket,
+    0x17, JavaPlay, Clientbound, java v1_8 EntityLookAndRelativeMovePacket,
+    0x18, JavaPlay, Clientbound, java v1_8 EntityTeleportPacket,
+    0x19, JavaPlay, Clientbound, java v1_8 EntityHeadLookPacket,
+    0x1A, JavaPlay, Clientbound, java v1_7 EntityStatusPacket,
+    0x1B, JavaPlay, Clientbound, java v1_7 AttachEntityPacket,
+    0x1C, JavaPlay, Clientbound, java v1_8 EntityMetadataPacket,
+    0x1D, JavaPlay, Clientbound, java v1_8 EntityEffectPacket,
+    0x1E, JavaPlay, Clientbound, java v1_8 RemoveEntityEffectPacket,
+    0x1F, JavaPlay, Clientbound, java v1_8 SetExperiencePacket,
+    0x20, JavaPlay, Clientbound, java v1_8 EntityPropertiesPacket,
+    0x21, JavaPlay, Clientbound, java v1_8 ChunkDataPacket,
+    0x22, JavaPlay, Clientbound, java v1_8 MultiBlockChangePacket,
+    0x23, JavaPlay, Clientbound, java v1_8 BlockChangePacket,
+    0x24, JavaPlay, Clientbound, java v1_8 BlockActionPacket,
+    0x25, JavaPlay, Clientbound, java v1_8 BlockBreakAnimationPacket,
+    0x26, JavaPlay, Clientbound, java v1_8 MapChunkBulkPacket,
+    0x27, JavaPlay, Clientbound, java v1_7 ExplosionPacket,
+    0x28, JavaPlay, Clientbound, java v1_8 EffectPacket,
+    0x29, JavaPlay, Clientbound, java v1_7 SoundEffectPacket,
+    0x2A, JavaPlay, Clientbound, java v1_8 ParticlePacket,
+    0x2B, JavaPlay, Clientbound, java v1_7 ChangeGameStatePacket,
+    0x2C, JavaPlay, Clientbound, java v1_7 SpawnGlobalEntityPacket,
+    0x2D, JavaPlay, Clientbound, java v1_8 OpenWindowPacket,
+    0x2E, JavaPlay, Clientbound, java v1_7 CloseWindowPacket,
+    0x2F, JavaPlay, Clientbound, java v1_8 SetSlotPacket,
+    0x30, JavaPlay, Clientbound, java v1_8 WindowItemsPacket,
+    0x31, JavaPlay, Clientbound, java v1_7 WindowPropertyPacket,
+    0x32, JavaPlay, Clientbound, java v1_7 ConfirmTransactionPacket,
+    0x33, JavaPlay, Clientbound, java v1_8 UpdateSignPacket,
+    0x34, JavaPlay, Clientbound, java v1_8 MapsPacket,
+    0x35, JavaPlay, Clientbound, java v1_8 UpdateBlockEntityPacket,
+    0x36, JavaPlay, Clientbound, java v1_8 SignEditorOpenPacket,
+    0x37, JavaPlay, Clientbound, java v1_7 StatisticsPacket,
+    0x38, JavaPlay, Clientbound, java v1_8 PlayerListItemPacket,
+    0x39, JavaPlay, Clientbound, java v1_7 PlayerAbilitiesPacket,
+    0x3A, JavaPlay, Clientbound, java v1_7 ClientboundTabCompletePacket,
+    0x3B, JavaPlay, Clientbound, java v1_8 ScoreboardObjectivePacket,
+    0x3C, JavaPlay, Clientbound, java v1_8 UpdateScorePacket,
+    0x3D, JavaPlay, Clientbound, java v1_7 DisplayScoreboardPacket,
+    0x3E, JavaPlay, Clientbound, java v1_8 TeamsPacket,
+    0x3F, JavaPlay, Clientbound, java v1_8 PluginMessagePacket,
+    0x40, JavaPlay, Clientbound, java v1_7 PlayDisconnectPacket,
+    0x41, JavaPlay, Clientbound, java v1_8 ServerDifficultyPacket,
+    0x42, JavaPlay, Clientbound, java v1_8 CombatEventPacket,
+    0x43, JavaPlay, Clientbound, java v1_8 CameraPacket,
+    0x44, JavaPlay, Clientbound, java v1_8 WorldBorderPacket,
+    0x45, JavaPlay, Clientbound, java v1_8 TitlePacket,
+    0x46, JavaPlay, Clientbound, java v1_8 PlaySetCompressionPacket,
+    0x47, JavaPlay, Clientbound, java v1_8 PlayerListHeaderAndFooterPacket,
+    0x48, JavaPlay, Clientbound, java v1_8 ResourcePackSendPacket,
+    0x49, JavaPlay, Clientbound, java v1_8 UpdateEntityNbtPacket,
+    // Play C->S
+    0x00, JavaPlay, Serverbound, java v1_8 KeepAlivePacket,
+    0x01, JavaPlay, Serverbound, java v1_7 ServerboundChatMessagePacket,
+    0x02, JavaPlay, Serverbound, java v1_8 UseEntityPacket,
+    0x03, JavaPlay, Serverbound, java v1_7 PlayerPacket,
+    0x04, JavaPlay, Serverbound, java v1_8 PlayerPositionPacket,
+    0x05, JavaPlay, Serverbound, java v1_7 PlayerLookPacket,
+    0x06, JavaPlay, Serverbound, java v1_8 ServerboundPlayerPositionAndLookPacket,
+    0x07, JavaPlay, Serverbound, java v1_8 PlayerDiggingPacket,
+    0x08, JavaPlay, Serverbound, java v1_8 PlayerBlockPlacementPacket,
+    0x09, JavaPlay, Serverbound, java v1_7 ServerboundHeldItemChangePacket,
+    0x0A, JavaPlay, Serverbound, java v1_8 ServerboundAnimationPacket,
+    0x0B, JavaPlay, Serverbound, java v1_8 EntityActionPacket,
+    0x0C, JavaPlay, Serverbound, java v1_8 SteerVehiclePacket,
+    0x0D, JavaPlay, Serverbound, java v1_7 CloseWindowPacket,
+    0x0E, JavaPlay, Serverbound, java v1_8 ClickWindowPacket,
+    0x0F, JavaPlay, Serverbound, java v1_7 ConfirmTransactionPacket,
+    0x10, JavaPlay, Serverbound, java v1_8 CreativeInventoryActionPacket,
+    0x11, JavaPlay, Serverbound, java v1_7 EnchantItemPacket,
+    0x12, JavaPlay, Serverbound, java v1_8 UpdateSignPacket,
+    0x13, JavaPlay, Serverbound, java v1_7 PlayerAbilitiesPacket,
+    0x14, JavaPlay, Serverbound, java v1_8 ServerboundTabCompletePacket,
+    0x15, JavaPlay, Serverbound, java v1_8 ClientSettingsPacket,
+    0x16, JavaPlay, Serverbound, java v1_8 ClientStatusPacket,
+    0x17, JavaPlay, Serverbound, java v1_8 PluginMessagePacket,
+    0x18, JavaPlay, Serverbound, java v1_8 SpectatePacket,
+    0x19, JavaPlay, Serverbound, java v1_8 ResourcePackStatusPacket
+);
+
+// Login S->C
+
+packet!(EncryptionRequestPacket,
+    server_id: VarIntLengthPrefixedString,
+    public_key: VarIntLengthPrefixedByteArray,
+    verify_token: VarIntLengthPrefixedByteArray
+);
+
+// Login C->S
+
+packet!(EncryptionResponsePacket,
+    shared_secret: VarIntLengthPrefixedByteArray,
+    verify_token: VarIntLengthPrefixedByteArray
+);
+
+// Play S->C & C->S
+
+packet!(KeepAlivePacket,
+    id: VarInt
+);
+
+packet!(UpdateSignPacket,
+    location: Position,
+    line_1: TextComponent,
+    line_2: TextComponent,
+    line_3: TextComponent,
+    line_4: TextComponent
+);
+
+packet!(PluginMessagePacket,
+    channel: VarIntLengthPrefixedString,
+    data: RemainingBytes
 );
 
 // Play S->C
@@ -35,3 +170,982 @@ packet!(JoinGamePacket,
     level_type: VarIntLengthPrefixedString,
     reduced_debug: u8
 );
+
+packet!(ClientboundChatMessagePacket,
+    message: TextComponent,
+    position: i8 // 0 is chat, 1 is system & 2 is above the hotbar
+);
+
+packet!(EntityEquipmentPacket,
+    entity_id: VarInt,
+    slot: i16,
+    item: Slot1_8
+);
+
+packet!(SpawnPositionPacket,
+    location: Position
+);
+
+packet!(UpdateHealthPacket,
+    health: f32,
+    food: VarInt,
+    food_saturation: f32
+);
+
+packet!(ClientboundPlayerPositionAndLookPacket,
+    x: f64,
+    y: f64,
+    z: f64,
+    yaw: f32,
+    pitch: f32,
+    flags: i8 // which of the fields are relative
+);
+
+packet!(UseBedPacket,
+    entity_id: VarInt,
+    location: Position
+);
+
+packet!(SpawnPlayerPacket,
+    entity_id: VarInt,
+    uuid: UUID,
+    x: FixedPointInt,
+    y: FixedPointInt,
+    z: FixedPointInt,
+    yaw: Angle,
+    pitch: Angle,
+    current_item: i16,
+    metadata: EntityMetadata1_8
+);
+
+packet!(CollectItemPacket,
+    collected_entity_id: VarInt,
+    collector_entity_id: VarInt
+);
+
+packet!(SpawnMobPacket,
+    entity_id: VarInt,
+    mob_type: u8,
+    x: FixedPointInt,
+    y: FixedPointInt,
+    z: FixedPointInt,
+    yaw: Angle,
+    pitch: Angle,
+    head_pitch: Angle,
+    velocity_x: i16,
+    velocity_y: i16,
+    velocity_z: i16,
+    metadata: EntityMetadata1_8
+);
+
+packet!(SpawnPaintingPacket,
+    entity_id: VarInt,
+    title: VarIntLengthPrefixedString,
+    location: Position,
+    direction: u8
+);
+
+packet!(EntityVelocityPacket,
+    entity_id: VarInt,
+    velocity_x: i16,
+    velocity_y: i16,
+    velocity_z: i16
+);
+
+packet!(DestroyEntitiesPacket,
+    entity_ids: LengthPrefixedVec<VarInt, VarInt>
+);
+
+packet!(EntityPacket,
+    entity_id: VarInt
+);
+
+packet!(EntityRelativeMovePacket,
+    entity_id: VarInt,
+    delta_x: FixedPointByte,
+    delta_y: FixedPointByte,
+    delta_z: FixedPointByte,
+    on_ground: bool
+);
+
+packet!(EntityLookPacket,
+    entity_id: VarInt,
+    yaw: Angle,
+    pitch: Angle,
+    on_ground: bool
+);
+
+packet!(EntityLookAndRelativeMovePacket,
+    entity_id: VarInt,
+    delta_x: FixedPointByte,
+    delta_y: FixedPointByte,
+    delta_z: FixedPointByte,
+    yaw: Angle,
+    pitch: Angle,
+    on_ground: bool
+);
+
+packet!(EntityTeleportPacket,
+    entity_id: VarInt,
+    x: FixedPointInt,
+    y: FixedPointInt,
+    z: FixedPointInt,
+    yaw: Angle,
+    pitch: Angle,
+    on_ground: bool
+);
+
+packet!(EntityHeadLookPacket,
+    entity_id: VarInt,
+    head_yaw: Angle
+);
+
+packet!(EntityMetadataPacket,
+    entity_id: VarInt,
+    metadata: EntityMetadata1_8
+);
+
+packet!(EntityEffectPacket,
+    entity_id: VarInt,
+    effect_id: i8,
+    amplifier: i8,
+    duration: VarInt,
+    hide_particles: bool
+);
+
+packet!(RemoveEntityEffectPacket,
+    entity_id: VarInt,
+    effect_id: i8
+);
+
+packet!(SetExperiencePacket,
+    experience_bar: f32,
+    level: VarInt,
+    total_experience: VarInt
+);
+
+field!(EntityProperty,
+    key: VarIntLengthPrefixedString,
+    value: f64,
+    modifiers: LengthPrefixedVec<VarInt, v1_7::AttributeModifier>
+);
+
+packet!(EntityPropertiesPacket,
+    entity_id: VarInt,
+    properties: LengthPrefixedVec<i32, EntityProperty>
+);
+
+packet!(ChunkDataPacket,
+    chunk_x: i32,
+    chunk_z: i32,
+    ground_up_continuous: bool,
+    primary_bit_mask: u16,
+    data: VarIntLengthPrefixedByteArray
+);
+
+field!(BlockChangeRecord,
+    horizontal_position: u8, // x in the top 4 bits & z in the bottom 4
+    y: u8,
+    block_id: VarInt
+);
+
+packet!(MultiBlockChangePacket,
+    chunk_x: i32,
+    chunk_z: i32,
+    records: LengthPrefixedVec<VarInt, BlockChangeRecord>
+);
+
+packet!(BlockChangePacket,
+    location: Position,
+    block_id: VarInt // type << 4 | metadata
+);
+
+packet!(BlockActionPacket,
+    location: Position,
+    action_id: u8,
+    action_param: u8,
+    block_type: VarInt
+);
+
+packet!(BlockBreakAnimationPacket,
+    entity_id: VarInt,
+    location: Position,
+    destroy_stage: i8
+);
+
+field!(ChunkMeta,
+    chunk_x: i32,
+    chunk_z: i32,
+    primary_bit_mask: u16
+);
+
+packet!(MapChunkBulkPacket,
+    sky_light_sent: bool,
+    columns: LengthPrefixedVec<VarInt, ChunkMeta>,
+    data: RemainingBytes // every column's data, one after another
+);
+
+packet!(EffectPacket,
+    effect_id: i32,
+    location: Position,
+    data: i32,
+    disable_relative_volume: bool
+);
+
+packet!(ParticlePacket,
+    particle_id: i32,
+    long_distance: bool,
+    x: f32,
+    y: f32,
+    z: f32,
+    offset_x: f32,
+    offset_y: f32,
+    offset_z: f32,
+    speed: f32,
+    count: i32,
+    // iconcrack has 2 arguments, blockcrack & blockdust have 1
+    argument_1: TrailingOption<VarInt>,
+    argument_2: TrailingOption<VarInt>
+);
+
+packet!(OpenWindowPacket,
+    window_id: u8,
+    window_type: VarIntLengthPrefixedString,
+    title: TextComponent,
+    slot_count: u8,
+    entity_id: TrailingOption<i32> // only sent for horses
+);
+
+packet!(SetSlotPacket,
+    window_id: i8,
+    slot: i16,
+    item: Slot1_8
+);
+
+packet!(WindowItemsPacket,
+    window_id: u8,
+    items: LengthPrefixedVec<i16, Slot1_8>
+);
+
+field!(MapIcon,
+    direction_and_type: u8,
+    x: i8,
+    z: i8
+);
+
+packet!(MapsPacket,
+    item_damage: VarInt,
+    scale: i8,
+    icons: LengthPrefixedVec<VarInt, MapIcon>,
+    update: MapUpdate
+);
+
+packet!(UpdateBlockEntityPacket,
+    location: Position,
+    action: u8,
+    nbt: Nbt
+);
+
+packet!(SignEditorOpenPacket,
+    location: Position
+);
+
+packet!(PlayerListItemPacket,
+    action: PlayerListAction
+);
+
+packet!(ScoreboardObjectivePacket,
+    objective_name: VarIntLengthPrefixedString,
+    action: ObjectiveAction
+);
+
+packet!(UpdateScorePacket,
+    score_name: VarIntLengthPrefixedString,
+    action: ScoreAction
+);
+
+field!(TeamInfo,
+    display_name: VarIntLengthPrefixedString,
+    prefix: VarIntLengthPrefixedString,
+    suffix: VarIntLengthPrefixedString,
+    friendly_fire: i8,
+    name_tag_visibility: VarIntLengthPrefixedString,
+    color: i8
+);
+
+pub type TeamPlayers = LengthPrefixedVec<VarInt, VarIntLengthPrefixedString>;
+
+packet!(TeamsPacket,
+    team_name: VarIntLengthPrefixedString,
+    action: v1_7::TeamAction<TeamInfo, TeamPlayers>
+);
+
+packet!(ServerDifficultyPacket,
+    difficulty: u8
+);
+
+packet!(CombatEventPacket,
+    event: CombatEvent
+);
+
+packet!(CameraPacket,
+    camera_id: VarInt
+);
+
+packet!(WorldBorderPacket,
+    action: WorldBorderAction
+);
+
+packet!(TitlePacket,
+    action: TitleAction
+);
+
+packet!(PlaySetCompressionPacket,
+    threshold: VarInt
+);
+
+packet!(PlayerListHeaderAndFooterPacket,
+    header: TextComponent,
+    footer: TextComponent
+);
+
+packet!(ResourcePackSendPacket,
+    url: VarIntLengthPrefixedString,
+    hash: VarIntLengthPrefixedString
+);
+
+packet!(UpdateEntityNbtPacket,
+    entity_id: VarInt,
+    nbt: Nbt
+);
+
+// Play C->S
+
+packet!(UseEntityPacket,
+    target: VarInt,
+    action: UseEntityAction
+);
+
+packet!(PlayerPositionPacket,
+    x: f64,
+    feet_y: f64,
+    z: f64,
+    on_ground: bool
+);
+
+packet!(ServerboundPlayerPositionAndLookPacket,
+    x: f64,
+    feet_y: f64,
+    z: f64,
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool
+);
+
+packet!(PlayerDiggingPacket,
+    status: i8,
+    location: Position,
+    face: i8
+);
+
+packet!(PlayerBlockPlacementPacket,
+    location: Position,
+    face: i8,
+    held_item: Slot1_8,
+    cursor_x: i8,
+    cursor_y: i8,
+    cursor_z: i8
+);
+
+packet!(ServerboundAnimationPacket,
+    // no fields
+);
+
+packet!(EntityActionPacket,
+    entity_id: VarInt,
+    action_id: VarInt,
+    jump_boost: VarInt
+);
+
+packet!(SteerVehiclePacket,
+    sideways: f32,
+    forward: f32,
+    flags: u8 // 0x1 is jump & 0x2 is unmount
+);
+
+packet!(ClickWindowPacket,
+    window_id: i8,
+    slot: i16,
+    button: i8,
+    action_number: i16,
+    mode: i8,
+    clicked_item: Slot1_8
+);
+
+packet!(CreativeInventoryActionPacket,
+    slot: i16,
+    clicked_item: Slot1_8
+);
+
+packet!(ServerboundTabCompletePacket,
+    text: VarIntLengthPrefixedString,
+    looked_at_block: Option<Position>
+);
+
+packet!(ClientSettingsPacket,
+    locale: VarIntLengthPrefixedString,
+    view_distance: i8,
+    chat_mode: i8,
+    chat_colors: bool,
+    displayed_skin_parts: u8
+);
+
+packet!(ClientStatusPacket,
+    action_id: VarInt
+);
+
+packet!(SpectatePacket,
+    target_player: UUID
+);
+
+packet!(ResourcePackStatusPacket,
+    hash: VarIntLengthPrefixedString,
+    result: VarInt
+);
+
+// Fields that depend on an earlier value
+
+field!(MapColumns,
+    columns: u8,
+    rows: u8,
+    x: u8,
+    z: u8,
+    data: VarIntLengthPrefixedByteArray
+);
+
+/// The updated part of a map in a `MapsPacket`. Nothing else is sent when `columns` is 0.
+#[derive(Clone, Default, Debug)]
+pub struct MapUpdate(pub Option<MapColumns>);
+
+impl ReadField for MapUpdate {
+    fn read(bytes: &[u8], index: usize) -> Result<(MapUpdate, usize), DecodeError> {
+        let (columns, length) = <u8 as ReadField>::read(bytes, index)?;
+        if columns == 0 {
+            return Ok((MapUpdate(None), length));
+        }
+        let (update, length) = <MapColumns as ReadField>::read(bytes, index)?;
+        Ok((MapUpdate(Some(update)), length))
+    }
+}
+
+impl WriteField for MapUpdate {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self.0 {
+            Some(ref update) => update.write_to(buf),
+            None => buf.push(0)
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        self.0.as_ref().map(MapColumns::size_hint).unwrap_or(1)
+    }
+}
+
+field!(PlayerProperty,
+    name: VarIntLengthPrefixedString,
+    value: VarIntLengthPrefixedString,
+    signature: Option<VarIntLengthPrefixedString>
+);
+
+field!(AddPlayer,
+    uuid: UUID,
+    name: VarIntLengthPrefixedString,
+    properties: LengthPrefixedVec<VarInt, PlayerProperty>,
+    game_mode: VarInt,
+    ping: VarInt,
+    display_name: Option<TextComponent>
+);
+
+field!(UpdateGameMode,
+    uuid: UUID,
+    game_mode: VarInt
+);
+
+field!(UpdateLatency,
+    uuid: UUID,
+    ping: VarInt
+);
+
+field!(UpdateDisplayName,
+    uuid: UUID,
+    display_name: Option<TextComponent>
+);
+
+/// Every player in a `PlayerListItemPacket` gets the same action
+#[derive(Clone, Debug)]
+pub enum PlayerListAction {
+    AddPlayers(LengthPrefixedVec<VarInt, AddPlayer>),
+    UpdateGameMode(LengthPrefixedVec<VarInt, UpdateGameMode>),
+    UpdateLatency(LengthPrefixedVec<VarInt, UpdateLatency>),
+    UpdateDisplayName(LengthPrefixedVec<VarInt, UpdateDisplayName>),
+    RemovePlayers(LengthPrefixedVec<VarInt, UUID>),
+}
+
+impl Default for PlayerListAction {
+    fn default() -> Self {
+        PlayerListAction::RemovePlayers(LengthPrefixedVec::default())
+    }
+}
+
+impl ReadField for PlayerListAction {
+    fn read(bytes: &[u8], index: usize) -> Result<(PlayerListAction, usize), DecodeError> {
+        let (action, length) = <VarInt as ReadField>::read(bytes, index)?;
+        let i = index + length;
+        let (action, size) = match action.0 {
+            0 => ReadField::read(bytes, i).map(|(v, s)| (PlayerListAction::AddPlayers(v), s))?,
+            1 => ReadField::read(bytes, i).map(|(v, s)| (PlayerListAction::UpdateGameMode(v), s))?,
+            2 => ReadField::read(bytes, i).map(|(v, s)| (PlayerListAction::UpdateLatency(v), s))?,
+            3 => ReadField::read(bytes, i).map(|(v, s)| (PlayerListAction::UpdateDisplayName(v), s))?,
+            4 => ReadField::read(bytes, i).map(|(v, s)| (PlayerListAction::RemovePlayers(v), s))?,
+            _ => return Err(DecodeError::UnknownVariant)
+        };
+        Ok((action, length + size))
+    }
+}
+
+impl WriteField for PlayerListAction {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            PlayerListAction::AddPlayers(players) => {
+                VarInt(0).write_to(buf);
+                players.write_to(buf);
+            }
+            PlayerListAction::UpdateGameMode(players) => {
+                VarInt(1).write_to(buf);
+                players.write_to(buf);
+            }
+            PlayerListAction::UpdateLatency(players) => {
+                VarInt(2).write_to(buf);
+                players.write_to(buf);
+            }
+            PlayerListAction::UpdateDisplayName(players) => {
+                VarInt(3).write_to(buf);
+                players.write_to(buf);
+            }
+            PlayerListAction::RemovePlayers(players) => {
+                VarInt(4).write_to(buf);
+                players.write_to(buf);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        1 + match self {
+            PlayerListAction::AddPlayers(players) => players.size_hint(),
+            PlayerListAction::UpdateGameMode(players) => players.size_hint(),
+            PlayerListAction::UpdateLatency(players) => players.size_hint(),
+            PlayerListAction::UpdateDisplayName(players) => players.size_hint(),
+            PlayerListAction::RemovePlayers(players) => players.size_hint(),
+        }
+    }
+}
+
+field!(Objective,
+    value: VarIntLengthPrefixedString,
+    objective_type: VarIntLengthPrefixedString // "integer" or "hearts"
+);
+
+#[derive(Clone, Debug)]
+pub enum ObjectiveAction {
+    Create(Objective),
+    Remove,
+    Update(Objective),
+}
+
+impl Default for ObjectiveAction {
+    fn default() -> Self {
+        ObjectiveAction::Remove
+    }
+}
+
+impl ReadField for ObjectiveAction {
+    fn read(bytes: &[u8], index: usize) -> Result<(ObjectiveAction, usize), DecodeError> {
+        let (mode, length) = <u8 as ReadField>::read(bytes, index)?;
+        match mode {
+            0 | 2 => {
+                let (objective, size) = <Objective as ReadField>::read(bytes, index + length)?;
+                let action = if mode == 0 { ObjectiveAction::Create(objective) } else { ObjectiveAction::Update(objective) };
+                Ok((action, length + size))
+            }
+            1 => Ok((ObjectiveAction::Remove, length)),
+            _ => Err(DecodeError::UnknownVariant)
+        }
+    }
+}
+
+impl WriteField for ObjectiveAction {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            ObjectiveAction::Create(objective) => {
+                buf.push(0);
+                objective.write_to(buf);
+            }
+            ObjectiveAction::Remove => buf.push(1),
+            ObjectiveAction::Update(objective) => {
+                buf.push(2);
+                objective.write_to(buf);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        match self {
+            ObjectiveAction::Create(objective) | ObjectiveAction::Update(objective) => 1 + objective.size_hint(),
+            ObjectiveAction::Remove => 1
+        }
+    }
+}
+
+/// 1.8 sends the objective name for removals too, & the value as a VarInt
+#[derive(Clone, Debug)]
+pub enum ScoreAction {
+    Update {
+        objective_name: VarIntLengthPrefixedString,
+        value: VarInt,
+    },
+    Remove {
+        objective_name: VarIntLengthPrefixedString,
+    },
+}
+
+impl Default for ScoreAction {
+    fn default() -> Self {
+        ScoreAction::Remove { objective_name: VarIntLengthPrefixedString::default() }
+    }
+}
+
+impl ReadField for ScoreAction {
+    fn read(bytes: &[u8], index: usize) -> Result<(ScoreAction, usize), DecodeError> {
+        let (action, length) = <u8 as ReadField>::read(bytes, index)?;
+        let (objective_name, size) = <VarIntLengthPrefixedString as ReadField>::read(bytes, index + length)?;
+        match action {
+            0 => {
+                let (value, value_size) = <VarInt as ReadField>::read(bytes, index + length + size)?;
+                Ok((ScoreAction::Update { objective_name, value }, length + size + value_size))
+            }
+            1 => Ok((ScoreAction::Remove { objective_name }, length + size)),
+            _ => Err(DecodeError::UnknownVariant)
+        }
+    }
+}
+
+impl WriteField for ScoreAction {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            ScoreAction::Update { objective_name, value } => {
+                buf.push(0);
+                objective_name.write_to(buf);
+                value.write_to(buf);
+            }
+            ScoreAction::Remove { objective_name } => {
+                buf.push(1);
+                objective_name.write_to(buf);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        match self {
+            ScoreAction::Update { objective_name, value } => 1 + objective_name.size_hint() + value.size_hint(),
+            ScoreAction::Remove { objective_name } => 1 + objective_name.size_hint()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum CombatEvent {
+    EnterCombat,
+    EndCombat {
+        duration: VarInt,
+        entity_id: i32,
+    },
+    EntityDead {
+        player_id: VarInt,
+        entity_id: i32,
+        message: VarIntLengthPrefixedString,
+    },
+}
+
+impl Default for CombatEvent {
+    fn default() -> Self {
+        CombatEvent::EnterCombat
+    }
+}
+
+impl ReadField for CombatEvent {
+    fn read(bytes: &[u8], index: usize) -> Result<(CombatEvent, usize), DecodeError> {
+        let (event, mut length) = <VarInt as ReadField>::read(bytes, index)?;
+        let event = match event.0 {
+            0 => CombatEvent::EnterCombat,
+            1 => {
+                let (duration, size) = <VarInt as ReadField>::read(bytes, index + length)?;
+                length += size;
+                let (entity_id, size) = <i32 as ReadField>::read(bytes, index + length)?;
+                length += size;
+                CombatEvent::EndCombat { duration, entity_id }
+            }
+            2 => {
+                let (player_id, size) = <VarInt as ReadField>::read(bytes, index + length)?;
+                length += size;
+                let (entity_id, size) = <i32 as ReadField>::read(bytes, index + length)?;
+                length += size;
+                let (message, size) = <VarIntLengthPrefixedString as ReadField>::read(bytes, index + length)?;
+                length += size;
+                CombatEvent::EntityDead { player_id, entity_id, message }
+            }
+            _ => return Err(DecodeError::UnknownVariant)
+        };
+        Ok((event, length))
+    }
+}
+
+impl WriteField for CombatEvent {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            CombatEvent::EnterCombat => VarInt(0).write_to(buf),
+            CombatEvent::EndCombat { duration, entity_id } => {
+                VarInt(1).write_to(buf);
+                duration.write_to(buf);
+                entity_id.write_to(buf);
+            }
+            CombatEvent::EntityDead { player_id, entity_id, message } => {
+                VarInt(2).write_to(buf);
+                player_id.write_to(buf);
+                entity_id.write_to(buf);
+                message.write_to(buf);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        match self {
+            CombatEvent::EnterCombat => 1,
+            CombatEvent::EndCombat { duration, .. } => 5 + duration.size_hint(),
+            CombatEvent::EntityDead { player_id, message, .. } => 5 + player_id.size_hint() + message.size_hint()
+        }
+    }
+}
+
+field!(LerpSize,
+    old_radius: f64,
+    new_radius: f64,
+    speed: VarLong // milliseconds until the new radius is reached
+);
+
+field!(InitializeWorldBorder,
+    x: f64,
+    z: f64,
+    old_radius: f64,
+    new_radius: f64,
+    speed: VarLong,
+    portal_teleport_boundary: VarInt,
+    warning_time: VarInt,
+    warning_blocks: VarInt
+);
+
+#[derive(Clone, Debug)]
+pub enum WorldBorderAction {
+    SetSize(f64),
+    LerpSize(LerpSize),
+    SetCenter(f64, f64),
+    Initialize(InitializeWorldBorder),
+    SetWarningTime(VarInt),
+    SetWarningBlocks(VarInt),
+}
+
+impl Default for WorldBorderAction {
+    fn default() -> Self {
+        WorldBorderAction::SetSize(0.0)
+    }
+}
+
+impl ReadField for WorldBorderAction {
+    fn read(bytes: &[u8], index: usize) -> Result<(WorldBorderAction, usize), DecodeError> {
+        let (action, length) = <VarInt as ReadField>::read(bytes, index)?;
+        let i = index + length;
+        let (action, size) = match action.0 {
+            0 => ReadField::read(bytes, i).map(|(v, s)| (WorldBorderAction::SetSize(v), s))?,
+            1 => ReadField::read(bytes, i).map(|(v, s)| (WorldBorderAction::LerpSize(v), s))?,
+            2 => {
+                let (x, _) = <f64 as ReadField>::read(bytes, i)?;
+                let (z, _) = <f64 as ReadField>::read(bytes, i + 8)?;
+                (WorldBorderAction::SetCenter(x, z), 16)
+            }
+            3 => ReadField::read(bytes, i).map(|(v, s)| (WorldBorderAction::Initialize(v), s))?,
+            4 => ReadField::read(bytes, i).map(|(v, s)| (WorldBorderAction::SetWarningTime(v), s))?,
+            5 => ReadField::read(bytes, i).map(|(v, s)| (WorldBorderAction::SetWarningBlocks(v), s))?,
+            _ => return Err(DecodeError::UnknownVariant)
+        };
+        Ok((action, length + size))
+    }
+}
+
+impl WriteField for WorldBorderAction {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            WorldBorderAction::SetSize(radius) => {
+                VarInt(0).write_to(buf);
+                radius.write_to(buf);
+            }
+            WorldBorderAction::LerpSize(lerp) => {
+                VarInt(1).write_to(buf);
+                lerp.write_to(buf);
+            }
+            WorldBorderAction::SetCenter(x, z) => {
+                VarInt(2).write_to(buf);
+                x.write_to(buf);
+                z.write_to(buf);
+            }
+            WorldBorderAction::Initialize(border) => {
+                VarInt(3).write_to(buf);
+                border.write_to(buf);
+            }
+            WorldBorderAction::SetWarningTime(time) => {
+                VarInt(4).write_to(buf);
+                time.write_to(buf);
+            }
+            WorldBorderAction::SetWarningBlocks(blocks) => {
+                VarInt(5).write_to(buf);
+                blocks.write_to(buf);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        1 + match self {
+            WorldBorderAction::SetSize(_) => 8,
+            WorldBorderAction::LerpSize(lerp) => lerp.size_hint(),
+            WorldBorderAction::SetCenter(..) => 16,
+            WorldBorderAction::Initialize(border) => border.size_hint(),
+            WorldBorderAction::SetWarningTime(value) | WorldBorderAction::SetWarningBlocks(value) => value.size_hint(),
+        }
+    }
+}
+
+field!(TitleTimes,
+    fade_in: i32, // in ticks
+    stay: i32,
+    fade_out: i32
+);
+
+#[derive(Clone, Debug)]
+pub enum TitleAction {
+    SetTitle(TextComponent),
+    SetSubtitle(TextComponent),
+    SetTimes(TitleTimes),
+    Hide,
+    Reset,
+}
+
+impl Default for TitleAction {
+    fn default() -> Self {
+        TitleAction::Reset
+    }
+}
+
+impl ReadField for TitleAction {
+    fn read(bytes: &[u8], index: usize) -> Result<(TitleAction, usize), DecodeError> {
+        let (action, length) = <VarInt as ReadField>::read(bytes, index)?;
+        let i = index + length;
+        let (action, size) = match action.0 {
+            0 => ReadField::read(bytes, i).map(|(v, s)| (TitleAction::SetTitle(v), s))?,
+            1 => ReadField::read(bytes, i).map(|(v, s)| (TitleAction::SetSubtitle(v), s))?,
+            2 => ReadField::read(bytes, i).map(|(v, s)| (TitleAction::SetTimes(v), s))?,
+            3 => (TitleAction::Hide, 0),
+            4 => (TitleAction::Reset, 0),
+            _ => return Err(DecodeError::UnknownVariant)
+        };
+        Ok((action, length + size))
+    }
+}
+
+impl WriteField for TitleAction {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            TitleAction::SetTitle(title) => {
+                VarInt(0).write_to(buf);
+                title.write_to(buf);
+            }
+            TitleAction::SetSubtitle(subtitle) => {
+                VarInt(1).write_to(buf);
+                subtitle.write_to(buf);
+            }
+            TitleAction::SetTimes(times) => {
+                VarInt(2).write_to(buf);
+                times.write_to(buf);
+            }
+            TitleAction::Hide => VarInt(3).write_to(buf),
+            TitleAction::Reset => VarInt(4).write_to(buf),
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        match self {
+            TitleAction::SetTimes(_) => 13,
+            _ => 1
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum UseEntityAction {
+    Interact,
+    Attack,
+    /// Where on the entity was clicked
+    InteractAt(f32, f32, f32),
+}
+
+impl Default for UseEntityAction {
+    fn default() -> Self {
+        UseEntityAction::Interact
+    }
+}
+
+impl ReadField for UseEntityAction {
+    fn read(bytes: &[u8], index: usize) -> Result<(UseEntityAction, usize), DecodeError> {
+        let (action, length) = <VarInt as ReadField>::read(bytes, index)?;
+        match action.0 {
+            0 => Ok((UseEntityAction::Interact, length)),
+            1 => Ok((UseEntityAction::Attack, length)),
+            2 => {
+                let (x, _) = <f32 as ReadField>::read(bytes, index + length)?;
+                let (y, _) = <f32 as ReadField>::read(bytes, index + length + 4)?;
+                let (z, _) = <f32 as ReadField>::read(bytes, index + length + 8)?;
+                Ok((UseEntityAction::InteractAt(x, y, z), length + 12))
+            }
+            _ => Err(DecodeError::UnknownVariant)
+        }
+    }
+}
+
+impl WriteField for UseEntityAction {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            UseEntityAction::Interact => VarInt(0).write_to(buf),
+            UseEntityAction::Attack => VarInt(1).write_to(buf),
+            UseEntityAction::InteractAt(x, y, z) => {
+                VarInt(2).write_to(buf);
+                x.write_to(buf);
+                y.write_to(buf);
+                z.write_to(buf);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        match self {
+            UseEntityAction::InteractAt(..) => 13,
+            _ => 1
+        }
+    }
+}
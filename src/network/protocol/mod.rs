@@ -1,5 +1,6 @@
 use network::packet::*;
 use network::types::*;
+use network::chat::TextComponent;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum ProtocolEdition {
@@ -44,10 +45,30 @@ pub trait ProtocolType: Send + Sync {
     fn name(&self) -> &str;
     fn protocol_type(&self) -> ProtocolEdition;
     fn protocol_version(&self) -> i32;
-    fn read(&self, id: i32, state: State, bound: Bound, bytes: Vec<u8>) -> Option<Packet>;
+    /// Returns the packet & how many bytes it took up
+    fn read(&self, id: i32, state: State, bound: Bound, bytes: &[u8]) -> Result<(Packet, usize), DecodeError>;
+    /// Java Edition packets start with `JAVA_FRAME_HEADER_SIZE` bytes of space for the frame header
     fn write(&self, packet: Packet, bound: Bound) -> Option<Vec<u8>>;
+
+    /// Reads a packet that should take up all of `bytes`, like a Java frame or an offline datagram
+    fn read_exact(&self, id: i32, state: State, bound: Bound, bytes: &[u8]) -> Result<Packet, DecodeError> {
+        let (packet, length) = self.read(id, state, bound, bytes)?;
+        if length != bytes.len() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        Ok(packet)
+    }
+
+    /// Like `read_exact`, but an unknown id or a malformed packet comes back as `Packet::Unknown`
+    fn read_or_unknown(&self, id: i32, state: State, bound: Bound, bytes: &[u8]) -> Packet {
+        self.read_exact(id, state, bound, bytes).unwrap_or_else(|_| Packet::Unknown { id, state, bytes: bytes.to_vec() })
+    }
 }
 
+/// Space left at the start of a written Java packet for the length prefix & the compressed
+/// data length, so the packet doesn't have to be copied to prepend them
+pub const JAVA_FRAME_HEADER_SIZE: usize = 4;
+
 #[macro_export]
 macro_rules! protocol {
     ($pretty_name:expr, $protocol_name:ident, $protocol_type:expr, $protocol_version:expr,
@@ -73,19 +94,17 @@ macro_rules! protocol {
                 $protocol_version
             }
 
-            fn read(&self, id: i32, state: State, bound: Bound, bytes: Vec<u8>) -> Option<Packet> {
+            fn read(&self, id: i32, state: State, bound: Bound, bytes: &[u8]) -> Result<(Packet, usize), DecodeError> {
                 $(
                     if id == $id && state == State::$state && (bound == Bound::$bound || Bound::$bound == Bound::Any) {
                         let mut packet = $package::$protocol::$packet_name::default();
+                        let length = packet.read(bytes)?;
                         paste::item! {
-                            return match packet.read(bytes) {
-                                true =>  Some(Packet::[<$package _ $protocol _ $packet_name>](packet)),
-                                false => None
-                            }
+                            return Ok((Packet::[<$package _ $protocol _ $packet_name>](packet), length));
                         }
                     }
                 )*
-                None
+                Err(DecodeError::UnknownVariant)
             }
 
             fn write(&self, packet: Packet, bound: Bound) -> Option<Vec<u8>> {
@@ -95,15 +114,22 @@ macro_rules! protocol {
                             if let Packet::[<$package _ $protocol _ $packet_name>](packet) = packet {
                                 let id = $id;
                                 if $protocol_type == ProtocolEdition::JavaEdition {
-                                    let mut buf = VarInt(id).write();
-                                    buf.append(&mut packet.write());
-                                    // prepend length as a varint
-                                    let mut full = VarInt(buf.len() as i32).write();
-                                    full.append(&mut buf);
-                                    return Some(full);
+                                    // the length prefix is filled in by the Connection since it
+                                    // depends on whether compression is enabled
+                                    let mut buf = Vec::with_capacity(JAVA_FRAME_HEADER_SIZE + 5 + packet.size_hint());
+                                    buf.resize(JAVA_FRAME_HEADER_SIZE, 0);
+                                    VarInt(id).write_to(&mut buf);
+                                    packet.write_to(&mut buf);
+                                    return Some(buf);
                                 } else if $protocol_type == ProtocolEdition::BedrockEdition {
-                                    let mut buf = vec![id as u8];
-                                    buf.append(&mut packet.write());
+                                    // Minecraft packets have a VarUInt header, RakNet packets a single byte
+                                    let mut buf = Vec::with_capacity(5 + packet.size_hint());
+                                    if State::$state == State::BedrockMinecraft {
+                                        VarInt(id).write_to(&mut buf);
+                                    } else {
+                                        buf.push(id as u8);
+                                    }
+                                    packet.write_to(&mut buf);
                                     return Some(buf);
                                 } else {
                                     return None;
@@ -148,7 +174,7 @@ macro_rules! protocol_registry {
                 }
             }
 
-            fn read(&self, id: i32, state: State, bound: Bound, bytes: Vec<u8>) -> Option<Packet> {
+            fn read(&self, id: i32, state: State, bound: Bound, bytes: &[u8]) -> Result<(Packet, usize), DecodeError> {
                 match self {
                     $(Protocol::$protocol_name(protocol) => protocol.read(id, state, bound, bytes),)*
                 }
@@ -161,11 +187,13 @@ macro_rules! protocol_registry {
             }
         }
 
-        pub fn get_protocol(version: i32) -> Option<Protocol> {
+        /// Java & Bedrock protocol versions overlap, so the edition has to match too
+        pub fn get_protocol(edition: ProtocolEdition, version: i32) -> Option<Protocol> {
             $(
                 let protocol_id = paste::expr! { $package::$version::[<$protocol_name _Id>] };
-                if version == protocol_id {
-                    return Some(Protocol::$protocol_name($package::$version::$protocol_name));
+                let protocol = Protocol::$protocol_name($package::$version::$protocol_name);
+                if version == protocol_id && protocol.protocol_type() == edition {
+                    return Some(protocol);
                 }
             )*
             None
@@ -180,6 +208,9 @@ macro_rules! packet_registry {
             #[allow(non_camel_case_types)]
             pub enum Packet {
                 $([<$package _ $protocol _ $packet_name>]($package::$protocol::$packet_name),)*
+                /// A packet we don't have a definition for, or that couldn't be read.
+                /// `bytes` is everything after the id.
+                Unknown { id: i32, state: State, bytes: Vec<u8> },
             }
         }
         paste::item! {
@@ -187,24 +218,48 @@ macro_rules! packet_registry {
                 fn name(&self) -> &str {
                     match self {
                         $(Packet::[<$package _ $protocol _ $packet_name>](packet) => packet.name(),)*
+                        Packet::Unknown { .. } => "Unknown",
                     }
                 }
 
-                fn read(&mut self, bytes: Vec<u8>) -> bool {
+                fn read(&mut self, bytes: &[u8]) -> Result<usize, DecodeError> {
                     match self {
                         $(Packet::[<$package _ $protocol _ $packet_name>](packet) => packet.read(bytes),)*
+                        Packet::Unknown { bytes: ref mut unknown, .. } => {
+                            *unknown = bytes.to_vec();
+                            Ok(bytes.len())
+                        }
                     }
                 }
 
-                fn write(&self) -> Vec<u8> {
+                fn write_to(&self, buf: &mut Vec<u8>) {
                     match self {
-                        $(Packet::[<$package _ $protocol _ $packet_name>](packet) => packet.write(),)*
+                        $(Packet::[<$package _ $protocol _ $packet_name>](packet) => packet.write_to(buf),)*
+                        Packet::Unknown { bytes, .. } => buf.extend_from_slice(bytes),
+                    }
+                }
+
+                fn size_hint(&self) -> usize {
+                    match self {
+                        $(Packet::[<$package _ $protocol _ $packet_name>](packet) => packet.size_hint(),)*
+                        Packet::Unknown { bytes, .. } => bytes.len(),
                     }
                 }
 
                 fn next_state(&self) -> Option<State> {
                     match self {
                         $(Packet::[<$package _ $protocol _ $packet_name>](packet) => packet.next_state(),)*
+                        Packet::Unknown { .. } => None,
+                    }
+                }
+            }
+
+            impl Packet {
+                /// The module the packet is defined in, like `raknet` or `v1_8`
+                pub fn module(&self) -> &str {
+                    match self {
+                        $(Packet::[<$package _ $protocol _ $packet_name>](_) => stringify!($protocol),)*
+                        Packet::Unknown { .. } => "unknown",
                     }
                 }
             }
@@ -239,8 +294,23 @@ packet_registry! {
     bedrock raknet NakPacket
     bedrock raknet AckPacket
 
+    // 1.5
+    bedrock v1_5 LoginPacket
+    bedrock v1_5 PlayStatusPacket
+    bedrock v1_5 ServerToClientHandshakePacket
+    bedrock v1_5 ClientToServerHandshakePacket
+    bedrock v1_5 DisconnectPacket
+    bedrock v1_5 MovePlayerPacket
+    bedrock v1_5 MapInfoRequestPacket
+    bedrock v1_5 RequestChunkRadiusPacket
+    bedrock v1_5 ChunkRadiusUpdatedPacket
+
     // JAVA PACKETS \\
 
+    // Legacy
+    java legacy LegacyPingPacket
+    java legacy BetaPingPacket
+
     // 1.7
     // Handshake
     java v1_7 HandshakePacket
@@ -259,16 +329,177 @@ packet_registry! {
     // Play
     java v1_7 KeepAlivePacket
     java v1_7 JoinGamePacket
+    java v1_7 ClientboundChatMessagePacket
+    java v1_7 TimeUpdatePacket
+    java v1_7 EntityEquipmentPacket
+    java v1_7 SpawnPositionPacket
+    java v1_7 UpdateHealthPacket
+    java v1_7 RespawnPacket
+    java v1_7 ClientboundPlayerPositionAndLookPacket
+    java v1_7 ClientboundHeldItemChangePacket
+    java v1_7 UseBedPacket
+    java v1_7 ClientboundAnimationPacket
+    java v1_7 SpawnPlayerPacket
+    java v1_7 CollectItemPacket
+    java v1_7 SpawnObjectPacket
+    java v1_7 SpawnMobPacket
+    java v1_7 SpawnPaintingPacket
+    java v1_7 SpawnExperienceOrbPacket
+    java v1_7 EntityVelocityPacket
+    java v1_7 DestroyEntitiesPacket
+    java v1_7 EntityPacket
+    java v1_7 EntityRelativeMovePacket
+    java v1_7 EntityLookPacket
+    java v1_7 EntityLookAndRelativeMovePacket
+    java v1_7 EntityTeleportPacket
+    java v1_7 EntityHeadLookPacket
+    java v1_7 EntityStatusPacket
+    java v1_7 AttachEntityPacket
+    java v1_7 EntityMetadataPacket
+    java v1_7 EntityEffectPacket
+    java v1_7 RemoveEntityEffectPacket
+    java v1_7 SetExperiencePacket
+    java v1_7 EntityPropertiesPacket
+    java v1_7 ChunkDataPacket
+    java v1_7 MultiBlockChangePacket
+    java v1_7 BlockChangePacket
+    java v1_7 BlockActionPacket
+    java v1_7 BlockBreakAnimationPacket
+    java v1_7 MapChunkBulkPacket
+    java v1_7 ExplosionPacket
+    java v1_7 EffectPacket
+    java v1_7 SoundEffectPacket
+    java v1_7 ParticlePacket
+    java v1_7 ChangeGameStatePacket
+    java v1_7 SpawnGlobalEntityPacket
+    java v1_7 OpenWindowPacket
+    java v1_7 CloseWindowPacket
+    java v1_7 SetSlotPacket
+    java v1_7 WindowItemsPacket
+    java v1_7 WindowPropertyPacket
+    java v1_7 ConfirmTransactionPacket
+    java v1_7 UpdateSignPacket
+    java v1_7 MapsPacket
+    java v1_7 UpdateBlockEntityPacket
+    java v1_7 SignEditorOpenPacket
+    java v1_7 StatisticsPacket
+    java v1_7 PlayerListItemPacket
+    java v1_7 PlayerAbilitiesPacket
+    java v1_7 ClientboundTabCompletePacket
+    java v1_7 ScoreboardObjectivePacket
+    java v1_7 UpdateScorePacket
+    java v1_7 DisplayScoreboardPacket
+    java v1_7 TeamsPacket
+    java v1_7 PluginMessagePacket
+    java v1_7 PlayDisconnectPacket
+    java v1_7 ServerboundChatMessagePacket
+    java v1_7 UseEntityPacket
+    java v1_7 PlayerPacket
+    java v1_7 PlayerPositionPacket
+    java v1_7 PlayerLookPacket
+    java v1_7 ServerboundPlayerPositionAndLookPacket
+    java v1_7 PlayerDiggingPacket
+    java v1_7 PlayerBlockPlacementPacket
+    java v1_7 ServerboundHeldItemChangePacket
+    java v1_7 ServerboundAnimationPacket
+    java v1_7 EntityActionPacket
+    java v1_7 SteerVehiclePacket
+    java v1_7 ClickWindowPacket
+    java v1_7 CreativeInventoryActionPacket
+    java v1_7 EnchantItemPacket
+    java v1_7 ServerboundTabCompletePacket
+    java v1_7 ClientSettingsPacket
+    java v1_7 ClientStatusPacket
 
     // 1.8
+    // Login
+    java v1_8 EncryptionResponsePacket
+    java v1_8 EncryptionRequestPacket
+    // Play
+    java v1_8 KeepAlivePacket
     java v1_8 JoinGamePacket
+    java v1_8 ClientboundChatMessagePacket
+    java v1_8 EntityEquipmentPacket
+    java v1_8 SpawnPositionPacket
+    java v1_8 UpdateHealthPacket
+    java v1_8 ClientboundPlayerPositionAndLookPacket
+    java v1_8 UseBedPacket
+    java v1_8 SpawnPlayerPacket
+    java v1_8 CollectItemPacket
+    java v1_8 SpawnMobPacket
+    java v1_8 SpawnPaintingPacket
+    java v1_8 EntityVelocityPacket
+    java v1_8 DestroyEntitiesPacket
+    java v1_8 EntityPacket
+    java v1_8 EntityRelativeMovePacket
+    java v1_8 EntityLookPacket
+    java v1_8 EntityLookAndRelativeMovePacket
+    java v1_8 EntityTeleportPacket
+    java v1_8 EntityHeadLookPacket
+    java v1_8 EntityMetadataPacket
+    java v1_8 EntityEffectPacket
+    java v1_8 RemoveEntityEffectPacket
+    java v1_8 SetExperiencePacket
+    java v1_8 EntityPropertiesPacket
+    java v1_8 ChunkDataPacket
+    java v1_8 MultiBlockChangePacket
+    java v1_8 BlockChangePacket
+    java v1_8 BlockActionPacket
+    java v1_8 BlockBreakAnimationPacket
+    java v1_8 MapChunkBulkPacket
+    java v1_8 EffectPacket
+    java v1_8 ParticlePacket
+    java v1_8 OpenWindowPacket
+    java v1_8 SetSlotPacket
+    java v1_8 WindowItemsPacket
+    java v1_8 UpdateSignPacket
+    java v1_8 MapsPacket
+    java v1_8 UpdateBlockEntityPacket
+    java v1_8 SignEditorOpenPacket
+    java v1_8 PlayerListItemPacket
+    java v1_8 ScoreboardObjectivePacket
+    java v1_8 UpdateScorePacket
+    java v1_8 TeamsPacket
+    java v1_8 PluginMessagePacket
+    java v1_8 ServerDifficultyPacket
+    java v1_8 CombatEventPacket
+    java v1_8 CameraPacket
+    java v1_8 WorldBorderPacket
+    java v1_8 TitlePacket
+    java v1_8 PlaySetCompressionPacket
+    java v1_8 PlayerListHeaderAndFooterPacket
+    java v1_8 ResourcePackSendPacket
+    java v1_8 UpdateEntityNbtPacket
+    java v1_8 UseEntityPacket
+    java v1_8 PlayerPositionPacket
+    java v1_8 ServerboundPlayerPositionAndLookPacket
+    java v1_8 PlayerDiggingPacket
+    java v1_8 PlayerBlockPlacementPacket
+    java v1_8 ServerboundAnimationPacket
+    java v1_8 EntityActionPacket
+    java v1_8 SteerVehiclePacket
+    java v1_8 ClickWindowPacket
+    java v1_8 CreativeInventoryActionPacket
+    java v1_8 ServerboundTabCompletePacket
+    java v1_8 ClientSettingsPacket
+    java v1_8 ClientStatusPacket
+    java v1_8 SpectatePacket
+    java v1_8 ResourcePackStatusPacket
 
     // 1.9
     java v1_9 JoinGamePacket
+
+    // 1.12
+    java v1_12 KeepAlivePacket
+
+    // 1.14
+    java v1_14 JoinGamePacket
+    java v1_14 SpawnPositionPacket
 }
 
 protocol_registry!(
     bedrock raknet ProtocolBedrockRakNet
+    bedrock v1_5 ProtocolBedrock_1_5
     java v1_7 ProtocolJava_1_7
     java v1_8 ProtocolJava_1_8
     java v1_9 ProtocolJava_1_9
@@ -0,0 +1,1084 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use network::packet::*;
+use network::types::*;
+
+/// RakNet has 32 ordering channels
+pub const ORDER_CHANNELS: usize = 32;
+/// How far ahead of what we're expecting we'll hold on to frames & sequence numbers.
+/// Anything further ahead is dropped so a client can't make us buffer forever.
+const MAX_WINDOW: u32 = 1024;
+
+/// Limits on split packets a client can make us hold on to
+const MAX_SPLIT_COUNT: u32 = 2048;
+const MAX_CONCURRENT_SPLITS: usize = 4;
+const MAX_SPLIT_BYTES: usize = 4 * 1024 * 1024;
+/// Limit on the frames held back waiting for an earlier order index, across every channel
+const MAX_PENDING_BYTES: usize = 4 * 1024 * 1024;
+/// Split packets that haven't been completed in this long are thrown away
+const SPLIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sequence numbers & indexes are 24 bits and wrap around
+const TRIAD_MASK: u32 = 0xFFFFFF;
+/// Half of the triad range. Anything further ahead than this is treated as behind.
+const HALF_TRIAD_RANGE: u32 = 1 << 23;
+
+/// The client is considered gone if it leaves us waiting on ACKs this long
+const ACK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Or if this many of our datagrams are waiting to be acknowledged
+const MAX_RESEND_QUEUE: usize = 4096;
+
+/// RakNet won't go outside of these
+pub const MIN_MTU_SIZE: u16 = 576;
+pub const MAX_MTU_SIZE: u16 = 1492;
+/// IP & UDP headers
+const UDP_HEADER_SIZE: usize = 28;
+/// Flags & sequence number
+const DATAGRAM_HEADER_SIZE: usize = 4;
+/// The biggest a frame header can be: flags, length, indexes, order channel & split info
+const MAX_FRAME_HEADER_SIZE: usize = 1 + 2 + 3 + 3 + 3 + 1 + 10;
+
+const INITIAL_RTO: Duration = Duration::from_secs(1);
+const MIN_RTO: Duration = Duration::from_millis(100);
+const MAX_RTO: Duration = Duration::from_secs(3);
+
+/// Datagram header flags
+pub const FLAG_VALID: u8 = 0x80;
+pub const FLAG_ACK: u8 = 0x40;
+pub const FLAG_NAK: u8 = 0x20;
+pub const FLAG_NEEDS_B_AND_AS: u8 = 0x04;
+
+/// How a frame should be delivered, from RakNet's PacketPriority.h
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Reliability {
+    Unreliable,
+    UnreliableSequenced,
+    Reliable,
+    ReliableOrdered,
+    ReliableSequenced,
+    UnreliableWithAckReceipt,
+    ReliableWithAckReceipt,
+    ReliableOrderedWithAckReceipt,
+}
+
+impl Reliability {
+    pub fn from_id(id: u8) -> Reliability {
+        match id {
+            1 => Reliability::UnreliableSequenced,
+            2 => Reliability::Reliable,
+            3 => Reliability::ReliableOrdered,
+            4 => Reliability::ReliableSequenced,
+            5 => Reliability::UnreliableWithAckReceipt,
+            6 => Reliability::ReliableWithAckReceipt,
+            7 => Reliability::ReliableOrderedWithAckReceipt,
+            _ => Reliability::Unreliable,
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        match self {
+            Reliability::Unreliable => 0,
+            Reliability::UnreliableSequenced => 1,
+            Reliability::Reliable => 2,
+            Reliability::ReliableOrdered => 3,
+            Reliability::ReliableSequenced => 4,
+            Reliability::UnreliableWithAckReceipt => 5,
+            Reliability::ReliableWithAckReceipt => 6,
+            Reliability::ReliableOrderedWithAckReceipt => 7,
+        }
+    }
+
+    /// Has a reliable message index
+    pub fn is_reliable(&self) -> bool {
+        match self {
+            Reliability::Reliable
+            | Reliability::ReliableOrdered
+            | Reliability::ReliableSequenced
+            | Reliability::ReliableWithAckReceipt
+            | Reliability::ReliableOrderedWithAckReceipt => true,
+            _ => false
+        }
+    }
+
+    /// Has a sequencing index
+    pub fn is_sequenced(&self) -> bool {
+        match self {
+            Reliability::UnreliableSequenced | Reliability::ReliableSequenced => true,
+            _ => false
+        }
+    }
+
+    /// Has an order index & channel. Sequenced frames have these too.
+    pub fn is_ordered(&self) -> bool {
+        match self {
+            Reliability::UnreliableSequenced
+            | Reliability::ReliableOrdered
+            | Reliability::ReliableSequenced
+            | Reliability::ReliableOrderedWithAckReceipt => true,
+            _ => false
+        }
+    }
+}
+
+/// Where a fragment fits in a split packet
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SplitInfo {
+    pub count: u32,
+    pub id: u16,
+    pub index: u32,
+}
+
+/// A message inside of a datagram
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub reliability: Reliability,
+    pub reliable_index: u32,
+    pub sequence_index: u32,
+    pub order_index: u32,
+    pub order_channel: u8,
+    pub split: Option<SplitInfo>,
+    pub body: Vec<u8>,
+}
+
+impl Frame {
+    /// Reads a frame starting at `index`. Returns the frame & how many bytes it took up.
+    pub fn read(buf: &[u8], mut index: usize) -> Result<(Frame, usize), DecodeError> {
+        let start_index = index;
+        let flags = <u8 as ReadField>::read(buf, index)?.0;
+        index += 1;
+        let reliability = Reliability::from_id(flags >> 5);
+        let split = flags & 0x10 == 0x10;
+
+        // this short is actually the body's *bit* length
+        let (bits, v) = <u16 as ReadField>::read(buf, index)?;
+        index += v;
+        let length = (bits as usize + 7) / 8;
+
+        let mut frame = Frame {
+            reliability,
+            reliable_index: 0,
+            sequence_index: 0,
+            order_index: 0,
+            order_channel: 0,
+            split: None,
+            body: vec![],
+        };
+
+        if reliability.is_reliable() {
+            frame.reliable_index = read_triad(buf, index)?;
+            index += 3;
+        }
+
+        if reliability.is_sequenced() {
+            frame.sequence_index = read_triad(buf, index)?;
+            index += 3;
+        }
+
+        if reliability.is_ordered() {
+            frame.order_index = read_triad(buf, index)?;
+            index += 3;
+            frame.order_channel = <u8 as ReadField>::read(buf, index)?.0;
+            index += 1;
+        }
+
+        if split {
+            let (count, v) = <u32 as ReadField>::read(buf, index)?;
+            index += v;
+            let (id, v) = <u16 as ReadField>::read(buf, index)?;
+            index += v;
+            let (split_index, v) = <u32 as ReadField>::read(buf, index)?;
+            index += v;
+            frame.split = Some(SplitInfo {
+                count,
+                id,
+                index: split_index,
+            });
+        }
+
+        frame.body = read_slice(buf, index, length)?.to_vec();
+        index += length;
+
+        Ok((frame, index - start_index))
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let mut flags = self.reliability.id() << 5;
+        if self.split.is_some() {
+            flags |= 0x10;
+        }
+
+        let mut buf = vec![flags];
+        buf.append(&mut ((self.body.len() * 8) as u16).write());
+
+        if self.reliability.is_reliable() {
+            buf.extend_from_slice(&write_triad(self.reliable_index));
+        }
+
+        if self.reliability.is_sequenced() {
+            buf.extend_from_slice(&write_triad(self.sequence_index));
+        }
+
+        if self.reliability.is_ordered() {
+            buf.extend_from_slice(&write_triad(self.order_index));
+            buf.push(self.order_channel);
+        }
+
+        if let Some(split) = self.split {
+            buf.append(&mut split.count.write());
+            buf.append(&mut split.id.write());
+            buf.append(&mut split.index.write());
+        }
+
+        buf.extend_from_slice(&self.body);
+        buf
+    }
+}
+
+/// RakNet uses little endian 24 bit numbers for sequence numbers & indexes
+pub fn read_triad(buf: &[u8], index: usize) -> Result<u32, DecodeError> {
+    let bytes = read_slice(buf, index, 3)?;
+    Ok(bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16)
+}
+
+pub fn write_triad(value: u32) -> [u8; 3] {
+    [
+        (value & 0xFF) as u8,
+        ((value >> 8) & 0xFF) as u8,
+        ((value >> 16) & 0xFF) as u8,
+    ]
+}
+
+/// How far `to` is ahead of `from`, wrapping at 2^24
+fn triad_distance(from: u32, to: u32) -> u32 {
+    to.wrapping_sub(from) & TRIAD_MASK
+}
+
+/// Whether `a` comes before `b`, wrapping at 2^24
+fn triad_before(a: u32, b: u32) -> bool {
+    a != b && triad_distance(a, b) < HALF_TRIAD_RANGE
+}
+
+fn next_triad(value: u32) -> u32 {
+    (value + 1) & TRIAD_MASK
+}
+
+/// Turns a set of sequence numbers into the ranges used by ACKs & NAKs
+pub fn to_ranges(sequence_numbers: &BTreeSet<u32>) -> IntRangeList {
+    let mut ranges = Vec::new();
+    let mut iter = sequence_numbers.iter();
+    if let Some(&first) = iter.next() {
+        let (mut start, mut end) = (first, first);
+        for &n in iter {
+            if n == next_triad(end) {
+                end = n;
+            } else {
+                ranges.push((write_triad(start), write_triad(end)));
+                start = n;
+                end = n;
+            }
+        }
+        ranges.push((write_triad(start), write_triad(end)));
+    }
+    IntRangeList(ranges)
+}
+
+/// Every sequence number covered by the ranges in an ACK or NAK
+pub fn from_ranges(ranges: &IntRangeList) -> Vec<u32> {
+    let mut sequence_numbers = Vec::new();
+    for (start, end) in ranges.0.iter() {
+        let start = read_triad(start, 0).unwrap_or(0);
+        let end = read_triad(end, 0).unwrap_or(0);
+        let length = triad_distance(start, end);
+        if length > MAX_WINDOW {
+            // bogus range
+            continue;
+        }
+        sequence_numbers.extend((0..=length).map(|i| (start + i) & TRIAD_MASK));
+    }
+    sequence_numbers
+}
+
+/// Fragments of a split packet we've received so far
+struct SplitAssembly {
+    /// reliability & indexes of the packet as a whole
+    frame: Frame,
+    parts: Vec<Option<Vec<u8>>>,
+    received: u32,
+    size: usize,
+    created: Instant,
+}
+
+/// A datagram we sent that hasn't been acknowledged yet
+struct SentDatagram {
+    /// the encoded frames, without the datagram header
+    frames: Vec<u8>,
+    sent: Instant,
+    resent: bool,
+}
+
+/// Ordering state for one channel
+#[derive(Default)]
+struct OrderChannel {
+    expected_index: u32,
+    pending: BTreeMap<u32, Vec<u8>>,
+    highest_sequence_index: u32,
+}
+
+/// Reliability state for a RakNet connection
+pub struct RakNetSession {
+    /// negotiated in OpenConnectionRequest2Packet
+    pub mtu_size: u16,
+    // receiving
+    expected_sequence_number: u32,
+    ack_queue: BTreeSet<u32>,
+    nak_queue: BTreeSet<u32>,
+    reliable_window_start: u32,
+    reliable_received: HashSet<u32>,
+    order_channels: Vec<OrderChannel>,
+    splits: HashMap<u16, SplitAssembly>,
+    split_bytes: usize,
+    pending_bytes: usize,
+    /// set once the client has made us hold on to too much, the connection should be closed
+    overflowed: bool,
+    // sending
+    sequence_number: u32,
+    reliable_index: u32,
+    order_indexes: [u32; ORDER_CHANNELS],
+    /// restarts at 0 with every ordered frame on the channel
+    sequence_indexes: [u32; ORDER_CHANNELS],
+    split_id: u16,
+    resend_queue: BTreeMap<u32, SentDatagram>,
+    /// when the client last acknowledged something, or when we started waiting on it
+    last_ack: Instant,
+    // round trip time
+    smoothed_rtt: Option<Duration>,
+    rtt_variance: Duration,
+    rto: Duration,
+}
+
+impl RakNetSession {
+    pub fn new() -> RakNetSession {
+        RakNetSession {
+            mtu_size: MIN_MTU_SIZE,
+            expected_sequence_number: 0,
+            ack_queue: BTreeSet::new(),
+            nak_queue: BTreeSet::new(),
+            reliable_window_start: 0,
+            reliable_received: HashSet::new(),
+            order_channels: (0..ORDER_CHANNELS).map(|_| OrderChannel::default()).collect(),
+            splits: HashMap::new(),
+            split_bytes: 0,
+            pending_bytes: 0,
+            overflowed: false,
+            sequence_number: 0,
+            reliable_index: 0,
+            order_indexes: [0; ORDER_CHANNELS],
+            sequence_indexes: [0; ORDER_CHANNELS],
+            split_id: 0,
+            resend_queue: BTreeMap::new(),
+            last_ack: Instant::now(),
+            smoothed_rtt: None,
+            rtt_variance: Duration::from_millis(0),
+            rto: INITIAL_RTO,
+        }
+    }
+
+    /// Records a datagram's sequence number so it's acknowledged, and any we skipped over so
+    /// they're NAKed. Returns false if it's too far ahead to be believable.
+    pub fn receive_sequence_number(&mut self, sequence_number: u32) -> bool {
+        let ahead = triad_distance(self.expected_sequence_number, sequence_number);
+        if ahead < MAX_WINDOW {
+            for i in 0..ahead {
+                self.nak_queue.insert((self.expected_sequence_number + i) & TRIAD_MASK);
+            }
+            self.expected_sequence_number = next_triad(sequence_number);
+        } else if triad_before(sequence_number, self.expected_sequence_number) {
+            // it came in late
+            self.nak_queue.remove(&sequence_number);
+        } else {
+            return false;
+        }
+
+        self.ack_queue.insert(sequence_number);
+        true
+    }
+
+    /// Returns the bodies of any frames that are ready to be handled, in order.
+    /// Duplicate & stale frames are dropped.
+    pub fn receive_frame(&mut self, frame: Frame) -> Vec<Vec<u8>> {
+        if frame.reliability.is_reliable() && !self.receive_reliable_index(frame.reliable_index) {
+            return vec![];
+        }
+
+        let frame = if frame.split.is_some() {
+            match self.receive_split(frame) {
+                Some(frame) => frame,
+                None => return vec![]
+            }
+        } else {
+            frame
+        };
+
+        let channel = frame.order_channel as usize;
+        if frame.reliability.is_ordered() && channel >= ORDER_CHANNELS {
+            return vec![];
+        }
+
+        if frame.reliability.is_sequenced() {
+            let channel = &mut self.order_channels[channel];
+            if triad_before(frame.sequence_index, channel.highest_sequence_index)
+                || triad_before(frame.order_index, channel.expected_index) {
+                // there's a newer one already
+                return vec![];
+            }
+            channel.highest_sequence_index = next_triad(frame.sequence_index);
+            vec![frame.body]
+        } else if frame.reliability.is_ordered() {
+            let channel = &mut self.order_channels[channel];
+            if frame.order_index == channel.expected_index {
+                let mut ready = vec![frame.body];
+                channel.expected_index = next_triad(channel.expected_index);
+                channel.highest_sequence_index = 0;
+                // release anything that was waiting on this
+                while let Some(body) = channel.pending.remove(&channel.expected_index) {
+                    self.pending_bytes -= body.len();
+                    ready.push(body);
+                    channel.expected_index = next_triad(channel.expected_index);
+                }
+                ready
+            } else {
+                if triad_distance(channel.expected_index, frame.order_index) < MAX_WINDOW
+                    && !channel.pending.contains_key(&frame.order_index) {
+                    if self.pending_bytes + frame.body.len() > MAX_PENDING_BYTES {
+                        // the frame was already counted as received, so the channel can't recover without it
+                        self.overflowed = true;
+                        return vec![];
+                    }
+                    self.pending_bytes += frame.body.len();
+                    channel.pending.insert(frame.order_index, frame.body);
+                }
+                vec![]
+            }
+        } else {
+            vec![frame.body]
+        }
+    }
+
+    /// Stores a fragment of a split packet. Returns the whole packet once every fragment is here.
+    fn receive_split(&mut self, frame: Frame) -> Option<Frame> {
+        let split = frame.split?;
+        if split.count == 0 || split.count > MAX_SPLIT_COUNT || split.index >= split.count {
+            println!("Dropping split packet with {} fragments", split.count);
+            return None;
+        }
+
+        if !self.splits.contains_key(&split.id) {
+            self.expire_splits();
+            if self.splits.len() >= MAX_CONCURRENT_SPLITS {
+                // make room by giving up on the oldest one
+                let oldest = self.splits.iter().min_by_key(|(_, assembly)| assembly.created).map(|(id, _)| *id).unwrap();
+                println!("Dropping split packet {}, too many are being reassembled", oldest);
+                self.remove_split(oldest);
+            }
+            self.splits.insert(split.id, SplitAssembly {
+                frame: Frame {
+                    split: None,
+                    body: vec![],
+                    ..frame.clone()
+                },
+                parts: vec![None; split.count as usize],
+                received: 0,
+                size: 0,
+                created: Instant::now(),
+            });
+        }
+
+        let complete = {
+            let assembly = self.splits.get_mut(&split.id).unwrap();
+            if assembly.parts.len() != split.count as usize {
+                // the count changed, so this isn't the same packet
+                return None;
+            }
+
+            let part = &mut assembly.parts[split.index as usize];
+            if part.is_none() {
+                if self.split_bytes + frame.body.len() > MAX_SPLIT_BYTES {
+                    println!("Dropping fragment of split packet {}, out of memory for splits", split.id);
+                    return None;
+                }
+                self.split_bytes += frame.body.len();
+                assembly.size += frame.body.len();
+                assembly.received += 1;
+                *part = Some(frame.body);
+            }
+            assembly.received == split.count
+        };
+
+        if complete {
+            let assembly = self.remove_split(split.id).unwrap();
+            let mut body = Vec::with_capacity(assembly.size);
+            for part in assembly.parts {
+                body.extend_from_slice(&part.unwrap());
+            }
+            Some(Frame {
+                body,
+                ..assembly.frame
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Throws away split packets that have been waiting on fragments for too long
+    fn expire_splits(&mut self) {
+        let expired: Vec<u16> = self.splits.iter()
+            .filter(|(_, assembly)| assembly.created.elapsed() > SPLIT_TIMEOUT)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired {
+            println!("Dropping split packet {}, it took too long to arrive", id);
+            self.remove_split(id);
+        }
+    }
+
+    /// Removes a split packet & frees up the memory it was counted for
+    fn remove_split(&mut self, id: u16) -> Option<SplitAssembly> {
+        let assembly = self.splits.remove(&id)?;
+        self.split_bytes -= assembly.size;
+        Some(assembly)
+    }
+
+    /// Returns false if we've already seen this reliable index
+    fn receive_reliable_index(&mut self, reliable_index: u32) -> bool {
+        if triad_distance(self.reliable_window_start, reliable_index) >= MAX_WINDOW
+            || self.reliable_received.contains(&reliable_index) {
+            return false;
+        }
+
+        self.reliable_received.insert(reliable_index);
+        // slide the window forward
+        while self.reliable_received.remove(&self.reliable_window_start) {
+            self.reliable_window_start = next_triad(self.reliable_window_start);
+        }
+        true
+    }
+
+    /// ACK to send for the datagrams we've received since the last call
+    pub fn take_ack(&mut self) -> Option<IntRangeList> {
+        if self.ack_queue.is_empty() {
+            return None;
+        }
+        let ranges = to_ranges(&self.ack_queue);
+        self.ack_queue.clear();
+        Some(ranges)
+    }
+
+    /// NAK to send for datagrams we think were lost
+    pub fn take_nak(&mut self) -> Option<IntRangeList> {
+        if self.nak_queue.is_empty() {
+            return None;
+        }
+        let ranges = to_ranges(&self.nak_queue);
+        self.nak_queue.clear();
+        Some(ranges)
+    }
+
+    /// The client received these datagrams, so we can stop trying to send them
+    pub fn handle_ack(&mut self, ranges: &IntRangeList) {
+        let now = Instant::now();
+        for sequence_number in from_ranges(ranges) {
+            if let Some(sent) = self.resend_queue.remove(&sequence_number) {
+                self.last_ack = now;
+                // Karn's algorithm: resent datagrams give ambiguous samples
+                if !sent.resent {
+                    self.update_rtt(now - sent.sent);
+                }
+            }
+        }
+    }
+
+    /// The client lost these datagrams. Returns new datagrams to send right away.
+    pub fn handle_nak(&mut self, ranges: &IntRangeList) -> Vec<Vec<u8>> {
+        let mut datagrams = Vec::new();
+        for sequence_number in from_ranges(ranges) {
+            if let Some(sent) = self.resend_queue.remove(&sequence_number) {
+                datagrams.push(self.wrap_datagram(sent.frames, true, true));
+            }
+        }
+        datagrams
+    }
+
+    /// Whether the client sent more out of order frames than we're willing to hold on to
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Whether the client has stopped acknowledging what we send, so the connection should be dropped
+    pub fn is_stalled(&self) -> bool {
+        !self.resend_queue.is_empty()
+            && (self.last_ack.elapsed() > ACK_TIMEOUT || self.resend_queue.len() > MAX_RESEND_QUEUE)
+    }
+
+    /// Datagrams that have gone unacknowledged for longer than the retransmission timeout
+    pub fn take_resends(&mut self) -> Vec<Vec<u8>> {
+        let now = Instant::now();
+        let rto = self.rto;
+        let expired: Vec<u32> = self.resend_queue.iter()
+            .filter(|(_, sent)| now - sent.sent >= rto)
+            .map(|(sequence_number, _)| *sequence_number)
+            .collect();
+
+        let mut datagrams = Vec::with_capacity(expired.len());
+        for sequence_number in expired {
+            if let Some(sent) = self.resend_queue.remove(&sequence_number) {
+                datagrams.push(self.wrap_datagram(sent.frames, true, true));
+            }
+        }
+
+        if datagrams.len() > 0 {
+            // back off until we hear from the client
+            self.rto = (self.rto * 2).min(MAX_RTO);
+        }
+        datagrams
+    }
+
+    /// Builds frames around `body` with the next indexes for the reliability.
+    /// Bodies that won't fit in one datagram are split into fragments.
+    /// Ordered frames are sent on channel 0.
+    pub fn create_frames(&mut self, body: Vec<u8>, reliability: Reliability) -> Vec<Frame> {
+        let max_body_size = self.mtu_size as usize - UDP_HEADER_SIZE - DATAGRAM_HEADER_SIZE - MAX_FRAME_HEADER_SIZE;
+
+        // fragments are always sent reliably since losing one loses the whole packet
+        let reliability = if body.len() > max_body_size && !reliability.is_reliable() {
+            if reliability.is_sequenced() {
+                Reliability::ReliableSequenced
+            } else {
+                Reliability::Reliable
+            }
+        } else {
+            reliability
+        };
+
+        let mut template = Frame {
+            reliability,
+            reliable_index: 0,
+            sequence_index: 0,
+            order_index: 0,
+            order_channel: 0,
+            split: None,
+            body: vec![],
+        };
+
+        if reliability.is_sequenced() {
+            // every sequenced frame goes out with the latest order index
+            template.order_index = self.order_indexes[0];
+            template.sequence_index = self.sequence_indexes[0];
+            self.sequence_indexes[0] = next_triad(self.sequence_indexes[0]);
+        } else if reliability.is_ordered() {
+            template.order_index = self.order_indexes[0];
+            self.order_indexes[0] = next_triad(self.order_indexes[0]);
+            self.sequence_indexes[0] = 0;
+        }
+
+        if body.len() <= max_body_size {
+            let mut frame = template;
+            if reliability.is_reliable() {
+                frame.reliable_index = self.next_reliable_index();
+            }
+            frame.body = body;
+            return vec![frame];
+        }
+
+        let id = self.split_id;
+        self.split_id = self.split_id.wrapping_add(1);
+        let count = ((body.len() + max_body_size - 1) / max_body_size) as u32;
+
+        body.chunks(max_body_size).enumerate().map(|(index, chunk)| {
+            Frame {
+                reliable_index: self.next_reliable_index(),
+                split: Some(SplitInfo {
+                    count,
+                    id,
+                    index: index as u32,
+                }),
+                body: chunk.to_vec(),
+                ..template.clone()
+            }
+        }).collect()
+    }
+
+    fn next_reliable_index(&mut self) -> u32 {
+        let reliable_index = self.reliable_index;
+        self.reliable_index = next_triad(self.reliable_index);
+        reliable_index
+    }
+
+    /// Puts a frame in a datagram. Reliable frames are kept until they're acknowledged.
+    pub fn create_datagram(&mut self, frame: &Frame) -> Vec<u8> {
+        self.wrap_datagram(frame.write(), frame.reliability.is_reliable(), false)
+    }
+
+    /// Adds the datagram header with the next sequence number
+    fn wrap_datagram(&mut self, frames: Vec<u8>, reliable: bool, resent: bool) -> Vec<u8> {
+        let sequence_number = self.sequence_number;
+        self.sequence_number = next_triad(self.sequence_number);
+
+        let mut datagram = Vec::with_capacity(frames.len() + 4);
+        datagram.push(FLAG_VALID | FLAG_NEEDS_B_AND_AS);
+        datagram.extend_from_slice(&write_triad(sequence_number));
+        datagram.extend_from_slice(&frames);
+
+        if reliable {
+            if self.resend_queue.is_empty() {
+                // the ACK timeout starts now, not from whenever the client last acknowledged something
+                self.last_ack = Instant::now();
+            }
+            self.resend_queue.insert(sequence_number, SentDatagram {
+                frames,
+                sent: Instant::now(),
+                resent,
+            });
+        }
+        datagram
+    }
+
+    /// RFC 6298
+    fn update_rtt(&mut self, sample: Duration) {
+        match self.smoothed_rtt {
+            Some(smoothed_rtt) => {
+                let difference = if smoothed_rtt > sample { smoothed_rtt - sample } else { sample - smoothed_rtt };
+                self.rtt_variance = (self.rtt_variance * 3 + difference) / 4;
+                self.smoothed_rtt = Some((smoothed_rtt * 7 + sample) / 8);
+            }
+            None => {
+                self.rtt_variance = sample / 2;
+                self.smoothed_rtt = Some(sample);
+            }
+        }
+        let rto = self.smoothed_rtt.unwrap() + self.rtt_variance * 4;
+        self.rto = rto.max(MIN_RTO).min(MAX_RTO);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(reliability: Reliability, reliable_index: u32, order_index: u32, body: u8) -> Frame {
+        Frame {
+            reliability,
+            reliable_index,
+            sequence_index: 0,
+            order_index,
+            order_channel: 0,
+            split: None,
+            body: vec![body],
+        }
+    }
+
+    fn sequenced(sequence_index: u32, body: u8) -> Frame {
+        Frame {
+            sequence_index,
+            ..frame(Reliability::UnreliableSequenced, 0, 0, body)
+        }
+    }
+
+    /// Makes every datagram waiting to be acknowledged look like it was sent `age` ago
+    fn age_resend_queue(session: &mut RakNetSession, age: Duration) {
+        for sent in session.resend_queue.values_mut() {
+            sent.sent -= age;
+        }
+    }
+
+    #[test]
+    fn sequence_numbers_wrap() {
+        let mut session = RakNetSession::new();
+        session.expected_sequence_number = 0xFFFFFE;
+        assert!(session.receive_sequence_number(0xFFFFFE));
+        // 0xFFFFFF & 0 are skipped
+        assert!(session.receive_sequence_number(1));
+        assert_eq!(session.expected_sequence_number, 2);
+        assert!(session.nak_queue.contains(&0xFFFFFF));
+        assert!(session.nak_queue.contains(&0));
+
+        // a late one is acknowledged instead of NAKed
+        assert!(session.receive_sequence_number(0xFFFFFF));
+        assert!(!session.nak_queue.contains(&0xFFFFFF));
+        assert!(session.ack_queue.contains(&0xFFFFFF));
+
+        // too far ahead to believe
+        assert!(!session.receive_sequence_number(2 + MAX_WINDOW));
+        assert_eq!(session.expected_sequence_number, 2);
+    }
+
+    #[test]
+    fn ack_ranges() {
+        let sequence_numbers: BTreeSet<u32> = [1, 2, 3, 7, 0xFFFFFF].iter().cloned().collect();
+        let ranges = to_ranges(&sequence_numbers);
+        assert_eq!(ranges.0.len(), 3);
+        assert_eq!(from_ranges(&ranges), vec![1, 2, 3, 7, 0xFFFFFF]);
+
+        // a range can wrap around
+        let ranges = IntRangeList(vec![(write_triad(0xFFFFFE), write_triad(1))]);
+        assert_eq!(from_ranges(&ranges), vec![0xFFFFFE, 0xFFFFFF, 0, 1]);
+
+        // but can't be huge
+        let ranges = IntRangeList(vec![(write_triad(0), write_triad(MAX_WINDOW + 1))]);
+        assert!(from_ranges(&ranges).is_empty());
+
+        let mut session = RakNetSession::new();
+        session.receive_sequence_number(0);
+        session.receive_sequence_number(2);
+        assert_eq!(from_ranges(&session.take_ack().unwrap()), vec![0, 2]);
+        assert_eq!(from_ranges(&session.take_nak().unwrap()), vec![1]);
+        assert!(session.take_ack().is_none());
+        assert!(session.take_nak().is_none());
+    }
+
+    #[test]
+    fn duplicate_reliable_frames() {
+        let mut session = RakNetSession::new();
+        assert_eq!(session.receive_frame(frame(Reliability::Reliable, 0, 0, 1)), vec![vec![1]]);
+        assert!(session.receive_frame(frame(Reliability::Reliable, 0, 0, 1)).is_empty());
+        assert!(session.receive_frame(frame(Reliability::Reliable, 1 + MAX_WINDOW, 0, 2)).is_empty());
+
+        session.reliable_window_start = 0xFFFFFF;
+        assert_eq!(session.receive_frame(frame(Reliability::Reliable, 0, 0, 3)), vec![vec![3]]);
+        assert_eq!(session.receive_frame(frame(Reliability::Reliable, 0xFFFFFF, 0, 4)), vec![vec![4]]);
+        assert_eq!(session.reliable_window_start, 1);
+        assert!(session.receive_frame(frame(Reliability::Reliable, 0xFFFFFF, 0, 4)).is_empty());
+        assert!(session.receive_frame(frame(Reliability::Reliable, 0, 0, 3)).is_empty());
+    }
+
+    #[test]
+    fn ordered_frames_out_of_order() {
+        let mut session = RakNetSession::new();
+        assert!(session.receive_frame(frame(Reliability::ReliableOrdered, 0, 1, 1)).is_empty());
+        assert!(session.receive_frame(frame(Reliability::ReliableOrdered, 1, 2, 2)).is_empty());
+        assert_eq!(session.receive_frame(frame(Reliability::ReliableOrdered, 2, 0, 0)), vec![vec![0], vec![1], vec![2]]);
+        assert_eq!(session.pending_bytes, 0);
+        // resent with a new reliable index, but it's already been handled
+        assert!(session.receive_frame(frame(Reliability::ReliableOrdered, 3, 1, 1)).is_empty());
+    }
+
+    #[test]
+    fn ordered_frames_wrap() {
+        let mut session = RakNetSession::new();
+        session.order_channels[0].expected_index = 0xFFFFFF;
+        assert!(session.receive_frame(frame(Reliability::ReliableOrdered, 0, 0, 1)).is_empty());
+        assert_eq!(session.receive_frame(frame(Reliability::ReliableOrdered, 1, 0xFFFFFF, 0)), vec![vec![0], vec![1]]);
+        assert_eq!(session.order_channels[0].expected_index, 1);
+    }
+
+    #[test]
+    fn sequenced_frames_drop_older() {
+        let mut session = RakNetSession::new();
+        assert_eq!(session.receive_frame(sequenced(2, 2)), vec![vec![2]]);
+        assert!(session.receive_frame(sequenced(1, 1)).is_empty());
+        assert!(session.receive_frame(sequenced(2, 2)).is_empty());
+        assert_eq!(session.receive_frame(sequenced(5, 5)), vec![vec![5]]);
+
+        session.order_channels[0].highest_sequence_index = 0xFFFFFF;
+        assert_eq!(session.receive_frame(sequenced(0xFFFFFF, 6)), vec![vec![6]]);
+        assert_eq!(session.receive_frame(sequenced(0, 7)), vec![vec![7]]);
+        assert!(session.receive_frame(sequenced(0xFFFFFE, 8)).is_empty());
+    }
+
+    #[test]
+    fn outgoing_indexes_wrap() {
+        let mut session = RakNetSession::new();
+        session.sequence_number = 0xFFFFFF;
+        session.reliable_index = 0xFFFFFF;
+        session.order_indexes[0] = 0xFFFFFF;
+
+        let frames = session.create_frames(vec![1], Reliability::ReliableOrdered);
+        assert_eq!(frames[0].reliable_index, 0xFFFFFF);
+        assert_eq!(frames[0].order_index, 0xFFFFFF);
+        let datagram = session.create_datagram(&frames[0]);
+        assert_eq!(read_triad(&datagram, 1).unwrap(), 0xFFFFFF);
+
+        let frames = session.create_frames(vec![2], Reliability::ReliableOrdered);
+        assert_eq!(frames[0].reliable_index, 0);
+        assert_eq!(frames[0].order_index, 0);
+        let datagram = session.create_datagram(&frames[0]);
+        assert_eq!(read_triad(&datagram, 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn resends_after_rto() {
+        let mut session = RakNetSession::new();
+        let frames = session.create_frames(vec![1, 2, 3], Reliability::Reliable);
+        let datagram = session.create_datagram(&frames[0]);
+        assert!(session.take_resends().is_empty());
+
+        age_resend_queue(&mut session, INITIAL_RTO);
+        let resends = session.take_resends();
+        assert_eq!(resends.len(), 1);
+        // same frames under a new sequence number
+        assert_eq!(read_triad(&resends[0], 1).unwrap(), 1);
+        assert_eq!(&resends[0][4..], &datagram[4..]);
+        assert_eq!(session.rto, INITIAL_RTO * 2);
+
+        // resent datagrams don't give an RTT sample
+        session.handle_ack(&to_ranges(&[1].iter().cloned().collect()));
+        assert!(session.resend_queue.is_empty());
+        assert!(session.smoothed_rtt.is_none());
+
+        let frames = session.create_frames(vec![4], Reliability::Reliable);
+        session.create_datagram(&frames[0]);
+        session.handle_ack(&to_ranges(&[2].iter().cloned().collect()));
+        assert!(session.smoothed_rtt.is_some());
+        assert!(session.rto >= MIN_RTO && session.rto <= MAX_RTO);
+    }
+
+    #[test]
+    fn nak_resends_right_away() {
+        let mut session = RakNetSession::new();
+        let frames = session.create_frames(vec![1], Reliability::Reliable);
+        session.create_datagram(&frames[0]);
+        // unreliable datagrams aren't kept
+        let frames = session.create_frames(vec![2], Reliability::Unreliable);
+        session.create_datagram(&frames[0]);
+        assert_eq!(session.resend_queue.len(), 1);
+
+        let resends = session.handle_nak(&to_ranges(&[0, 1].iter().cloned().collect()));
+        assert_eq!(resends.len(), 1);
+        assert!(session.resend_queue.contains_key(&2));
+    }
+
+    #[test]
+    fn stalls_without_acks() {
+        let mut session = RakNetSession::new();
+        session.last_ack -= ACK_TIMEOUT * 2;
+        // nothing to acknowledge
+        assert!(!session.is_stalled());
+
+        // the timeout starts when we send something
+        let frames = session.create_frames(vec![1], Reliability::Reliable);
+        session.create_datagram(&frames[0]);
+        assert!(!session.is_stalled());
+
+        session.last_ack -= ACK_TIMEOUT * 2;
+        assert!(session.is_stalled());
+        session.handle_ack(&to_ranges(&[0].iter().cloned().collect()));
+        assert!(!session.is_stalled());
+
+        for _ in 0..=MAX_RESEND_QUEUE {
+            let frames = session.create_frames(vec![1], Reliability::Reliable);
+            session.create_datagram(&frames[0]);
+        }
+        assert!(session.is_stalled());
+    }
+
+    fn fragment(id: u16, count: u32, index: u32, reliable_index: u32, body: Vec<u8>) -> Frame {
+        Frame {
+            split: Some(SplitInfo {
+                count,
+                id,
+                index,
+            }),
+            body,
+            ..frame(Reliability::Reliable, reliable_index, 0, 0)
+        }
+    }
+
+    #[test]
+    fn split_packets_round_trip() {
+        let mut sender = RakNetSession::new();
+        let mut receiver = RakNetSession::new();
+        let body: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+        let mut frames = sender.create_frames(body.clone(), Reliability::ReliableOrdered);
+        assert!(frames.len() > 1);
+        assert!(frames.iter().all(|frame| frame.split.is_some() && frame.order_index == 0));
+
+        // the fragments can arrive in any order
+        frames.reverse();
+        let last = frames.pop().unwrap();
+        for frame in frames {
+            let (frame, _) = Frame::read(&frame.write(), 0).unwrap();
+            assert!(receiver.receive_frame(frame).is_empty());
+        }
+        assert_eq!(receiver.receive_frame(last), vec![body]);
+        assert!(receiver.splits.is_empty());
+        assert_eq!(receiver.split_bytes, 0);
+    }
+
+    #[test]
+    fn bad_fragments_are_dropped() {
+        let mut session = RakNetSession::new();
+        assert!(session.receive_split(fragment(0, 0, 0, 0, vec![1])).is_none());
+        assert!(session.receive_split(fragment(0, 2, 2, 0, vec![1])).is_none());
+        assert!(session.receive_split(fragment(0, MAX_SPLIT_COUNT + 1, 0, 0, vec![1])).is_none());
+        assert!(session.splits.is_empty());
+
+        assert!(session.receive_split(fragment(0, 2, 0, 0, vec![1])).is_none());
+        // a different count means it isn't the same packet
+        assert!(session.receive_split(fragment(0, 3, 1, 1, vec![2])).is_none());
+        // duplicates aren't counted twice
+        assert!(session.receive_split(fragment(0, 2, 0, 2, vec![1])).is_none());
+        assert_eq!(session.split_bytes, 1);
+        assert_eq!(session.receive_split(fragment(0, 2, 1, 3, vec![2])).unwrap().body, vec![1, 2]);
+    }
+
+    #[test]
+    fn oldest_split_is_evicted() {
+        let mut session = RakNetSession::new();
+        for id in 0..MAX_CONCURRENT_SPLITS as u16 + 2 {
+            assert!(session.receive_split(fragment(id, 2, 0, id as u32, vec![0; 100])).is_none());
+        }
+        assert_eq!(session.splits.len(), MAX_CONCURRENT_SPLITS);
+        assert!(!session.splits.contains_key(&0));
+        assert!(!session.splits.contains_key(&1));
+        assert_eq!(session.split_bytes, 100 * MAX_CONCURRENT_SPLITS);
+    }
+
+    #[test]
+    fn stale_splits_expire() {
+        let mut session = RakNetSession::new();
+        session.receive_split(fragment(0, 2, 0, 0, vec![0; 100]));
+        session.receive_split(fragment(1, 2, 0, 1, vec![0; 100]));
+        session.splits.get_mut(&0).unwrap().created -= SPLIT_TIMEOUT * 2;
+
+        session.receive_split(fragment(2, 2, 0, 2, vec![0; 100]));
+        assert!(!session.splits.contains_key(&0));
+        assert!(session.splits.contains_key(&1));
+        assert_eq!(session.split_bytes, 200);
+    }
+
+    #[test]
+    fn sequenced_frames_are_numbered() {
+        let mut session = RakNetSession::new();
+        let first = session.create_frames(vec![1], Reliability::UnreliableSequenced);
+        let second = session.create_frames(vec![2], Reliability::UnreliableSequenced);
+        assert_eq!((first[0].sequence_index, second[0].sequence_index), (0, 1));
+        assert_eq!((first[0].order_index, second[0].order_index), (0, 0));
+
+        // an ordered frame starts the sequence over
+        session.create_frames(vec![3], Reliability::ReliableOrdered);
+        let third = session.create_frames(vec![4], Reliability::UnreliableSequenced);
+        assert_eq!((third[0].sequence_index, third[0].order_index), (0, 1));
+
+        let (frame, _) = Frame::read(&third[0].write(), 0).unwrap();
+        assert_eq!((frame.sequence_index, frame.order_index), (0, 1));
+    }
+
+    #[test]
+    fn held_back_frames_are_capped() {
+        let mut session = RakNetSession::new();
+        let body_size = 1024 * 1024;
+        // order index 0 never arrives
+        for i in 1..5 {
+            let frame = Frame {
+                body: vec![0; body_size],
+                ..frame(Reliability::ReliableOrdered, i, i, 0)
+            };
+            assert!(session.receive_frame(frame).is_empty());
+        }
+        assert_eq!(session.pending_bytes, 4 * body_size);
+        assert!(!session.is_overflowed());
+
+        assert!(session.receive_frame(frame(Reliability::ReliableOrdered, 5, 5, 0)).is_empty());
+        assert!(session.is_overflowed());
+    }
+}
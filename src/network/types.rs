@@ -1,7 +1,10 @@
 use std::mem;
+use std::marker::PhantomData;
 use std::net::{IpAddr, SocketAddr, Ipv6Addr, Ipv4Addr, SocketAddrV6};
 
+use md5::{Digest, Md5};
 use network::packet::*;
+use network::nbt::*;
 use network::protocol::bedrock;
 
 #[derive(Copy, Clone, Default, Debug)]
@@ -14,13 +17,18 @@ pub struct VarIntLengthPrefixedString(pub String);
 #[derive(Clone, Default, Debug)]
 pub struct VarIntLengthPrefixedByteArray(pub Vec<u8>);
 
+/// Used by the Java protocol before 1.8
+#[derive(Clone, Default, Debug)]
+pub struct ShortLengthPrefixedByteArray(pub Vec<u8>);
+
 /// Used by the Bedrock protocol
 #[derive(Clone, Default, Debug)]
 pub struct ShortLengthPrefixedString(pub String);
 
-// it's just 46 zeros. no more, no less
+/// Zeros that fill the rest of a packet, like the MTU discovery padding in
+/// `OpenConnectionRequest1Packet`. Holds how many there are.
 #[derive(Clone, Default, Debug)]
-pub struct FortySixZeros(pub Vec<u8>);
+pub struct Padding(pub usize);
 
 // Used for ACK & NACK in RakNet
 #[derive(Clone, Default, Debug)]
@@ -36,28 +44,169 @@ pub struct RakNetMagic(pub [u8; 16]);
 /// Constant for building a packet
 pub const RAKNET_MAGIC: RakNetMagic = RakNetMagic(bedrock::MAGIC);
 
+/// Longer strings are rejected before they're copied
+pub const MAX_STRING_LENGTH: usize = 1 << 16;
+
+/// Java Edition's 64-bit VarInt
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct VarLong(pub i64);
+
+/// Bedrock Edition's unsigned VarInt
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct VarUInt(pub u32);
+
+/// Bedrock Edition's unsigned 64-bit VarInt
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct VarULong(pub u64);
+
+/// Bedrock Edition's signed VarInt, which is ZigZag encoded so small negative numbers stay small
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct ZigZagVarInt(pub i32);
+
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct ZigZagVarLong(pub i64);
+
+/// Bedrock Edition writes most numbers little-endian
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct LittleEndian<T>(pub T);
+
+/// Written as 16 big-endian bytes
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Hash)]
+pub struct UUID(pub u128);
+
+/// A UUID written as a string with hyphens, like in Java Edition's `LoginSuccessPacket`
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct HyphenatedUUID(pub UUID);
+
+/// Block position packed into a long, with x in the top 26 bits, then y in 12 bits, then z.
+/// Used from 1.8 until 1.14.
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// 1.14 moved y to the bottom 12 bits, after z
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct Position1_14(pub Position);
+
+/// Rotation in 1/256ths of a full turn
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct Angle(pub u8);
+
+/// Entity coordinates before 1.9 are sent as ints with 5 fractional bits
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct FixedPointInt(pub f64);
+
+/// Relative movement before 1.9 is sent as a byte with 5 fractional bits
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct FixedPointByte(pub f64);
+
+/// An array prefixed by its length as `L`, like `LengthPrefixedVec<VarInt, Slot>`
+#[derive(Clone, Debug)]
+pub struct LengthPrefixedVec<L, T>(pub Vec<T>, pub PhantomData<L>);
+
+/// An item in an inventory slot or an entity's metadata
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct ItemStack {
+    pub id: i16,
+    pub count: u8,
+    pub damage: i16,
+    /// `Tag::End` when the item doesn't have any
+    pub nbt: Nbt,
+}
+
+/// Java Edition 1.7 inventory slot, with the item's NBT gzip compressed
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Slot(pub Option<ItemStack>);
+
+/// 1.8 stopped compressing the item's NBT
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Slot1_8(pub Option<ItemStack>);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetadataValue {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Float(f32),
+    String(String),
+    Slot(Option<ItemStack>),
+    Position(i32, i32, i32),
+    /// Added in 1.8
+    Rotation(f32, f32, f32),
+}
+
+/// Java Edition 1.7 entity metadata. Holds the index & value of each entry.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct EntityMetadata(pub Vec<(u8, MetadataValue)>);
+
+/// 1.8 entity metadata, which has 1.8 slots & rotations
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct EntityMetadata1_8(pub Vec<(u8, MetadataValue)>);
+
+/// The rest of a packet's bytes, like the data in a 1.8 plugin message
+#[derive(Clone, Default, Debug)]
+pub struct RemainingBytes(pub Vec<u8>);
+
+/// A field at the end of a packet that's only sent sometimes, so it's there if any bytes are left
+#[derive(Clone, Default, Debug)]
+pub struct TrailingOption<T>(pub Option<T>);
+
+/// Types an array's length can be written as
+pub trait LengthPrefix: ReadField + WriteField {
+    fn from_length(length: usize) -> Self;
+    /// `None` if the length is negative
+    fn length(&self) -> Option<usize>;
+}
+
 //u8
 
 impl ReadField for u8 {
-    fn read(bytes: &Vec<u8>, index: usize) -> Option<(u8, usize)> {
-        if bytes.len() <= index {
-            None
-        } else {
-            Some((*bytes.get(index).unwrap(), 1))
+    fn read(bytes: &[u8], index: usize) -> Result<(u8, usize), DecodeError> {
+        match bytes.get(index) {
+            Some(b) => Ok((*b, 1)),
+            None => Err(DecodeError::UnexpectedEof)
         }
     }
 }
 
 impl WriteField for u8 {
-    fn write(&self) -> Vec<u8> {
-        vec![self.clone()]
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.push(*self);
+    }
+
+    fn size_hint(&self) -> usize {
+        1
+    }
+}
+
+// bool
+
+impl ReadField for bool {
+    fn read(bytes: &[u8], index: usize) -> Result<(bool, usize), DecodeError> {
+        match bytes.get(index) {
+            Some(b) => Ok((*b != 0, 1)),
+            None => Err(DecodeError::UnexpectedEof)
+        }
+    }
+}
+
+impl WriteField for bool {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+
+    fn size_hint(&self) -> usize {
+        1
     }
 }
 
 // varint
 
 impl ReadField for VarInt {
-    fn read(buf: &Vec<u8>, mut index: usize) -> Option<(VarInt, usize)> {
+    fn read(buf: &[u8], mut index: usize) -> Result<(VarInt, usize), DecodeError> {
         let mut result = 0;
         let mut bytes_used: usize = 0;
 
@@ -67,10 +216,7 @@ impl ReadField for VarInt {
         for i in 0..5 {
             let read = match buf.get(index) {
                 Some(r) => r,
-                None => {
-                    println!("read_varint couldn't find byte {}/{}", index, buf.len());
-                    return None;
-                }
+                None => return Err(DecodeError::UnexpectedEof)
             };
             bytes_used += 1;
             index += 1;
@@ -79,22 +225,20 @@ impl ReadField for VarInt {
 
             /* The last (5th) byte is only allowed to have the 4 LSB set */
             if i == 4 && (read & 0xf0 != 0) {
-                println!("read_varint is too long, last byte: {}", read);
-                return None;
+                return Err(DecodeError::VarIntTooLong);
             }
 
             if (read & msb) == 0 {
-                return Some((VarInt(result), bytes_used));
+                return Ok((VarInt(result), bytes_used));
             }
         }
 
-        println!("read_varint reached end of loop, which should not be possible");
-        None
+        unreachable!("the 5th byte of a VarInt can't have the msb set")
     }
 }
 
 impl WriteField for VarInt {
-    fn write(&self) -> Vec<u8> {
+    fn write_to(&self, buf: &mut Vec<u8>) {
         /* Define some helpful values for dealing with varints */
         let msb: u8 = 0b10000000;
         let mask: u32 = !(msb as u32);
@@ -102,7 +246,6 @@ impl WriteField for VarInt {
         /* Make the value unsigned to avoid weird signed behavior when bit-shifting */
         let mut val = self.0 as u32;
 
-        let mut vec: Vec<u8> = Vec::new();
         for _ in 0..5 {
             /* Get the last 7 bits and cast to an u8.
              * Also right-shift the value to advance further. */
@@ -112,463 +255,283 @@ impl WriteField for VarInt {
             /* If there's still something to write, set the most significant bit and continue */
             if val != 0 {
                 tmp |= msb;
-                vec.push(tmp);
+                buf.push(tmp);
             } else {
-                vec.push(tmp);
+                buf.push(tmp);
                 break;
             }
         }
+    }
 
-        vec
+    fn size_hint(&self) -> usize {
+        var_int_size(self.0 as u32 as u64)
     }
 }
 
 // VarIntLengthPrefixedByteArray
 
 impl ReadField for VarIntLengthPrefixedByteArray {
-    fn read(buf: &Vec<u8>, mut index: usize) -> Option<(VarIntLengthPrefixedByteArray, usize)> {
-        let varint_size;
-        let length = match <VarInt as ReadField>::read(buf, index) {
-            Some((l, v)) => {
-                varint_size = v;
-                index += v;
-                l.0 as usize
-            }
-            None => return None
-        };
+    fn read(buf: &[u8], index: usize) -> Result<(VarIntLengthPrefixedByteArray, usize), DecodeError> {
+        let (length, varint_size) = <VarInt as ReadField>::read(buf, index)?;
+        let length = length.length().ok_or(DecodeError::LengthExceedsLimit)?;
+        let bytes = read_slice(buf, index + varint_size, length)?.to_vec();
+        Ok((VarIntLengthPrefixedByteArray(bytes), length + varint_size))
+    }
+}
 
-        if buf.len() < index + length {
-            return None;
-        }
+impl WriteField for VarIntLengthPrefixedByteArray {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        VarInt(self.0.len() as i32).write_to(buf);
+        buf.extend_from_slice(&self.0);
+    }
 
-        let bytes = buf[index..length].to_vec();
-        Some((VarIntLengthPrefixedByteArray(bytes), length + varint_size))
+    fn size_hint(&self) -> usize {
+        var_int_size(self.0.len() as u64) + self.0.len()
     }
 }
 
-impl WriteField for VarIntLengthPrefixedByteArray {
-    fn write(&self) -> Vec<u8> {
-        let mut buf = VarInt(self.0.len() as i32).write();
-        buf.append(&mut self.0.clone());
-        buf
+// ShortLengthPrefixedByteArray
+
+impl ReadField for ShortLengthPrefixedByteArray {
+    fn read(buf: &[u8], index: usize) -> Result<(ShortLengthPrefixedByteArray, usize), DecodeError> {
+        let (length, _) = <u16 as ReadField>::read(buf, index)?;
+        let bytes = read_slice(buf, index + 2, length as usize)?.to_vec();
+        // plus 2 at the end for the size of the short
+        Ok((ShortLengthPrefixedByteArray(bytes), length as usize + 2))
+    }
+}
+
+impl WriteField for ShortLengthPrefixedByteArray {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        (self.0.len() as u16).write_to(buf);
+        buf.extend_from_slice(&self.0);
+    }
+
+    fn size_hint(&self) -> usize {
+        2 + self.0.len()
     }
 }
 
 // JE string
 
 impl ReadField for VarIntLengthPrefixedString {
-    fn read(buf: &Vec<u8>, mut index: usize) -> Option<(VarIntLengthPrefixedString, usize)> {
-        let varint_size;
-        let length = match <VarInt as ReadField>::read(buf, index) {
-            Some((l, v)) => {
-                varint_size = v;
-                index += v;
-                l.0 as usize
-            }
-            None => return None
+    fn read(buf: &[u8], index: usize) -> Result<(VarIntLengthPrefixedString, usize), DecodeError> {
+        let (length, varint_size) = <VarInt as ReadField>::read(buf, index)?;
+        let length = match length.length() {
+            Some(length) if length <= MAX_STRING_LENGTH => length,
+            _ => return Err(DecodeError::LengthExceedsLimit)
         };
 
-        if length > (1 << 16) {
-            println!("read_string refusing to read string due to its length");
-            return None;
-        }
-
-        if buf.len() < index + length {
-            println!("read_string expected a string with length {}", length);
-            return None;
-        }
-
-
-        Some((
-            VarIntLengthPrefixedString(
-                String::from_utf8((&buf[index..(index + length)]).to_vec()).unwrap()
-            ),
-            length + varint_size
-        ))
+        let bytes = read_slice(buf, index + varint_size, length)?;
+        let string = String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)?;
+        Ok((VarIntLengthPrefixedString(string), length + varint_size))
     }
 }
 
 impl WriteField for VarIntLengthPrefixedString {
-    fn write(&self) -> Vec<u8> {
-        let s = &self.0;
-        let length = s.len();
-        let mut buf = VarInt(length as i32).write();
-        // TODO: Remove clone
-        buf.append(&mut s.clone().into_bytes());
-        buf
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        VarInt(self.0.len() as i32).write_to(buf);
+        buf.extend_from_slice(self.0.as_bytes());
+    }
+
+    fn size_hint(&self) -> usize {
+        var_int_size(self.0.len() as u64) + self.0.len()
     }
 }
 
 // BE string
 
 impl ReadField for ShortLengthPrefixedString {
-    fn read(buf: &Vec<u8>, mut index: usize) -> Option<(ShortLengthPrefixedString, usize)> {
-        let length = match <u16 as ReadField>::read(buf, index) {
-            Some((l, v)) => {
-                index += v;
-                l as usize
-            }
-            None => return None
-        };
-
-        if buf.len() < index + length {
-            // string isn't there!
-            return None;
-        }
-
+    fn read(buf: &[u8], index: usize) -> Result<(ShortLengthPrefixedString, usize), DecodeError> {
+        let (length, _) = <u16 as ReadField>::read(buf, index)?;
+        let bytes = read_slice(buf, index + 2, length as usize)?;
+        let string = String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)?;
         // plus 2 at the end for the size of the short
-        Some((
-            ShortLengthPrefixedString(
-                String::from_utf8((&buf[index..(index + length)]).to_vec()).unwrap()
-            ),
-            length + 2
-        ))
+        Ok((ShortLengthPrefixedString(string), length as usize + 2))
     }
 }
 
 impl WriteField for ShortLengthPrefixedString {
-    fn write(&self) -> Vec<u8> {
-        let s = &self.0;
-        // TODO: Remove clone
-        let length = s.clone().len() as u16;
-
-        let mut buf = vec![
-            ((length & 0xFF00) >> 8) as u8,
-            (length & 0xFF) as u8
-        ];
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        (self.0.len() as u16).write_to(buf);
+        buf.extend_from_slice(self.0.as_bytes());
+    }
 
-        buf.append(&mut s.clone().into_bytes());
-        buf
+    fn size_hint(&self) -> usize {
+        2 + self.0.len()
     }
 }
 
 // u16
 
 impl ReadField for u16 {
-    fn read(buf: &Vec<u8>, index: usize) -> Option<(u16, usize)> {
-        if buf.len() < index + 2 {
-            return None;
-        }
-
-        let mut us_bytes: [u8; 2] = Default::default();
-        us_bytes.copy_from_slice(&buf[index..(index + 2)]);
-        let s: u16;
-        unsafe {
-            // swap bytes
-            s = mem::transmute([us_bytes[1], us_bytes[0]]);
-        }
-
-        Some((s, 2))
+    fn read(buf: &[u8], index: usize) -> Result<(u16, usize), DecodeError> {
+        let mut bytes = [0u8; 2];
+        bytes.copy_from_slice(read_slice(buf, index, 2)?);
+        Ok((u16::from_be_bytes(bytes), 2))
     }
 }
 
 impl WriteField for u16 {
-    fn write(&self) -> Vec<u8> {
-        vec![
-            (self >> 8) as u8,
-            (self & 0xFF) as u8,
-        ]
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn size_hint(&self) -> usize {
+        2
     }
 }
 
 // u32
 
 impl ReadField for u32 {
-    fn read(buf: &Vec<u8>, index: usize) -> Option<(u32, usize)> {
-        if buf.len() < index + 4 {
-            return None;
-        }
-
-        let mut bytes: [u8; 4] = Default::default();
-        bytes.copy_from_slice(&buf[index..(index + 4)]);
-        let s: u32;
-        unsafe {
-            s = mem::transmute([
-                bytes[3], bytes[2], bytes[1], bytes[0]
-            ]);
-        }
-
-        Some((s, 4))
+    fn read(buf: &[u8], index: usize) -> Result<(u32, usize), DecodeError> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(read_slice(buf, index, 4)?);
+        Ok((u32::from_be_bytes(bytes), 4))
     }
 }
 
 impl WriteField for u32 {
-    fn write(&self) -> Vec<u8> {
-        vec![
-            ((self & 0xFF000000) >> 24) as u8,
-            ((self & 0xFF0000) >> 16) as u8,
-            ((self & 0xFF00) >> 8) as u8,
-            (self & 0xFF) as u8
-        ]
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn size_hint(&self) -> usize {
+        4
     }
 }
 
 // u64
 
 impl ReadField for u64 {
-    fn read(buf: &Vec<u8>, index: usize) -> Option<(u64, usize)> {
-        if buf.len() < index + 8 {
-            return None;
-        }
-
-        let mut b: [u8; 8] = Default::default();
-        b.copy_from_slice(&buf[index..(index + 8)]);
-        let s: u64;
-        unsafe {
-            // swap bytes
-            s = mem::transmute([
-                b[7], b[6], b[5], b[4],
-                b[3], b[2], b[1], b[0]
-            ]);
-        }
-
-        Some((s, 8))
+    fn read(buf: &[u8], index: usize) -> Result<(u64, usize), DecodeError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(read_slice(buf, index, 8)?);
+        Ok((u64::from_be_bytes(bytes), 8))
     }
 }
 
 impl WriteField for u64 {
-    fn write(&self) -> Vec<u8> {
-        (unsafe { mem::transmute::<u64, [u8; 8]>(self.to_be()) })[..].to_vec()
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn size_hint(&self) -> usize {
+        8
     }
 }
 
 // i32
 
 impl ReadField for i32 {
-    fn read(buf: &Vec<u8>, index: usize) -> Option<(i32, usize)> {
-        if buf.len() < index + 8 {
-            return None;
-        }
-
-        let mut b: [u8; 4] = Default::default();
-        b.copy_from_slice(&buf[index..(index + 4)]);
-        let s: i32;
-        unsafe {
-            // swap bytes
-            s = mem::transmute([
-                b[3], b[2], b[1], b[0]
-            ]);
-        }
-
-        Some((s, 4))
+    fn read(buf: &[u8], index: usize) -> Result<(i32, usize), DecodeError> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(read_slice(buf, index, 4)?);
+        Ok((i32::from_be_bytes(bytes), 4))
     }
 }
 
 impl WriteField for i32 {
-    fn write(&self) -> Vec<u8> {
-        (unsafe { mem::transmute::<i32, [u8; 4]>(self.to_be()) })[..].to_vec()
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn size_hint(&self) -> usize {
+        4
     }
 }
 
 // RakNetMagic
 
 impl ReadField for RakNetMagic {
-    fn read(buf: &Vec<u8>, index: usize) -> Option<(RakNetMagic, usize)> {
-        // TODO: Validate
-        if buf.len() < index + 16 {
-            return None;
+    fn read(buf: &[u8], index: usize) -> Result<(RakNetMagic, usize), DecodeError> {
+        if read_slice(buf, index, 16)? != bedrock::MAGIC {
+            return Err(DecodeError::UnknownVariant);
         }
-
-        Some((RakNetMagic(bedrock::MAGIC), 16))
+        Ok((RakNetMagic(bedrock::MAGIC), 16))
     }
 }
 
 impl WriteField for RakNetMagic {
-    fn write(&self) -> Vec<u8> {
-        bedrock::MAGIC.to_vec()
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&bedrock::MAGIC);
     }
-}
 
-// it's just 46 zeros
+    fn size_hint(&self) -> usize {
+        16
+    }
+}
 
-impl ReadField for FortySixZeros {
-    fn read(buf: &Vec<u8>, index: usize) -> Option<(FortySixZeros, usize)> {
-        println!("READING 46 0s");
-        if buf.len() < index + 45 {
-            println!(" NOT ENOUGH ZEROS?!??!?!?");
-            return None;
-        }
+// Padding
 
-        Some((FortySixZeros(vec![0; 46]), 46))
+impl ReadField for Padding {
+    fn read(buf: &[u8], index: usize) -> Result<(Padding, usize), DecodeError> {
+        let length = buf.len().saturating_sub(index);
+        Ok((Padding(length), length))
     }
 }
 
-impl WriteField for FortySixZeros {
-    fn write(&self) -> Vec<u8> {
-        vec![0; 46]
+impl WriteField for Padding {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.resize(buf.len() + self.0, 0);
+    }
+
+    fn size_hint(&self) -> usize {
+        self.0
     }
 }
 
 // SocketAddr
 
 impl ReadField for Address {
-    fn read(buf: &Vec<u8>, mut index: usize) -> Option<(Address, usize)> {
-        if buf.len() < index + 6 {
-            return None;
-        }
-
-        let id = buf[index];
-        index += 1;
-        match id {
+    fn read(buf: &[u8], index: usize) -> Result<(Address, usize), DecodeError> {
+        let (version, _) = <u8 as ReadField>::read(buf, index)?;
+        match version {
             4 => {
-                if buf.len() < index + 4 {
-                    return None;
-                }
-
                 // TODO: Why are these inverted?
-                let a = 255u8 - buf[index];
-                let b = 255u8 - buf[index + 1];
-                let c = 255u8 - buf[index + 2];
-                let d = 255u8 - buf[index + 3];
-                index += 4;
-
-                let port = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, _v)) => {
-                        // index += v;
-                        l
-                    }
-                    None => return None
-                };
-
-                Some((Address(
-                    SocketAddr::new(
-                        IpAddr::V4(
-                            Ipv4Addr::new(a, b, c, d)
-                        ),
-                        port,
-                    )), 7
-                ))
+                let ip = read_slice(buf, index + 1, 4)?;
+                let ip = Ipv4Addr::new(255 - ip[0], 255 - ip[1], 255 - ip[2], 255 - ip[3]);
+                let (port, _) = <u16 as ReadField>::read(buf, index + 5)?;
+                Ok((Address(SocketAddr::new(IpAddr::V4(ip), port)), 7))
             }
             6 => {
-                // this is obnoxious code
-
-                // this should always be 23?
-                let _family = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 3
-
-                let port = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 5
-
-                let flow = match <u32 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 9
-
-                let a = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 11
-                let b = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 13
-                let c = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 15
-                let d = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 17
-                let e = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 18
-                let f = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 19
-                let g = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 21
-                let h = match <u16 as ReadField>::read(buf, index) {
-                    Some((l, v)) => {
-                        index += v;
-                        l
-                    }
-                    None => return None
-                }; // 23
-
-                let scope_id = match <u32 as ReadField>::read(buf, index) {
-                    Some((l, _v)) => {
-                        // index += v;
-                        l
-                    }
-                    None => return None
-                }; // 27
-
-                Some((Address(
-                    SocketAddr::V6(
-                        SocketAddrV6::new(
-                            Ipv6Addr::new(a, b, c, d, e, f, g, h),
-                            port,
-                            flow,
-                            scope_id,
-                        )
-                    )), 27
-                ))
+                // a sockaddr_in6: family (always 23), port, flow info, address & scope id
+                let (port, _) = <u16 as ReadField>::read(buf, index + 3)?;
+                let (flow, _) = <u32 as ReadField>::read(buf, index + 5)?;
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(read_slice(buf, index + 9, 16)?);
+                let (scope_id, _) = <u32 as ReadField>::read(buf, index + 25)?;
+                Ok((Address(SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::from(octets), port, flow, scope_id))), 29))
             }
-            _ => None
+            _ => Err(DecodeError::UnknownVariant)
         }
     }
 }
 
 impl WriteField for Address {
-    fn write(&self) -> Vec<u8> {
+    fn write_to(&self, buf: &mut Vec<u8>) {
         let addr = self.0;
         match addr.ip() {
             IpAddr::V4(socketaddrv4) => {
-                let mut buf = vec![4u8];
-                buf.append(&mut (socketaddrv4.octets().to_vec()));
-                buf.append(&mut addr.port().write());
-                buf
+                buf.push(4u8);
+                buf.extend_from_slice(&socketaddrv4.octets());
+                addr.port().write_to(buf);
             }
             IpAddr::V6(socketaddrv6) => {
-                let mut buf = vec![6u8];
-                buf.append(&mut 23u16.write());
-                buf.append(&mut addr.port().write());
-                buf.append(&mut 0u32.write());
-                buf.append(&mut (socketaddrv6.octets().to_vec()));
-                buf.append(&mut 0u32.write());
-                buf
+                buf.push(6u8);
+                23u16.write_to(buf);
+                addr.port().write_to(buf);
+                0u32.write_to(buf);
+                buf.extend_from_slice(&socketaddrv6.octets());
+                0u32.write_to(buf);
             }
         }
     }
+
+    fn size_hint(&self) -> usize {
+        if self.0.is_ipv4() { 7 } else { 29 }
+    }
 }
 
 impl Default for Address {
@@ -578,78 +541,1044 @@ impl Default for Address {
 }
 
 impl ReadField for Vec<Address> {
-    fn read(buf: &Vec<u8>, mut index: usize) -> Option<(Self, usize)> {
+    fn read(buf: &[u8], mut index: usize) -> Result<(Self, usize), DecodeError> {
         let mut addresses = Vec::with_capacity(20);
         let mut size = 0;
         for _i in 0..20 {
-            let address = match <Address as ReadField>::read(buf, index) {
-                Some((l, v)) => {
-                    index += v;
-                    size += v;
-                    l
-                }
-                None => return None
-            };
+            let (address, length) = <Address as ReadField>::read(buf, index)?;
+            index += length;
+            size += length;
             addresses.push(address);
         }
-        Some((addresses, size))
+        Ok((addresses, size))
     }
 }
 
 impl WriteField for Vec<Address> {
-    fn write(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(20);
+    fn write_to(&self, buf: &mut Vec<u8>) {
         for a in self.iter() {
-            buf.append(&mut a.write());
+            a.write_to(buf);
         }
-        buf
+    }
+
+    fn size_hint(&self) -> usize {
+        self.iter().map(Address::size_hint).sum()
     }
 }
 
 impl ReadField for IntRangeList {
-    fn read(buf: &Vec<u8>, mut index: usize) -> Option<(Self, usize)> {
+    fn read(buf: &[u8], mut index: usize) -> Result<(Self, usize), DecodeError> {
         let start_index = index;
-        let len = match <u16 as ReadField>::read(buf, index) {
-            Some((l, v)) => {
-                index += v;
-                l
-            }
-            None => return None
-        };
+        let (len, v) = <u16 as ReadField>::read(buf, index)?;
+        index += v;
 
-        let mut ranges = Vec::with_capacity(len as usize);
+        // each range takes at least 4 bytes, so a bogus count can't make us allocate much
+        let mut ranges = Vec::with_capacity((len as usize).min(buf.len().saturating_sub(index) / 4));
         for _i in 0..len {
-            let singleton = buf[index] == 1;
+            let (singleton, _) = <bool as ReadField>::read(buf, index)?;
             index += 1;
-            let start = [buf[index], buf[index + 1], buf[index + 2]];
+            let mut start = [0u8; 3];
+            start.copy_from_slice(read_slice(buf, index, 3)?);
             index += 3;
             if singleton {
                 ranges.push((start, start));
             } else {
-                let end = [buf[index], buf[index + 1], buf[index + 2]];
+                let mut end = [0u8; 3];
+                end.copy_from_slice(read_slice(buf, index, 3)?);
                 index += 3;
                 ranges.push((start, end));
             }
         }
 
-        Some((IntRangeList(ranges), index - start_index))
+        Ok((IntRangeList(ranges), index - start_index))
     }
 }
 
 impl WriteField for IntRangeList {
-    fn write(&self) -> Vec<u8> {
-        let mut buf = (self.0.len() as u16).write();
-        for (mut start, mut end) in self.0.iter() {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        (self.0.len() as u16).write_to(buf);
+        for (start, end) in self.0.iter() {
             if start == end {
                 buf.push(1u8);
-                buf.append(&mut start.to_vec());
+                buf.extend_from_slice(start);
             } else {
                 buf.push(0u8);
-                buf.append(&mut start.to_vec());
-                buf.append(&mut end.to_vec());
+                buf.extend_from_slice(start);
+                buf.extend_from_slice(end);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        2 + self.0.len() * 7
+    }
+}
+
+
+// fixed size numbers
+
+macro_rules! number_field {
+    ($($t:ty),*) => {
+        $(
+            impl ReadField for $t {
+                fn read(buf: &[u8], index: usize) -> Result<($t, usize), DecodeError> {
+                    const SIZE: usize = mem::size_of::<$t>();
+                    let mut bytes = [0u8; SIZE];
+                    bytes.copy_from_slice(read_slice(buf, index, SIZE)?);
+                    Ok((<$t>::from_be_bytes(bytes), SIZE))
+                }
+            }
+
+            impl WriteField for $t {
+                fn write_to(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn size_hint(&self) -> usize {
+                    mem::size_of::<$t>()
+                }
+            }
+        )*
+    }
+}
+
+number_field!(i8, i16, i64, f32, f64);
+
+macro_rules! little_endian_field {
+    ($($t:ty),*) => {
+        $(
+            impl ReadField for LittleEndian<$t> {
+                fn read(buf: &[u8], index: usize) -> Result<(LittleEndian<$t>, usize), DecodeError> {
+                    const SIZE: usize = mem::size_of::<$t>();
+                    let mut bytes = [0u8; SIZE];
+                    bytes.copy_from_slice(read_slice(buf, index, SIZE)?);
+                    Ok((LittleEndian(<$t>::from_le_bytes(bytes)), SIZE))
+                }
+            }
+
+            impl WriteField for LittleEndian<$t> {
+                fn write_to(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.0.to_le_bytes());
+                }
+
+                fn size_hint(&self) -> usize {
+                    mem::size_of::<$t>()
+                }
+            }
+        )*
+    }
+}
+
+little_endian_field!(u16, u32, u64, i16, i32, i64, f32, f64);
+
+// variable length numbers
+
+/// Reads up to `max_bytes` 7-bit groups, least significant first
+fn read_var_u64(buf: &[u8], index: usize, max_bytes: usize) -> Result<(u64, usize), DecodeError> {
+    let mut result = 0u64;
+    for i in 0..max_bytes {
+        let byte = *buf.get(index + i).ok_or(DecodeError::UnexpectedEof)?;
+        result |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((result, i + 1));
+        }
+    }
+    Err(DecodeError::VarIntTooLong)
+}
+
+fn write_var_u64(mut value: u64, buf: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+/// The biggest number a 3 byte VarInt can hold
+pub const MAX_PADDED_VAR_INT: usize = (1 << 21) - 1;
+
+/// Writes `value` into `buf` as a VarInt that always takes 3 bytes, so space for it can be
+/// reserved before the value is known. Extra continuation bytes are allowed by the protocol.
+pub fn write_padded_var_int(value: usize, buf: &mut [u8]) {
+    buf[0] = (value & 0x7F) as u8 | 0x80;
+    buf[1] = ((value >> 7) & 0x7F) as u8 | 0x80;
+    buf[2] = ((value >> 14) & 0x7F) as u8;
+}
+
+/// How many bytes `value` takes up as a VarInt
+pub fn var_int_size(value: u64) -> usize {
+    let bits = 64 - value.leading_zeros() as usize;
+    1.max((bits + 6) / 7)
+}
+
+impl ReadField for VarLong {
+    fn read(buf: &[u8], index: usize) -> Result<(VarLong, usize), DecodeError> {
+        read_var_u64(buf, index, 10).map(|(value, length)| (VarLong(value as i64), length))
+    }
+}
+
+impl WriteField for VarLong {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        write_var_u64(self.0 as u64, buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        var_int_size(self.0 as u64)
+    }
+}
+
+impl ReadField for VarUInt {
+    fn read(buf: &[u8], index: usize) -> Result<(VarUInt, usize), DecodeError> {
+        let (value, length) = read_var_u64(buf, index, 5)?;
+        if value > u32::MAX as u64 {
+            return Err(DecodeError::VarIntTooLong);
+        }
+        Ok((VarUInt(value as u32), length))
+    }
+}
+
+impl WriteField for VarUInt {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        write_var_u64(self.0 as u64, buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        var_int_size(self.0 as u64)
+    }
+}
+
+impl ReadField for VarULong {
+    fn read(buf: &[u8], index: usize) -> Result<(VarULong, usize), DecodeError> {
+        read_var_u64(buf, index, 10).map(|(value, length)| (VarULong(value), length))
+    }
+}
+
+impl WriteField for VarULong {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        write_var_u64(self.0, buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        var_int_size(self.0)
+    }
+}
+
+impl ReadField for ZigZagVarInt {
+    fn read(buf: &[u8], index: usize) -> Result<(ZigZagVarInt, usize), DecodeError> {
+        let (VarUInt(value), length) = <VarUInt as ReadField>::read(buf, index)?;
+        Ok((ZigZagVarInt((value >> 1) as i32 ^ -((value & 1) as i32)), length))
+    }
+}
+
+impl WriteField for ZigZagVarInt {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        write_var_u64(((self.0 << 1) ^ (self.0 >> 31)) as u32 as u64, buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        var_int_size(((self.0 << 1) ^ (self.0 >> 31)) as u32 as u64)
+    }
+}
+
+impl ReadField for ZigZagVarLong {
+    fn read(buf: &[u8], index: usize) -> Result<(ZigZagVarLong, usize), DecodeError> {
+        let (value, length) = read_var_u64(buf, index, 10)?;
+        Ok((ZigZagVarLong((value >> 1) as i64 ^ -((value & 1) as i64)), length))
+    }
+}
+
+impl WriteField for ZigZagVarLong {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        write_var_u64(((self.0 << 1) ^ (self.0 >> 63)) as u64, buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        var_int_size(((self.0 << 1) ^ (self.0 >> 63)) as u64)
+    }
+}
+
+// UUID
+
+impl UUID {
+    /// Version 3 UUID of `OfflinePlayer:<name>`, the same as Java's `UUID.nameUUIDFromBytes`
+    pub fn offline(username: &str) -> UUID {
+        let mut hash = Md5::digest(format!("OfflinePlayer:{}", username).as_bytes());
+        // version 3
+        hash[6] = (hash[6] & 0x0f) | 0x30;
+        // IETF variant
+        hash[8] = (hash[8] & 0x3f) | 0x80;
+
+        UUID(hash.iter().fold(0u128, |uuid, b| (uuid << 8) | *b as u128))
+    }
+
+    /// Parses a UUID with or without hyphens
+    pub fn parse(uuid: &str) -> Option<UUID> {
+        let hex: String = uuid.chars().filter(|c| *c != '-').collect();
+        if hex.len() != 32 {
+            return None;
+        }
+        u128::from_str_radix(&hex, 16).ok().map(UUID)
+    }
+
+    /// Formats the UUID as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`
+    pub fn hyphenated(&self) -> String {
+        let hex = format!("{:032x}", self.0);
+        format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+    }
+}
+
+impl ReadField for UUID {
+    fn read(buf: &[u8], index: usize) -> Result<(UUID, usize), DecodeError> {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(read_slice(buf, index, 16)?);
+        Ok((UUID(u128::from_be_bytes(bytes)), 16))
+    }
+}
+
+impl WriteField for UUID {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.0.to_be_bytes());
+    }
+
+    fn size_hint(&self) -> usize {
+        16
+    }
+}
+
+/// Bedrock Edition writes the most significant half first, but each half is little-endian
+impl ReadField for LittleEndian<UUID> {
+    fn read(buf: &[u8], index: usize) -> Result<(LittleEndian<UUID>, usize), DecodeError> {
+        let (LittleEndian(most), _) = <LittleEndian<u64> as ReadField>::read(buf, index)?;
+        let (LittleEndian(least), _) = <LittleEndian<u64> as ReadField>::read(buf, index + 8)?;
+        Ok((LittleEndian(UUID((most as u128) << 64 | least as u128)), 16))
+    }
+}
+
+impl WriteField for LittleEndian<UUID> {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&(((self.0).0 >> 64) as u64).to_le_bytes());
+        buf.extend_from_slice(&((self.0).0 as u64).to_le_bytes());
+    }
+
+    fn size_hint(&self) -> usize {
+        16
+    }
+}
+
+impl ReadField for HyphenatedUUID {
+    fn read(buf: &[u8], index: usize) -> Result<(HyphenatedUUID, usize), DecodeError> {
+        let (string, length) = <VarIntLengthPrefixedString as ReadField>::read(buf, index)?;
+        let uuid = UUID::parse(&string.0).ok_or(DecodeError::UnknownVariant)?;
+        Ok((HyphenatedUUID(uuid), length))
+    }
+}
+
+impl WriteField for HyphenatedUUID {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        VarIntLengthPrefixedString(self.0.hyphenated()).write_to(buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        37
+    }
+}
+
+// Position
+
+impl Position {
+    pub fn new(x: i32, y: i32, z: i32) -> Position {
+        Position { x, y, z }
+    }
+}
+
+/// Sign extends the lowest `bits` bits of `value`
+fn sign_extend(value: u64, bits: u32) -> i32 {
+    ((value << (64 - bits)) as i64 >> (64 - bits)) as i32
+}
+
+impl ReadField for Position {
+    fn read(buf: &[u8], index: usize) -> Result<(Position, usize), DecodeError> {
+        let (value, length) = <u64 as ReadField>::read(buf, index)?;
+        Ok((Position {
+            x: sign_extend(value >> 38, 26),
+            y: sign_extend(value >> 26, 12),
+            z: sign_extend(value, 26),
+        }, length))
+    }
+}
+
+impl WriteField for Position {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        let value = (self.x as u64 & 0x3FFFFFF) << 38
+            | (self.y as u64 & 0xFFF) << 26
+            | (self.z as u64 & 0x3FFFFFF);
+        value.write_to(buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        8
+    }
+}
+
+impl ReadField for Position1_14 {
+    fn read(buf: &[u8], index: usize) -> Result<(Position1_14, usize), DecodeError> {
+        let (value, length) = <u64 as ReadField>::read(buf, index)?;
+        Ok((Position1_14(Position {
+            x: sign_extend(value >> 38, 26),
+            y: sign_extend(value, 12),
+            z: sign_extend(value >> 12, 26),
+        }), length))
+    }
+}
+
+impl WriteField for Position1_14 {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        let position = self.0;
+        let value = (position.x as u64 & 0x3FFFFFF) << 38
+            | (position.z as u64 & 0x3FFFFFF) << 12
+            | (position.y as u64 & 0xFFF);
+        value.write_to(buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        8
+    }
+}
+
+// Angle
+
+impl ReadField for Angle {
+    fn read(buf: &[u8], index: usize) -> Result<(Angle, usize), DecodeError> {
+        buf.get(index).map(|b| (Angle(*b), 1)).ok_or(DecodeError::UnexpectedEof)
+    }
+}
+
+impl WriteField for Angle {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.push(self.0);
+    }
+
+    fn size_hint(&self) -> usize {
+        1
+    }
+}
+
+// fixed-point
+
+impl ReadField for FixedPointInt {
+    fn read(buf: &[u8], index: usize) -> Result<(FixedPointInt, usize), DecodeError> {
+        let (value, length) = <i32 as ReadField>::read(buf, index)?;
+        Ok((FixedPointInt(value as f64 / 32.0), length))
+    }
+}
+
+impl WriteField for FixedPointInt {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        ((self.0 * 32.0).floor() as i32).write_to(buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        4
+    }
+}
+
+impl ReadField for FixedPointByte {
+    fn read(buf: &[u8], index: usize) -> Result<(FixedPointByte, usize), DecodeError> {
+        let (value, length) = <i8 as ReadField>::read(buf, index)?;
+        Ok((FixedPointByte(value as f64 / 32.0), length))
+    }
+}
+
+impl WriteField for FixedPointByte {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        ((self.0 * 32.0).round() as i8).write_to(buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        1
+    }
+}
+
+// length prefixed arrays
+
+impl LengthPrefix for u8 {
+    fn from_length(length: usize) -> Self {
+        length as u8
+    }
+
+    fn length(&self) -> Option<usize> {
+        Some(*self as usize)
+    }
+}
+
+impl LengthPrefix for u16 {
+    fn from_length(length: usize) -> Self {
+        length as u16
+    }
+
+    fn length(&self) -> Option<usize> {
+        Some(*self as usize)
+    }
+}
+
+impl LengthPrefix for i16 {
+    fn from_length(length: usize) -> Self {
+        length as i16
+    }
+
+    fn length(&self) -> Option<usize> {
+        if *self < 0 { None } else { Some(*self as usize) }
+    }
+}
+
+impl LengthPrefix for i32 {
+    fn from_length(length: usize) -> Self {
+        length as i32
+    }
+
+    fn length(&self) -> Option<usize> {
+        if *self < 0 { None } else { Some(*self as usize) }
+    }
+}
+
+impl LengthPrefix for VarInt {
+    fn from_length(length: usize) -> Self {
+        VarInt(length as i32)
+    }
+
+    fn length(&self) -> Option<usize> {
+        if self.0 < 0 { None } else { Some(self.0 as usize) }
+    }
+}
+
+impl LengthPrefix for VarUInt {
+    fn from_length(length: usize) -> Self {
+        VarUInt(length as u32)
+    }
+
+    fn length(&self) -> Option<usize> {
+        Some(self.0 as usize)
+    }
+}
+
+impl<L, T> LengthPrefixedVec<L, T> {
+    pub fn new(elements: Vec<T>) -> Self {
+        LengthPrefixedVec(elements, PhantomData)
+    }
+}
+
+impl<L, T> Default for LengthPrefixedVec<L, T> {
+    fn default() -> Self {
+        LengthPrefixedVec(Vec::new(), PhantomData)
+    }
+}
+
+impl<L: LengthPrefix, T: ReadField> ReadField for LengthPrefixedVec<L, T> {
+    fn read(buf: &[u8], mut index: usize) -> Result<(Self, usize), DecodeError> {
+        let start_index = index;
+        let (length, size) = <L as ReadField>::read(buf, index)?;
+        index += size;
+        let length = length.length().ok_or(DecodeError::LengthExceedsLimit)?;
+
+        // every element takes at least a byte, so don't trust lengths longer than what's left
+        let mut elements = Vec::with_capacity(length.min(buf.len().saturating_sub(index)));
+        for _ in 0..length {
+            let (element, size) = <T as ReadField>::read(buf, index)?;
+            index += size;
+            elements.push(element);
+        }
+        Ok((LengthPrefixedVec::new(elements), index - start_index))
+    }
+}
+
+impl<L: LengthPrefix, T: WriteField> WriteField for LengthPrefixedVec<L, T> {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        L::from_length(self.0.len()).write_to(buf);
+        for element in self.0.iter() {
+            element.write_to(buf);
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        L::from_length(self.0.len()).size_hint() + self.0.iter().map(T::size_hint).sum::<usize>()
+    }
+}
+
+// Option, prefixed with a bool saying whether it's there
+
+impl<T: ReadField> ReadField for Option<T> {
+    fn read(buf: &[u8], index: usize) -> Result<(Self, usize), DecodeError> {
+        let (present, size) = <bool as ReadField>::read(buf, index)?;
+        if !present {
+            return Ok((None, size));
+        }
+        let (value, length) = <T as ReadField>::read(buf, index + size)?;
+        Ok((Some(value), size + length))
+    }
+}
+
+impl<T: WriteField> WriteField for Option<T> {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        match self {
+            Some(value) => {
+                buf.push(1u8);
+                value.write_to(buf);
+            }
+            None => buf.push(0u8)
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        1 + self.as_ref().map(T::size_hint).unwrap_or(0)
+    }
+}
+
+// Slot
+
+/// Reads a slot's item id, count & damage, returning `None` for an empty slot
+fn read_item_header(buf: &[u8], index: usize) -> Result<(Option<ItemStack>, usize), DecodeError> {
+    let (id, length) = <i16 as ReadField>::read(buf, index)?;
+    if id == -1 {
+        return Ok((None, length));
+    }
+    let (count, _) = <u8 as ReadField>::read(buf, index + 2)?;
+    let (damage, _) = <i16 as ReadField>::read(buf, index + 3)?;
+    Ok((Some(ItemStack { id, count, damage, nbt: Nbt::default() }), 5))
+}
+
+fn write_item_header(item: &ItemStack, buf: &mut Vec<u8>) {
+    item.id.write_to(buf);
+    item.count.write_to(buf);
+    item.damage.write_to(buf);
+}
+
+impl ReadField for Slot {
+    fn read(buf: &[u8], index: usize) -> Result<(Slot, usize), DecodeError> {
+        let (item, length) = read_item_header(buf, index)?;
+        match item {
+            Some(mut item) => {
+                let (nbt, size) = <GzipNbt as ReadField>::read(buf, index + length)?;
+                item.nbt = nbt.0;
+                Ok((Slot(Some(item)), length + size))
+            }
+            None => Ok((Slot(None), length))
+        }
+    }
+}
+
+impl WriteField for Slot {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        write_slot(&self.0, buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        if self.0.is_some() { 7 } else { 2 }
+    }
+}
+
+fn write_slot(slot: &Option<ItemStack>, buf: &mut Vec<u8>) {
+    match slot {
+        Some(item) => {
+            write_item_header(item, buf);
+            GzipNbt(item.nbt.clone()).write_to(buf);
+        }
+        None => (-1i16).write_to(buf)
+    }
+}
+
+impl ReadField for Slot1_8 {
+    fn read(buf: &[u8], index: usize) -> Result<(Slot1_8, usize), DecodeError> {
+        let (item, length) = read_item_header(buf, index)?;
+        match item {
+            Some(mut item) => {
+                let (nbt, size) = <Nbt as ReadField>::read(buf, index + length)?;
+                item.nbt = nbt;
+                Ok((Slot1_8(Some(item)), length + size))
+            }
+            None => Ok((Slot1_8(None), length))
+        }
+    }
+}
+
+impl WriteField for Slot1_8 {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        write_slot_1_8(&self.0, buf);
+    }
+
+    fn size_hint(&self) -> usize {
+        if self.0.is_some() { 6 } else { 2 }
+    }
+}
+
+fn write_slot_1_8(slot: &Option<ItemStack>, buf: &mut Vec<u8>) {
+    match slot {
+        Some(item) => {
+            write_item_header(item, buf);
+            item.nbt.write_to(buf);
+        }
+        None => (-1i16).write_to(buf)
+    }
+}
+
+// EntityMetadata
+
+/// Marks the end of entity metadata
+const METADATA_END: u8 = 0x7F;
+
+impl MetadataValue {
+    pub fn type_id(&self) -> u8 {
+        match self {
+            MetadataValue::Byte(_) => 0,
+            MetadataValue::Short(_) => 1,
+            MetadataValue::Int(_) => 2,
+            MetadataValue::Float(_) => 3,
+            MetadataValue::String(_) => 4,
+            MetadataValue::Slot(_) => 5,
+            MetadataValue::Position(..) => 6,
+            MetadataValue::Rotation(..) => 7,
+        }
+    }
+}
+
+/// Reads metadata entries until the end marker. 1.8 changed the slot format & added rotations.
+fn read_metadata(buf: &[u8], index: usize, v1_8: bool) -> Result<(Vec<(u8, MetadataValue)>, usize), DecodeError> {
+    let mut entries = Vec::new();
+    let mut length = 0;
+    loop {
+        let (key, size) = <u8 as ReadField>::read(buf, index + length)?;
+        length += size;
+        if key == METADATA_END {
+            break;
+        }
+
+        // the top 3 bits are the type & the rest is the index
+        let i = index + length;
+        let (value, size) = match key >> 5 {
+            0 => <i8 as ReadField>::read(buf, i).map(|(v, s)| (MetadataValue::Byte(v), s))?,
+            1 => <i16 as ReadField>::read(buf, i).map(|(v, s)| (MetadataValue::Short(v), s))?,
+            2 => <i32 as ReadField>::read(buf, i).map(|(v, s)| (MetadataValue::Int(v), s))?,
+            3 => <f32 as ReadField>::read(buf, i).map(|(v, s)| (MetadataValue::Float(v), s))?,
+            4 => <VarIntLengthPrefixedString as ReadField>::read(buf, i).map(|(v, s)| (MetadataValue::String(v.0), s))?,
+            5 if v1_8 => <Slot1_8 as ReadField>::read(buf, i).map(|(v, s)| (MetadataValue::Slot(v.0), s))?,
+            5 => <Slot as ReadField>::read(buf, i).map(|(v, s)| (MetadataValue::Slot(v.0), s))?,
+            6 => {
+                let x = <i32 as ReadField>::read(buf, i)?.0;
+                let y = <i32 as ReadField>::read(buf, i + 4)?.0;
+                let z = <i32 as ReadField>::read(buf, i + 8)?.0;
+                (MetadataValue::Position(x, y, z), 12)
+            }
+            7 if v1_8 => {
+                let pitch = <f32 as ReadField>::read(buf, i)?.0;
+                let yaw = <f32 as ReadField>::read(buf, i + 4)?.0;
+                let roll = <f32 as ReadField>::read(buf, i + 8)?.0;
+                (MetadataValue::Rotation(pitch, yaw, roll), 12)
+            }
+            _ => return Err(DecodeError::UnknownVariant)
+        };
+        length += size;
+        entries.push((key & 0x1F, value));
+    }
+    Ok((entries, length))
+}
+
+fn write_metadata(entries: &[(u8, MetadataValue)], buf: &mut Vec<u8>, v1_8: bool) {
+    for (index, value) in entries.iter() {
+        buf.push(value.type_id() << 5 | index & 0x1F);
+        match value {
+            MetadataValue::Byte(v) => v.write_to(buf),
+            MetadataValue::Short(v) => v.write_to(buf),
+            MetadataValue::Int(v) => v.write_to(buf),
+            MetadataValue::Float(v) => v.write_to(buf),
+            MetadataValue::String(v) => VarIntLengthPrefixedString(v.clone()).write_to(buf),
+            MetadataValue::Slot(v) if v1_8 => write_slot_1_8(v, buf),
+            MetadataValue::Slot(v) => write_slot(v, buf),
+            MetadataValue::Position(x, y, z) => {
+                x.write_to(buf);
+                y.write_to(buf);
+                z.write_to(buf);
+            }
+            MetadataValue::Rotation(pitch, yaw, roll) => {
+                pitch.write_to(buf);
+                yaw.write_to(buf);
+                roll.write_to(buf);
             }
         }
-        buf
+    }
+    buf.push(METADATA_END);
+}
+
+impl ReadField for EntityMetadata {
+    fn read(buf: &[u8], index: usize) -> Result<(EntityMetadata, usize), DecodeError> {
+        read_metadata(buf, index, false).map(|(entries, length)| (EntityMetadata(entries), length))
+    }
+}
+
+impl WriteField for EntityMetadata {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        write_metadata(&self.0, buf, false);
+    }
+
+    fn size_hint(&self) -> usize {
+        1 + self.0.len() * 5
+    }
+}
+
+impl ReadField for EntityMetadata1_8 {
+    fn read(buf: &[u8], index: usize) -> Result<(EntityMetadata1_8, usize), DecodeError> {
+        read_metadata(buf, index, true).map(|(entries, length)| (EntityMetadata1_8(entries), length))
+    }
+}
+
+impl WriteField for EntityMetadata1_8 {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        write_metadata(&self.0, buf, true);
+    }
+
+    fn size_hint(&self) -> usize {
+        1 + self.0.len() * 5
+    }
+}
+
+// RemainingBytes
+
+impl ReadField for RemainingBytes {
+    fn read(buf: &[u8], index: usize) -> Result<(RemainingBytes, usize), DecodeError> {
+        let bytes = buf.get(index..).ok_or(DecodeError::UnexpectedEof)?.to_vec();
+        let length = bytes.len();
+        Ok((RemainingBytes(bytes), length))
+    }
+}
+
+impl WriteField for RemainingBytes {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.0);
+    }
+
+    fn size_hint(&self) -> usize {
+        self.0.len()
     }
 }
 
+// TrailingOption
+
+impl<T: ReadField> ReadField for TrailingOption<T> {
+    fn read(buf: &[u8], index: usize) -> Result<(Self, usize), DecodeError> {
+        if index >= buf.len() {
+            return Ok((TrailingOption(None), 0));
+        }
+        let (value, length) = <T as ReadField>::read(buf, index)?;
+        Ok((TrailingOption(Some(value)), length))
+    }
+}
+
+impl<T: WriteField> WriteField for TrailingOption<T> {
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        if let Some(ref value) = self.0 {
+            value.write_to(buf);
+        }
+    }
+
+    fn size_hint(&self) -> usize {
+        self.0.as_ref().map(T::size_hint).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `value`, checks the size hint, then reads it back
+    fn round_trip<T: ReadField + WriteField>(value: &T) -> (T, Vec<u8>) {
+        let mut buf = Vec::new();
+        value.write_to(&mut buf);
+        assert_eq!(value.size_hint(), buf.len());
+        let (read, length) = T::read(&buf, 0).unwrap();
+        assert_eq!(length, buf.len());
+        (read, buf)
+    }
+
+    #[test]
+    fn bools() {
+        assert_eq!(round_trip(&true), (true, vec![1]));
+        assert_eq!(round_trip(&false), (false, vec![0]));
+    }
+
+    #[test]
+    fn big_endian_numbers() {
+        assert_eq!(round_trip(&-2i8), (-2, vec![0xFE]));
+        assert_eq!(round_trip(&-2i16), (-2, vec![0xFF, 0xFE]));
+        assert_eq!(round_trip(&0x0102030405060708i64), (0x0102030405060708, vec![1, 2, 3, 4, 5, 6, 7, 8]));
+        assert_eq!(round_trip(&1.5f32), (1.5, vec![0x3F, 0xC0, 0, 0]));
+        assert_eq!(round_trip(&-2.0f64), (-2.0, vec![0xC0, 0, 0, 0, 0, 0, 0, 0]));
+        for &value in &[i8::MIN, i8::MAX] {
+            assert_eq!(round_trip(&value).0, value);
+        }
+        for &value in &[i16::MIN, i16::MAX] {
+            assert_eq!(round_trip(&value).0, value);
+        }
+        for &value in &[i64::MIN, i64::MAX] {
+            assert_eq!(round_trip(&value).0, value);
+        }
+        assert!(round_trip(&f32::NAN).0.is_nan());
+        assert_eq!(round_trip(&f64::INFINITY).0, f64::INFINITY);
+        assert!(<i64 as ReadField>::read(&[0; 7], 0).is_err());
+    }
+
+    #[test]
+    fn var_int_boundaries() {
+        for &(value, size) in &[(0, 1), (127, 1), (128, 2), (2097151, 3), (i32::MAX, 5), (-1, 5), (i32::MIN, 5)] {
+            let (read, buf) = round_trip(&VarInt(value));
+            assert_eq!(read.0, value);
+            assert_eq!(buf.len(), size);
+        }
+        assert_eq!(round_trip(&VarInt(300)).1, vec![0xAC, 0x02]);
+        assert!(<VarInt as ReadField>::read(&[0xFF; 6], 0).is_err());
+        assert!(<VarInt as ReadField>::read(&[0x80], 0).is_err());
+    }
+
+    #[test]
+    fn little_endian_floats() {
+        let (read, buf) = round_trip(&LittleEndian(1.5f32));
+        assert_eq!(read, LittleEndian(1.5f32));
+        assert_eq!(buf, vec![0, 0, 0xC0, 0x3F]);
+
+        let (read, buf) = round_trip(&LittleEndian(-2.0f64));
+        assert_eq!(read, LittleEndian(-2.0f64));
+        assert_eq!(buf, vec![0, 0, 0, 0, 0, 0, 0, 0xC0]);
+
+        for &value in &[i16::MIN, -1, i16::MAX] {
+            assert_eq!(round_trip(&LittleEndian(value)).0, LittleEndian(value));
+        }
+        for &value in &[i32::MIN, -1, i32::MAX] {
+            assert_eq!(round_trip(&LittleEndian(value)).0, LittleEndian(value));
+        }
+        for &value in &[i64::MIN, -1, i64::MAX] {
+            assert_eq!(round_trip(&LittleEndian(value)).0, LittleEndian(value));
+        }
+        assert_eq!(round_trip(&LittleEndian(0x0102u16)).1, vec![2, 1]);
+        assert_eq!(round_trip(&LittleEndian(1u64)).1, vec![1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn var_long_boundaries() {
+        for &(value, size) in &[(0, 1), (127, 1), (128, 2), (i64::MAX, 9), (-1, 10), (i64::MIN, 10)] {
+            let (read, buf) = round_trip(&VarLong(value));
+            assert_eq!(read, VarLong(value));
+            assert_eq!(buf.len(), size);
+        }
+        assert!(VarLong::read(&[0xFF; 11], 0).is_err());
+    }
+
+    #[test]
+    fn unsigned_var_ints() {
+        for &value in &[0, 300, u32::MAX] {
+            assert_eq!(round_trip(&VarUInt(value)).0, VarUInt(value));
+        }
+        for &value in &[0, 1 << 35, u64::MAX] {
+            assert_eq!(round_trip(&VarULong(value)).0, VarULong(value));
+        }
+    }
+
+    #[test]
+    fn zig_zag_negative_values() {
+        assert_eq!(round_trip(&ZigZagVarInt(-1)).1, vec![0x01]);
+        assert_eq!(round_trip(&ZigZagVarInt(1)).1, vec![0x02]);
+        assert_eq!(round_trip(&ZigZagVarInt(-65)).1, vec![0x81, 0x01]);
+        for &value in &[-1, -64, i32::MIN, i32::MAX] {
+            assert_eq!(round_trip(&ZigZagVarInt(value)).0, ZigZagVarInt(value));
+        }
+        assert_eq!(round_trip(&ZigZagVarLong(-1)).1, vec![0x01]);
+        for &value in &[-1, -1 << 40, i64::MIN, i64::MAX] {
+            assert_eq!(round_trip(&ZigZagVarLong(value)).0, ZigZagVarLong(value));
+        }
+    }
+
+    #[test]
+    fn little_endian() {
+        let (read, buf) = round_trip(&LittleEndian(0x01020304u32));
+        assert_eq!(read, LittleEndian(0x01020304u32));
+        assert_eq!(buf, vec![4, 3, 2, 1]);
+
+        let uuid = UUID(0x00112233445566778899AABBCCDDEEFF);
+        let (read, buf) = round_trip(&LittleEndian(uuid));
+        assert_eq!(read, LittleEndian(uuid));
+        assert_eq!(&buf[..8], &[0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00]);
+    }
+
+    #[test]
+    fn position_bit_layouts() {
+        let position = Position::new(-2, 70, 5);
+
+        let (read, buf) = round_trip(&position);
+        assert_eq!(read, position);
+        assert_eq!(buf, (0x3FFFFFEu64 << 38 | 70 << 26 | 5).to_be_bytes().to_vec());
+
+        let (read, buf) = round_trip(&Position1_14(position));
+        assert_eq!(read, Position1_14(position));
+        assert_eq!(buf, (0x3FFFFFEu64 << 38 | 5 << 12 | 70).to_be_bytes().to_vec());
+
+        let negative = Position::new(-33554432, -2048, -1);
+        assert_eq!(round_trip(&negative).0, negative);
+        assert_eq!(round_trip(&Position1_14(negative)).0, Position1_14(negative));
+    }
+
+    #[test]
+    fn uuid_forms() {
+        let uuid = UUID::parse("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap();
+
+        let (read, buf) = round_trip(&uuid);
+        assert_eq!(read, uuid);
+        assert_eq!(buf[0], 0x06);
+        assert_eq!(buf[15], 0xf5);
+
+        let (read, buf) = round_trip(&HyphenatedUUID(uuid));
+        assert_eq!(read, HyphenatedUUID(uuid));
+        assert_eq!(buf[0], 36);
+        assert_eq!(&buf[1..], b"069a79f4-44e9-4726-a5be-fca90e38aaf5");
+
+        assert_eq!(UUID::parse("069a79f444e94726a5befca90e38aaf5"), Some(uuid));
+        assert_eq!(UUID::parse("069a79f4"), None);
+    }
+
+    #[test]
+    fn fixed_point_rounding() {
+        assert_eq!(round_trip(&FixedPointInt(1.5)).0, FixedPointInt(1.5));
+        // ints are floored so an entity never ends up past a block edge
+        assert_eq!(round_trip(&FixedPointInt(1.01)).0, FixedPointInt(1.0));
+        assert_eq!(round_trip(&FixedPointInt(-0.01)).0, FixedPointInt(-0.03125));
+
+        assert_eq!(round_trip(&FixedPointByte(0.02)).0, FixedPointByte(0.03125));
+        assert_eq!(round_trip(&FixedPointByte(-0.02)).0, FixedPointByte(-0.03125));
+        assert_eq!(round_trip(&FixedPointByte(0.01)).0, FixedPointByte(0.0));
+    }
+
+    #[test]
+    fn angles() {
+        assert_eq!(round_trip(&Angle(192)), (Angle(192), vec![192]));
+        assert!(<Angle as ReadField>::read(&[], 0).is_err());
+    }
+
+    #[test]
+    fn length_prefixed_vec_and_option() {
+        let vec: LengthPrefixedVec<VarInt, i32> = LengthPrefixedVec(vec![1, -2, 3], PhantomData);
+        let (read, buf) = round_trip(&vec);
+        assert_eq!(read.0, vec![1, -2, 3]);
+        assert_eq!(buf[0], 3);
+
+        let vec: LengthPrefixedVec<u16, u8> = LengthPrefixedVec(Vec::new(), PhantomData);
+        let (read, buf) = round_trip(&vec);
+        assert!(read.0.is_empty());
+        assert_eq!(buf, vec![0, 0]);
+
+        let (read, buf) = round_trip(&Some(7i32));
+        assert_eq!(read, Some(7));
+        assert_eq!(buf, vec![1, 0, 0, 0, 7]);
+        let (read, buf) = round_trip(&None::<i32>);
+        assert_eq!(read, None);
+        assert_eq!(buf, vec![0]);
+    }
+}
@@ -2,44 +2,138 @@ use std::net::{SocketAddr, IpAddr, Ipv4Addr};
 use std::thread;
 use std::time::*;
 use std::thread::JoinHandle;
-use std::sync::mpsc::{channel, Sender, Receiver};
-use std::sync::Arc;
+use std::sync::mpsc::{channel, sync_channel, Sender, SyncSender, Receiver, TrySendError};
+use std::sync::{Arc, Mutex};
 
 use network::*;
 use network::types::*;
 use network::packet::*;
+use network::encryption::*;
+use network::auth::*;
+use network::bedrock_auth::*;
+use network::chat::*;
+use network::reliability::{MIN_MTU_SIZE, MAX_MTU_SIZE};
+use config::*;
+use status::*;
 
 use network::protocol::*;
 use network::protocol::java::*;
 use network::protocol::bedrock::*;
 
+/// Logins are authenticated on this many threads
+const AUTH_THREADS: usize = 4;
+/// Logins waiting for an auth thread. Any more are turned away.
+const MAX_QUEUED_AUTHS: usize = 64;
+
 pub struct Server {
+    pub config: ServerConfig,
     pub network_manager: NetworkManager,
     pub connection_manager: Arc<ConnectionManager>,
     pub threads: Vec<JoinHandle<()>>,
     // Packet Channel
     pub packet_sender: Sender<(SocketAddr, Packet)>,
     pub packet_receiver: Receiver<(SocketAddr, Packet)>,
+    // Java login encryption
+    pub server_key: ServerKey,
+    /// Java packets at least this big are compressed. Negative values disable compression.
+    pub compression_threshold: i32,
+    /// Checks the login chain Bedrock clients send
+    pub chain_verifier: ChainVerifier,
+    // Bedrock encryption
+    pub bedrock_key: BedrockKey,
+    /// Identifies the server to RakNet clients
+    pub raknet_guid: u64,
+    pub favicon: Favicon,
+    // Logins for the auth threads: address, username & server hash
+    pub auth_jobs: SyncSender<(SocketAddr, String, String)>,
+    // Authentication results from the auth threads
+    pub auth_receiver: Receiver<(SocketAddr, Option<GameProfile>)>,
+    // Clients that closed their connection
+    pub disconnect_receiver: Receiver<SocketAddr>,
+    // Timeouts
+    pub keep_alive_interval: Duration,
+    pub keep_alive_timeout: Duration,
+    pub read_timeout: Duration,
 }
 
 impl Server {
-    pub fn new() -> Self {
-        let connection_manager = Arc::new(ConnectionManager::new());
+    pub fn new(config: ServerConfig) -> Self {
+        let (disconnect_sender, disconnect_receiver) = channel::<SocketAddr>();
+        let connection_manager = Arc::new(ConnectionManager::new(disconnect_sender));
         let (packet_sender, packet_receiver) = channel::<(SocketAddr, Packet)>();
         let network_manager = NetworkManager::new(connection_manager.clone(), packet_sender.clone());
+        println!("[Server] Generating RSA keypair");
+        let server_key = ServerKey::generate();
+        let (auth_sender, auth_receiver) = channel::<(SocketAddr, Option<GameProfile>)>();
+
+        // offline players skip encryption & are never sent to the authenticator
+        let authenticator: Arc<dyn Authenticator> = Arc::new(SessionServerAuthenticator::new(&config.java.session_server));
+        let (auth_jobs, threads) = Server::start_auth_threads(authenticator, auth_sender);
+        let chain_verifier = if config.online_mode {
+            // the key is checked when the config is loaded
+            ChainVerifier::new(&config.bedrock.root_key).unwrap()
+        } else {
+            ChainVerifier::offline()
+        };
 
         Self {
             network_manager,
             connection_manager,
-            threads: Vec::with_capacity(4),
+            threads,
             packet_sender,
             packet_receiver,
+            server_key,
+            compression_threshold: config.compression_threshold,
+            chain_verifier,
+            bedrock_key: BedrockKey::generate(),
+            raknet_guid: rand::random(),
+            favicon: Favicon::new(FAVICON_PATH),
+            auth_jobs,
+            auth_receiver,
+            disconnect_receiver,
+            keep_alive_interval: Duration::from_secs(config.timeouts.keep_alive_interval),
+            keep_alive_timeout: Duration::from_secs(config.timeouts.keep_alive_timeout),
+            read_timeout: Duration::from_secs(config.timeouts.read_timeout),
+            config,
         }
     }
 
+    /// Starts the threads that run `authenticator`. A fixed number are used so a flood of logins
+    /// can't start threads without limit.
+    fn start_auth_threads(
+        authenticator: Arc<dyn Authenticator>,
+        auth_sender: Sender<(SocketAddr, Option<GameProfile>)>,
+    ) -> (SyncSender<(SocketAddr, String, String)>, Vec<JoinHandle<()>>) {
+        let (job_sender, job_receiver) = sync_channel::<(SocketAddr, String, String)>(MAX_QUEUED_AUTHS);
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let threads = (0..AUTH_THREADS).map(|i| {
+            let authenticator = authenticator.clone();
+            let auth_sender = auth_sender.clone();
+            let job_receiver = job_receiver.clone();
+            thread::Builder::new().name(format!("Auth-{}", i)).spawn(move || {
+                loop {
+                    // the lock is only held while waiting for a job
+                    let job = job_receiver.lock().unwrap().recv();
+                    match job {
+                        Ok((address, username, hash)) => {
+                            let profile = authenticator.authenticate(&username, &hash);
+                            if auth_sender.send((address, profile)).is_err() {
+                                return;
+                            }
+                        }
+                        Err(_) => return
+                    }
+                }
+            }).unwrap()
+        }).collect();
+        (job_sender, threads)
+    }
+
     pub fn start(&mut self) {
-        self.network_manager.start();
-        let ticks_per_second = 20;
+        let java_address = if self.config.java.enabled { Some(self.config.java.address) } else { None };
+        let bedrock_address = if self.config.bedrock.enabled { Some(self.config.bedrock.address) } else { None };
+        self.network_manager.start(java_address, bedrock_address);
+        let ticks_per_second = self.config.tick_rate.max(1) as u64;
         let tick_time = Duration::from_millis(1000 / ticks_per_second);
         // Main Game Loop
         loop {
@@ -47,6 +141,10 @@ impl Server {
 
             // tick
             self.handle_packets();
+            self.handle_authentications();
+            self.handle_disconnects();
+            self.check_timeouts();
+            self.tick_connections();
 
             match now.elapsed() {
                 Ok(elapsed) => {
@@ -85,12 +183,166 @@ impl Server {
         }
     }
 
+    /// Finishes logging in players that the auth threads are done with
+    pub fn handle_authentications(&mut self) {
+        while let Ok((address, profile)) = self.auth_receiver.try_recv() {
+            match profile {
+                Some(profile) => {
+                    println!("[Server] Authenticated {} ({})", profile.name, profile.hyphenated_uuid());
+                    if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+                        connection.get().profile = Some(profile.clone());
+                    }
+                    self.finish_login(address, profile);
+                }
+                None => {
+                    println!("[Server] Failed to authenticate {}", address);
+                    let reason = TextComponent::text("Failed to verify username!").color(Color::Red);
+                    let response = Packet::java_v1_7_DisconnectPacket(v1_7::DisconnectPacket::new(reason));
+                    self.send_packet(address, response);
+                    self.disconnect(address);
+                }
+            }
+        }
+    }
+
+    /// Cleans up after connections that were removed, whether the client or the server closed them
+    pub fn handle_disconnects(&mut self) {
+        while let Ok(address) = self.disconnect_receiver.try_recv() {
+            println!("[Server] {} disconnected", address);
+        }
+    }
+
+    /// Sends KeepAlivePackets to Java players, answers Beta pings & closes connections that have stopped responding.
+    /// Bedrock connections time out when we stop getting datagrams or they stop acknowledging ours.
+    pub fn check_timeouts(&mut self) {
+        let mut timed_out = Vec::new();
+        let mut timed_out_players = Vec::new();
+        let mut keep_alives = Vec::new();
+        let mut beta_pings = Vec::new();
+
+        let addresses = self.connection_manager.tcp_addresses.lock().unwrap().clone();
+        for address in addresses {
+            if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+                let connection = connection.get();
+                if connection.is_beta_ping() {
+                    beta_pings.push(address);
+                } else if connection.protocol_state == State::JavaPlay {
+                    match connection.keep_alive_id {
+                        Some(_) => {
+                            if connection.keep_alive_sent.elapsed() > self.keep_alive_timeout {
+                                timed_out_players.push(address);
+                            }
+                        }
+                        None => {
+                            if connection.keep_alive_sent.elapsed() > self.keep_alive_interval {
+                                keep_alives.push(address);
+                            }
+                        }
+                    }
+                } else if connection.last_read.elapsed() > self.read_timeout {
+                    timed_out.push(address);
+                }
+            }
+        }
+
+        let addresses = self.connection_manager.udp_addresses.lock().unwrap().clone();
+        for address in addresses {
+            if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+                let connection = connection.get();
+                if connection.last_read.elapsed() > self.read_timeout || connection.raknet.is_stalled() {
+                    timed_out.push(address);
+                }
+            }
+        }
+
+        for address in keep_alives {
+            self.send_keep_alive(address);
+        }
+
+        for address in beta_pings {
+            self.handle_packet(address, Packet::java_legacy_BetaPingPacket(legacy::BetaPingPacket::new()));
+        }
+
+        for address in timed_out_players {
+            println!("[Server] {} timed out", address);
+            let reason = TextComponent::text("Timed out");
+            self.send_packet(address, Packet::java_v1_7_PlayDisconnectPacket(v1_7::PlayDisconnectPacket::new(reason)));
+            self.disconnect(address);
+        }
+
+        for address in timed_out {
+            println!("[Server] {} timed out", address);
+            self.disconnect(address);
+        }
+    }
+
+    /// Lets Bedrock connections resend anything that was lost
+    pub fn tick_connections(&mut self) {
+        let addresses = self.connection_manager.udp_addresses.lock().unwrap().clone();
+        for address in addresses {
+            if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+                connection.get().tick();
+            }
+        }
+    }
+
+    /// Closes the connection to a client
+    pub fn disconnect(&mut self, address: SocketAddr) {
+        if let Some(mut connection) = self.connection_manager.remove(&address) {
+            // try to get out anything we sent before this
+            connection.flush();
+        }
+    }
+
+    /// Answers a legacy ping & closes the connection
+    fn send_legacy_kick(&mut self, address: SocketAddr, reason: &str) {
+        if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+            connection.get().send_legacy_kick(reason);
+        }
+        self.disconnect(address);
+    }
+
+    fn send_keep_alive(&mut self, address: SocketAddr) {
+        let id = rand::random::<i32>() as i64;
+        let protocol = match self.connection_manager.connections.find_mut(&address) {
+            Some(mut connection) => {
+                let connection = connection.get();
+                connection.keep_alive_id = Some(id);
+                connection.keep_alive_sent = Instant::now();
+                connection.protocol
+            }
+            None => return
+        };
+
+        let keep_alive = match protocol {
+            Protocol::ProtocolJava_1_7(_) => Packet::java_v1_7_KeepAlivePacket(v1_7::KeepAlivePacket::new(id as i32)),
+            Protocol::ProtocolJava_1_8(_)
+            | Protocol::ProtocolJava_1_9(_)
+            | Protocol::ProtocolJava_1_10(_)
+            | Protocol::ProtocolJava_1_11(_) => Packet::java_v1_8_KeepAlivePacket(v1_8::KeepAlivePacket::new(VarInt(id as i32))),
+            Protocol::ProtocolJava_1_12(_)
+            | Protocol::ProtocolJava_1_13(_)
+            | Protocol::ProtocolJava_1_14(_) => Packet::java_v1_12_KeepAlivePacket(v1_12::KeepAlivePacket::new(id as u64)),
+            _ => return
+        };
+        self.send_packet(address, keep_alive);
+    }
+
+    fn handle_keep_alive(&mut self, address: SocketAddr, id: i64) {
+        if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+            let connection = connection.get();
+            if connection.keep_alive_id == Some(id) {
+                connection.keep_alive_id = None;
+            }
+        }
+    }
+
     fn handle_packet(&mut self, address: SocketAddr, packet: Packet) {
         match packet {
             // Ping
             Packet::java_v1_7_HandshakePacket(packet) => {
                 let protocol_version = packet.protocol_version.0;
-                let protocol = protocol::get_protocol(protocol_version);
+                let protocol = protocol::get_protocol(ProtocolEdition::JavaEdition, protocol_version);
                 if let Some(protocol) = protocol.clone() {
                     if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
                         connection.get().protocol = protocol
@@ -99,37 +351,9 @@ impl Server {
 
                 match packet.next_state.0 {
                     1 => {
-                        let support = if let Some(protocol) = protocol {
-                            format!("§2Client: {}", protocol.name())
-                        } else {
-                            "§cYour version is not supported yet!".to_string()
-                        };
-
                         // Server List Ping
-                        let response_string = format!("{{\
-                            \"version\": {{\
-                                \"name\": \"§7[§f1.7-1.14§7] §e0§b/§e1000\",\
-                                \"protocol\": -1\
-                            }},\
-                            \"players\": {{\
-                                \"max\": 3,\
-                                \"online\": 2,\
-                                \"sample\": [\
-                                    {{\
-                                        \"name\": \"§bphast §fis §calpha §fsoftware.\",\
-                                        \"id\": \"4566e69f-c907-48ee-8d71-d7ba5aa00d20\"\
-                                    }},\
-                                    {{\
-                                        \"name\": \"don't expect much\",\
-                                        \"id\": \"4566e69f-c907-48ee-8d71-d7ba5aa00d20\"\
-                                    }}\
-                                ]\
-                            }},\
-                            \"description\": {{\
-                                \"text\": \"§bphast §eserver\\n§f§ohttps://pha.st/ {}\"\
-                            }}\
-                        }}", support);
-                        let response = Packet::java_v1_7_ResponsePacket(v1_7::ResponsePacket::new(VarIntLengthPrefixedString(response_string.to_string())));
+                        let response_string = self.status(protocol).to_json(protocol_version);
+                        let response = Packet::java_v1_7_ResponsePacket(v1_7::ResponsePacket::new(VarIntLengthPrefixedString(response_string)));
                         self.send_packet(address, response);
                     }
                     2 => {
@@ -137,14 +361,22 @@ impl Server {
 
                         // if we don't support the protocol, disconnect them
                         if let None = protocol {
-                            let response_string = "{\"text\": \"Please use a supported version!\"}";
-                            let response = Packet::java_v1_7_DisconnectPacket(v1_7::DisconnectPacket::new(VarIntLengthPrefixedString(response_string.to_string())));
+                            let reason = TextComponent::text("Please use a supported version!").color(Color::Red);
+                            let response = Packet::java_v1_7_DisconnectPacket(v1_7::DisconnectPacket::new(reason));
                             self.send_packet(address, response);
                         }
                     }
                     _ => {}
                 }
             }
+            Packet::java_legacy_LegacyPingPacket(_) => {
+                let kick = self.status(None).to_legacy_kick();
+                self.send_legacy_kick(address, &kick);
+            }
+            Packet::java_legacy_BetaPingPacket(_) => {
+                let kick = self.status(None).to_beta_kick();
+                self.send_legacy_kick(address, &kick);
+            }
             Packet::java_v1_7_RequestPacket(_) => { /* do nothing */ }
             Packet::java_v1_7_PingPacket(packet) => {
                 // You can send ResponsePackets here for an animated MOTD on 1.7 clients
@@ -152,87 +384,85 @@ impl Server {
                 self.send_packet(address, response);
             }
             Packet::bedrock_raknet_UnconnectedPingPacket(packet) => {
-                let response_string = "MCPE;phast test;282;1.6.0;1;2;9999;test2;Survival;";
+                let bedrock = Protocol::ProtocolBedrock_1_5(v1_5::ProtocolBedrock_1_5);
+                let response_string = self.status(Some(bedrock)).to_bedrock_motd(self.raknet_guid);
                 let response = Packet::bedrock_raknet_UnconnectedPongPacket(raknet::UnconnectedPongPacket::new(
-                    0,
-                    1234,
+                    packet.ping_time,
+                    self.raknet_guid,
                     RAKNET_MAGIC,
-                    ShortLengthPrefixedString(response_string.to_string()),
+                    ShortLengthPrefixedString(response_string),
                 ));
                 self.send_packet(address, response);
             }
             // Login
             Packet::java_v1_7_LoginStartPacket(packet) => {
                 println!("[Server] Player wants to login: {}", packet.name.0);
+                if self.is_full() {
+                    println!("[Server] Disconnecting {}: the server is full", packet.name.0);
+                    let reason = TextComponent::text("The server is full!");
+                    self.send_packet(address, Packet::java_v1_7_DisconnectPacket(v1_7::DisconnectPacket::new(reason)));
+                    self.disconnect(address);
+                    return;
+                }
 
-//                let response = Packet::EncryptionRequestPacket(v1_12::EncryptionRequestPacket::new(
-//                    VarIntLengthPrefixedString("".to_string()),
-//                    VarIntLengthPrefixedByteArray(vec![0x0Au8, 0x0Bu8, 0x0Cu8, 0x0Du8, 0x0Eu8, 0x0Fu8]),
-//                    VarIntLengthPrefixedByteArray(vec![0x0Au8, 0x0Bu8, 0x0Cu8, 0x0Du8]),
-//                ));
-//                self.send_packet(address, response);
-
-                let response = Packet::java_v1_7_LoginSuccessPacket(v1_7::LoginSuccessPacket::new(
-                    // includes hyphens
-                    VarIntLengthPrefixedString("e63a1d61-adf1-4d47-b5f8-43efc5c84908".to_string()),
-                    VarIntLengthPrefixedString(packet.name.0),
-                ));
-                self.send_packet(address, response);
+                if !self.config.online_mode {
+                    // like vanilla, offline servers don't ask for encryption since the client would try to join with Mojang first
+                    let profile = OfflineAuthenticator.authenticate(&packet.name.0, "").unwrap();
+                    println!("[Server] {} logged in offline as {} ({})", address, profile.name, profile.hyphenated_uuid());
+                    if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+                        connection.get().profile = Some(profile.clone());
+                    }
+                    self.finish_login(address, profile);
+                    return;
+                }
+
+                let verify_token = generate_verify_token();
                 if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
-                    connection.get().protocol_state = State::JavaPlay;
-                    println!("[Server] Set connection state to JavaPlay");
+                    let connection = connection.get();
+                    connection.username = Some(packet.name.0);
+                    connection.verify_token = verify_token.clone();
                 }
 
-                if let Some(protocol) = self.connection_manager.get_protocol(address) {
-                    match protocol {
-                        Protocol::ProtocolJava_1_7(_) => {
-                            let join_game = Packet::java_v1_7_JoinGamePacket(v1_7::JoinGamePacket::new(
-                                0, // entity id
-                                0, // survival
-                                0, // overworld
-                                1, // peaceful
-                                70, // legacy max player count
-                                VarIntLengthPrefixedString("default".to_string()),
-                            ));
-                            self.send_packet(address, join_game);
-                        }
-                        Protocol::ProtocolJava_1_8(_) => {
-                            let join_game = Packet::java_v1_8_JoinGamePacket(v1_8::JoinGamePacket::new(
-                                0, // entity id
-                                0, // survival
-                                0, // overworld
-                                1, // peaceful
-                                70, // legacy max player count
-                                VarIntLengthPrefixedString("default".to_string()),
-                                0, // debug
-                            ));
-                            self.send_packet(address, join_game);
-                        }
-                        Protocol::ProtocolJava_1_9(_)
-                        | Protocol::ProtocolJava_1_10(_)
-                        | Protocol::ProtocolJava_1_11(_)
-                        | Protocol::ProtocolJava_1_12(_)
-                        | Protocol::ProtocolJava_1_13(_)
-                        | Protocol::ProtocolJava_1_14(_) => {
-                            let join_game = Packet::java_v1_9_JoinGamePacket(v1_9::JoinGamePacket::new(
-                                0, // entity id
-                                0, // survival
-                                0, // overworld (i32)
-                                1, // peaceful
-                                70, // legacy max player count
-                                VarIntLengthPrefixedString("default".to_string()),
-                                0, // debug
-                            ));
-                            self.send_packet(address, join_game);
-                        }
-                        _ => {}
+                let public_key = self.server_key.public_key().to_vec();
+                let response = match self.connection_manager.get_protocol(address) {
+                    Some(Protocol::ProtocolJava_1_7(_)) => {
+                        // 1.7 uses shorts for the array lengths
+                        Packet::java_v1_7_EncryptionRequestPacket(v1_7::EncryptionRequestPacket::new(
+                            VarIntLengthPrefixedString("".to_string()),
+                            ShortLengthPrefixedByteArray(public_key),
+                            ShortLengthPrefixedByteArray(verify_token),
+                        ))
                     }
-                }
+                    _ => {
+                        Packet::java_v1_8_EncryptionRequestPacket(v1_8::EncryptionRequestPacket::new(
+                            VarIntLengthPrefixedString("".to_string()),
+                            VarIntLengthPrefixedByteArray(public_key),
+                            VarIntLengthPrefixedByteArray(verify_token),
+                        ))
+                    }
+                };
+                self.send_packet(address, response);
+            }
+            Packet::java_v1_7_EncryptionResponsePacket(packet) => {
+                self.handle_encryption_response(address, packet.shared_secret.0, packet.verify_token.0);
+            }
+            Packet::java_v1_8_EncryptionResponsePacket(packet) => {
+                self.handle_encryption_response(address, packet.shared_secret.0, packet.verify_token.0);
+            }
+            // Play
+            Packet::java_v1_7_KeepAlivePacket(packet) => {
+                self.handle_keep_alive(address, packet.id as i64);
+            }
+            Packet::java_v1_8_KeepAlivePacket(packet) => {
+                self.handle_keep_alive(address, packet.id.0 as i64);
+            }
+            Packet::java_v1_12_KeepAlivePacket(packet) => {
+                self.handle_keep_alive(address, packet.id as i64);
             }
             Packet::bedrock_raknet_OpenConnectionRequest1Packet(packet) => {
                 let response = Packet::bedrock_raknet_OpenConnectionReply1Packet(raknet::OpenConnectionReply1Packet::new(
                     RAKNET_MAGIC,
-                    1234u64,
+                    self.raknet_guid,
                     0u8,
                     800u16,
                 ));
@@ -243,7 +473,7 @@ impl Server {
 
                 let response = Packet::bedrock_raknet_OpenConnectionReply2Packet(raknet::OpenConnectionReply2Packet::new(
                     RAKNET_MAGIC,
-                    1234,
+                    self.raknet_guid,
                     Address(address),
                     packet.mtu_size,
                     0,
@@ -251,9 +481,14 @@ impl Server {
                 self.send_packet(address, response);
 
                 if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
-                    connection.get().protocol_state = State::BedrockRakNet;
+                    let connection = connection.get();
+                    connection.protocol_state = State::BedrockRakNet;
+                    connection.raknet.mtu_size = packet.mtu_size.max(MIN_MTU_SIZE).min(MAX_MTU_SIZE);
                 }
             }
+            Packet::bedrock_raknet_DisconnectNotificationPacket(_) => {
+                self.disconnect(address);
+            }
             Packet::bedrock_raknet_ConnectionRequestPacket(packet) => {
                 println!("{:#?}", packet);
                 let loopback = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 19132);
@@ -280,12 +515,303 @@ impl Server {
 
                 self.send_packet(address, response)
             }
+            Packet::bedrock_v1_5_LoginPacket(packet) => {
+                match protocol::get_protocol(ProtocolEdition::BedrockEdition, packet.protocol_version) {
+                    Some(_) if self.is_full() => {
+                        println!("[Bedrock] Disconnecting {}: the server is full", address);
+                        let status = v1_5::PlayStatusPacket::new(v1_5::LOGIN_FAILED_SERVER_FULL);
+                        self.send_packet(address, Packet::bedrock_v1_5_PlayStatusPacket(status));
+                        self.disconnect(address);
+                    }
+                    Some(protocol) => {
+                        let identity = match self.chain_verifier.verify(&packet.payload.0) {
+                            Some(identity) => identity,
+                            None => {
+                                println!("[Bedrock] {} sent an invalid login chain", address);
+                                let disconnect = v1_5::DisconnectPacket::new(false, VarIntLengthPrefixedString("disconnectionScreen.notAuthenticated".to_string()));
+                                self.send_packet(address, Packet::bedrock_v1_5_DisconnectPacket(disconnect));
+                                self.disconnect(address);
+                                return;
+                            }
+                        };
+                        println!("[Bedrock] {} logged in as {} with {}", address, identity.display_name, protocol.name());
+
+                        let authenticated = identity.authenticated;
+                        let client_key = identity.public_key.clone();
+                        if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+                            let connection = connection.get();
+                            connection.protocol = protocol;
+                            connection.profile = Some(identity.profile());
+                            connection.bedrock_identity = Some(identity);
+                        }
+
+                        if authenticated {
+                            // Xbox Live players won't continue without encryption
+                            self.start_bedrock_encryption(address, &client_key);
+                        } else {
+                            self.send_packet(address, Packet::bedrock_v1_5_PlayStatusPacket(v1_5::PlayStatusPacket::new(v1_5::LOGIN_SUCCESS)));
+                        }
+                    }
+                    None => {
+                        let status = if packet.protocol_version < v1_5::ProtocolBedrock_1_5_Id {
+                            v1_5::LOGIN_FAILED_CLIENT
+                        } else {
+                            v1_5::LOGIN_FAILED_SERVER
+                        };
+                        self.send_packet(address, Packet::bedrock_v1_5_PlayStatusPacket(v1_5::PlayStatusPacket::new(status)));
+                        self.disconnect(address);
+                    }
+                }
+            }
+            Packet::bedrock_v1_5_ClientToServerHandshakePacket(_) => {
+                // the client has enabled encryption
+                self.send_packet(address, Packet::bedrock_v1_5_PlayStatusPacket(v1_5::PlayStatusPacket::new(v1_5::LOGIN_SUCCESS)));
+            }
+            Packet::bedrock_v1_5_RequestChunkRadiusPacket(packet) => {
+                // the client can have less, but not more
+                let radius = packet.radius.0.max(1).min(self.config.view_distance as i32);
+                let response = v1_5::ChunkRadiusUpdatedPacket::new(ZigZagVarInt(radius));
+                self.send_packet(address, Packet::bedrock_v1_5_ChunkRadiusUpdatedPacket(response));
+            }
+            // there's no world to move around in or maps to show yet
+            Packet::bedrock_v1_5_MovePlayerPacket(_) | Packet::bedrock_v1_5_MapInfoRequestPacket(_) => {}
+            Packet::Unknown { id, state, bytes } => {
+                match self.config.unknown_packets {
+                    UnknownPacketPolicy::Skip => {}
+                    UnknownPacketPolicy::Log => {
+                        println!("[Server] Unknown packet {:X} in {:?} ({} bytes) from {}", id, state, bytes.len(), address);
+                    }
+                    UnknownPacketPolicy::Disconnect => {
+                        println!("[Server] Disconnecting {}: unknown packet {:X} in {:?}", address, id, state);
+                        self.disconnect(address);
+                    }
+                }
+            }
             _ => {
                 dbg!(packet);
             }
         }
     }
 
+    /// Sends the salt in a `ServerToClientHandshakePacket` & encrypts everything after it
+    fn start_bedrock_encryption(&mut self, address: SocketAddr, client_key: &str) {
+        let salt = generate_salt();
+        let key = match self.bedrock_key.derive_key(client_key, &salt) {
+            Some(key) => key,
+            None => {
+                println!("[Bedrock] {} sent an invalid public key", address);
+                self.disconnect(address);
+                return;
+            }
+        };
+
+        let token = self.bedrock_key.handshake_token(&salt);
+        self.send_packet(address, Packet::bedrock_v1_5_ServerToClientHandshakePacket(
+            v1_5::ServerToClientHandshakePacket::new(VarIntLengthPrefixedString(token))));
+
+        if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+            connection.get().enable_bedrock_encryption(&key);
+        }
+    }
+
+    /// Checks the verify token the client sent back & enables encryption on the connection
+    fn handle_encryption_response(&mut self, address: SocketAddr, shared_secret: Vec<u8>, verify_token: Vec<u8>) {
+        let shared_secret = self.server_key.decrypt(&shared_secret);
+        let verify_token = self.server_key.decrypt(&verify_token);
+
+        let username = match self.connection_manager.connections.find_mut(&address) {
+            Some(mut connection) => {
+                let connection = connection.get();
+                match (shared_secret, verify_token) {
+                    (Some(ref shared_secret), Some(ref verify_token))
+                    if !connection.verify_token.is_empty() && *verify_token == connection.verify_token => {
+                        connection.verify_token.clear();
+                        if connection.enable_encryption(shared_secret) {
+                            connection.shared_secret = shared_secret.clone();
+                            connection.username.clone()
+                        } else {
+                            None
+                        }
+                    }
+                    _ => None
+                }
+            }
+            None => return
+        };
+
+        match username {
+            Some(username) => self.authenticate(address, username),
+            None => {
+                println!("[Server] Encryption failed for {}", address);
+                let reason = TextComponent::text("Failed to verify encryption!").color(Color::Red);
+                let response = Packet::java_v1_7_DisconnectPacket(v1_7::DisconnectPacket::new(reason));
+                self.send_packet(address, response);
+                self.disconnect(address);
+            }
+        }
+    }
+
+    /// Queues the player for an auth thread since it may block on a web request.
+    /// The result is picked up by `handle_authentications`.
+    fn authenticate(&mut self, address: SocketAddr, username: String) {
+        let shared_secret = match self.connection_manager.connections.find_mut(&address) {
+            Some(mut connection) => connection.get().shared_secret.clone(),
+            None => return
+        };
+        let hash = server_hash("", &shared_secret, self.server_key.public_key());
+
+        if let Err(TrySendError::Full(_)) = self.auth_jobs.try_send((address, username, hash)) {
+            println!("[Server] Disconnecting {}: too many logins are waiting to be authenticated", address);
+            let reason = TextComponent::text("The server is busy, try again later").color(Color::Red);
+            self.send_packet(address, Packet::java_v1_7_DisconnectPacket(v1_7::DisconnectPacket::new(reason)));
+            self.disconnect(address);
+        }
+    }
+
+    /// Sends the packets that move a client from Login to Play
+    fn finish_login(&mut self, address: SocketAddr, profile: GameProfile) {
+        let protocol = self.connection_manager.get_protocol(address);
+        // compression was added in 1.8
+        let supports_compression = match protocol {
+            Some(Protocol::ProtocolJava_1_7(_)) | None => false,
+            _ => true
+        };
+        if self.compression_threshold >= 0 && supports_compression {
+            let threshold = self.compression_threshold;
+            let set_compression = Packet::java_v1_7_SetCompressionPacket(v1_7::SetCompressionPacket::new(VarInt(threshold)));
+            self.send_packet(address, set_compression);
+            if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+                connection.get().enable_compression(threshold as usize);
+            }
+        }
+
+        let response = Packet::java_v1_7_LoginSuccessPacket(v1_7::LoginSuccessPacket::new(
+            // includes hyphens
+            VarIntLengthPrefixedString(profile.hyphenated_uuid()),
+            VarIntLengthPrefixedString(profile.name),
+        ));
+        self.send_packet(address, response);
+        if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
+            connection.get().protocol_state = State::JavaPlay;
+            println!("[Server] Set connection state to JavaPlay");
+        }
+
+        let spawn = Position::new(0, 64, 0);
+        // the client doesn't use this anymore
+        let legacy_max_players = self.config.max_players.min(255) as u8;
+        if let Some(protocol) = protocol {
+            match protocol {
+                Protocol::ProtocolJava_1_7(_) => {
+                    let join_game = Packet::java_v1_7_JoinGamePacket(v1_7::JoinGamePacket::new(
+                        0, // entity id
+                        0, // survival
+                        0, // overworld
+                        1, // peaceful
+                        legacy_max_players,
+                        VarIntLengthPrefixedString("default".to_string()),
+                    ));
+                    self.send_packet(address, join_game);
+                    let spawn_position = v1_7::SpawnPositionPacket::new(spawn.x, spawn.y, spawn.z);
+                    self.send_packet(address, Packet::java_v1_7_SpawnPositionPacket(spawn_position));
+                }
+                Protocol::ProtocolJava_1_8(_) => {
+                    let join_game = Packet::java_v1_8_JoinGamePacket(v1_8::JoinGamePacket::new(
+                        0, // entity id
+                        0, // survival
+                        0, // overworld
+                        1, // peaceful
+                        legacy_max_players,
+                        VarIntLengthPrefixedString("default".to_string()),
+                        0, // debug
+                    ));
+                    self.send_packet(address, join_game);
+                    self.send_packet(address, Packet::java_v1_8_SpawnPositionPacket(v1_8::SpawnPositionPacket::new(spawn)));
+                }
+                Protocol::ProtocolJava_1_9(_)
+                | Protocol::ProtocolJava_1_10(_)
+                | Protocol::ProtocolJava_1_11(_)
+                | Protocol::ProtocolJava_1_12(_)
+                | Protocol::ProtocolJava_1_13(_) => {
+                    let join_game = Packet::java_v1_9_JoinGamePacket(v1_9::JoinGamePacket::new(
+                        0, // entity id
+                        0, // survival
+                        0, // overworld (i32)
+                        1, // peaceful
+                        legacy_max_players,
+                        VarIntLengthPrefixedString("default".to_string()),
+                        0, // debug
+                    ));
+                    self.send_packet(address, join_game);
+                    self.send_packet(address, Packet::java_v1_8_SpawnPositionPacket(v1_8::SpawnPositionPacket::new(spawn)));
+                }
+                Protocol::ProtocolJava_1_14(_) => {
+                    let join_game = Packet::java_v1_14_JoinGamePacket(v1_14::JoinGamePacket::new(
+                        0, // entity id
+                        0, // survival
+                        0, // overworld
+                        legacy_max_players,
+                        VarIntLengthPrefixedString("default".to_string()),
+                        VarInt(self.config.view_distance as i32),
+                        0, // debug
+                    ));
+                    self.send_packet(address, join_game);
+                    let spawn_position = v1_14::SpawnPositionPacket::new(Position1_14(spawn));
+                    self.send_packet(address, Packet::java_v1_14_SpawnPositionPacket(spawn_position));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Profiles of every logged in player, from both editions
+    fn online_players(&self) -> Vec<GameProfile> {
+        let mut players = Vec::new();
+        for (_, connection) in self.connection_manager.connections.iter() {
+            let playing = connection.protocol_state == State::JavaPlay || connection.protocol_state == State::BedrockMinecraft;
+            if let (true, Some(ref profile)) = (playing, &connection.profile) {
+                players.push(profile.clone());
+            }
+        }
+        players
+    }
+
+    /// Whether another player would go over `max_players`
+    fn is_full(&self) -> bool {
+        self.online_players().len() >= self.config.max_players as usize
+    }
+
+    /// Builds the server list status for a client using `protocol`
+    fn status(&mut self, protocol: Option<Protocol>) -> ServerStatus {
+        let players = self.online_players();
+        let online = players.len() as u32;
+        let sample = players.iter().take(MAX_SAMPLE_SIZE).map(|profile| StatusPlayer {
+            name: profile.name.clone(),
+            id: profile.hyphenated_uuid(),
+        }).collect();
+
+        let version = match protocol {
+            Some(protocol) => StatusVersion {
+                name: protocol.name().to_string(),
+                protocol: protocol.protocol_version(),
+            },
+            None => StatusVersion {
+                name: SUPPORTED_VERSIONS.to_string(),
+                protocol: -1,
+            }
+        };
+
+        ServerStatus {
+            version,
+            players: StatusPlayers {
+                max: self.config.max_players,
+                online,
+                sample,
+            },
+            description: TextComponent::from_legacy(&self.config.motd),
+            favicon: self.favicon.get(),
+        }
+    }
+
     fn send_packet(&self, address: SocketAddr, packet: Packet) {
         println!("[Server] Sending {} to {}", packet.name(), address);
         if let Some(mut connection) = self.connection_manager.connections.find_mut(&address) {
@@ -0,0 +1,168 @@
+use std::fs;
+use std::time::SystemTime;
+
+use network::chat::{TextComponent, HEX_COLOR_PROTOCOL_VERSION};
+
+/// Shown as the version when a client's protocol isn't supported
+pub const SUPPORTED_VERSIONS: &str = "1.7-1.14";
+/// Vanilla servers don't send more players than this in the sample
+pub const MAX_SAMPLE_SIZE: usize = 12;
+/// Sent to legacy clients so they show the server as incompatible, same as the vanilla server
+pub const LEGACY_PROTOCOL_VERSION: i32 = 127;
+/// Same as the vanilla server, relative to the working directory
+pub const FAVICON_PATH: &str = "server-icon.png";
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const FAVICON_SIZE: u32 = 64;
+
+/// What the server list shows.
+/// Java clients get this as JSON in the `ResponsePacket`, Bedrock clients as a string in the `UnconnectedPongPacket`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ServerStatus {
+    pub version: StatusVersion,
+    pub players: StatusPlayers,
+    /// Added by `to_json`, since it depends on the client's protocol version
+    #[serde(skip_serializing)]
+    pub description: TextComponent,
+    /// `data:image/png;base64,...`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StatusVersion {
+    pub name: String,
+    /// Clients show the version name in red if this isn't their protocol version
+    pub protocol: i32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StatusPlayers {
+    pub max: u32,
+    pub online: u32,
+    pub sample: Vec<StatusPlayer>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StatusPlayer {
+    pub name: String,
+    /// With hyphens
+    pub id: String,
+}
+
+impl ServerStatus {
+    /// JSON for a client using `protocol_version`, which can be newer than any supported version
+    pub fn to_json(&self, protocol_version: i32) -> String {
+        // there's nothing in here that can fail to serialize
+        let mut json = serde_json::to_value(self).unwrap();
+        json["description"] = self.description.to_value(protocol_version >= HEX_COLOR_PROTOCOL_VERSION);
+        json.to_string()
+    }
+
+    /// The string in the `UnconnectedPongPacket`:
+    /// `MCPE;motd;protocol;version;online;max;guid;second motd line;game mode;`
+    pub fn to_bedrock_motd(&self, guid: u64) -> String {
+        // the fields are separated with semicolons & the client only shows one line for each motd
+        let clean = |s: &str| s.replace(';', "");
+        let description = self.description.to_legacy();
+        let mut lines = description.lines();
+        let motd = lines.next().unwrap_or("");
+        let sub_motd = lines.next().unwrap_or("phast");
+
+        format!("MCPE;{};{};{};{};{};{};{};Survival;",
+            clean(motd),
+            self.version.protocol,
+            clean(&self.version.name),
+            self.players.online,
+            self.players.max,
+            guid,
+            clean(sub_motd))
+    }
+
+    /// The kick reason sent to 1.4 - 1.6 clients: `§1\0protocol\0version\0motd\0online\0max`
+    pub fn to_legacy_kick(&self) -> String {
+        // null characters separate the fields & only one line is shown
+        let clean = |s: &str| s.replace('\0', "").replace('\n', " ");
+        format!("§1\0{}\0{}\0{}\0{}\0{}",
+            LEGACY_PROTOCOL_VERSION,
+            clean(&self.version.name),
+            clean(&self.description.to_legacy()),
+            self.players.online,
+            self.players.max)
+    }
+
+    /// The kick reason sent to Beta 1.8 - 1.3 clients: `motd§online§max`
+    pub fn to_beta_kick(&self) -> String {
+        // § separates the fields, so there can't be any formatting codes
+        let description = self.description.to_plain().replace('§', "");
+        let motd = description.lines().next().unwrap_or("");
+        format!("{}§{}§{}", motd, self.players.online, self.players.max)
+    }
+}
+
+/// The icon shown next to the server in the Java server list.
+/// It's loaded again whenever the file is modified.
+pub struct Favicon {
+    path: String,
+    modified: Option<SystemTime>,
+    /// `data:image/png;base64,...`
+    data: Option<String>,
+}
+
+impl Favicon {
+    pub fn new(path: &str) -> Favicon {
+        let mut favicon = Favicon {
+            path: path.to_string(),
+            modified: None,
+            data: None,
+        };
+        favicon.reload_if_modified();
+        favicon
+    }
+
+    /// The icon as a data url, or `None` if there isn't a valid icon
+    pub fn get(&mut self) -> Option<String> {
+        self.reload_if_modified();
+        self.data.clone()
+    }
+
+    fn reload_if_modified(&mut self) {
+        let modified = fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok();
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        self.data = match modified {
+            Some(_) => load_favicon(&self.path),
+            // the icon was removed
+            None => None
+        };
+    }
+}
+
+/// Reads a PNG & encodes it as a data url.
+/// Returns `None` if the file can't be read or isn't a 64x64 PNG.
+fn load_favicon(path: &str) -> Option<String> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            println!("[Server] Failed to read {}: {}", path, e);
+            return None;
+        }
+    };
+
+    // the IHDR chunk always comes right after the signature
+    if bytes.len() < 24 || bytes[0..8] != PNG_SIGNATURE || &bytes[12..16] != b"IHDR" {
+        println!("[Server] {} isn't a PNG", path);
+        return None;
+    }
+    let width = u32::from_be_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]);
+    let height = u32::from_be_bytes([bytes[20], bytes[21], bytes[22], bytes[23]]);
+    if width != FAVICON_SIZE || height != FAVICON_SIZE {
+        println!("[Server] {} must be {}x{} but it's {}x{}", path, FAVICON_SIZE, FAVICON_SIZE, width, height);
+        return None;
+    }
+
+    println!("[Server] Loaded {}", path);
+    Some(format!("data:image/png;base64,{}", base64::encode(&bytes)))
+}